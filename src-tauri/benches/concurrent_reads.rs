@@ -0,0 +1,82 @@
+//! Compares the throughput of concurrent commands sharing the database client with commands
+//! that lock it (as they did before the client was shared).
+//!
+//! Run with `cargo bench --bench concurrent_reads`.
+
+use std::{future::Future, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use spots_lib::bench_support::BenchDb;
+use tauri::async_runtime::{self, Mutex};
+use uuid::Uuid;
+
+const TRACKS: usize = 2_000;
+const TASKS: usize = 8;
+const READS_PER_TASK: usize = 500;
+
+/// Runs [TASKS] tasks that each get [READS_PER_TASK] tracks.
+async fn run_reads<F, Fut>(track_ids: Arc<Vec<Uuid>>, read: F)
+where
+    F: Fn(Uuid) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let tasks: Vec<_> = (0..TASKS)
+        .map(|task| {
+            let (track_ids, read) = (track_ids.clone(), read.clone());
+            async_runtime::spawn(async move {
+                for i in 0..READS_PER_TASK {
+                    read(track_ids[(task * READS_PER_TASK + i) % track_ids.len()]).await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+fn concurrent_reads(c: &mut Criterion) {
+    let path = std::env::temp_dir().join(format!("spots-bench-{}.sqlite", Uuid::new_v4()));
+    let (db, track_ids) = async_runtime::block_on(async {
+        let db = BenchDb::open(&path).await.unwrap();
+        let track_ids = db.insert_tracks(TRACKS).await.unwrap();
+        (db, Arc::new(track_ids))
+    });
+
+    let mut group = c.benchmark_group("concurrent_reads");
+    group.throughput(Throughput::Elements((TASKS * READS_PER_TASK) as u64));
+
+    let locked = Arc::new(Mutex::new(db.clone()));
+    group.bench_function("locked", |b| {
+        b.iter(|| {
+            let locked = locked.clone();
+            async_runtime::block_on(run_reads(track_ids.clone(), move |track_id| {
+                let locked = locked.clone();
+                async move {
+                    let db = locked.lock().await;
+                    db.get_track(track_id).await.unwrap();
+                }
+            }))
+        })
+    });
+
+    group.bench_function("shared", |b| {
+        b.iter(|| {
+            let db = db.clone();
+            async_runtime::block_on(run_reads(track_ids.clone(), move |track_id| {
+                let db = db.clone();
+                async move {
+                    db.get_track(track_id).await.unwrap();
+                }
+            }))
+        })
+    });
+    group.finish();
+
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.to_string_lossy()));
+    }
+}
+
+criterion_group!(benches, concurrent_reads);
+criterion_main!(benches);
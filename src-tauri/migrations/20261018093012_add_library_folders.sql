@@ -0,0 +1,10 @@
+-- Library Folders Table
+CREATE TABLE library_folders (
+    path TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT REFERENCES users(id) ON DELETE CASCADE,
+    added_at TEXT NOT NULL
+);
+
+
+-- Flags tracks whose files can no longer be found on disk
+ALTER TABLE tracks ADD COLUMN is_missing INTEGER NOT NULL DEFAULT 0;
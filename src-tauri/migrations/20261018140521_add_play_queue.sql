@@ -0,0 +1,21 @@
+-- Play Queue Table
+CREATE TABLE play_queue (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    track_id TEXT NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    original_position INTEGER NOT NULL
+);
+
+
+-- Play Queue State Table
+CREATE TABLE play_queue_state (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    current_entry_id TEXT REFERENCES play_queue(id) ON DELETE SET NULL,
+    position_ms INTEGER NOT NULL DEFAULT 0,
+    repeat_mode TEXT NOT NULL DEFAULT 'off',
+    is_shuffled INTEGER NOT NULL DEFAULT 0
+);
+
+
+CREATE INDEX idx_play_queue_user ON play_queue(user_id, position);
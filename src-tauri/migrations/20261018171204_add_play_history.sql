@@ -0,0 +1,30 @@
+-- Play History Table
+CREATE TABLE play_history (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    track_id TEXT NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+    started_at TEXT NOT NULL,
+    ms_played INTEGER NOT NULL,
+    is_completed INTEGER NOT NULL DEFAULT 0
+);
+
+
+-- Track Play Counts (Per User) Table
+CREATE TABLE track_play_counts (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    track_id TEXT NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+    play_count INTEGER NOT NULL DEFAULT 0,
+    last_played_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, track_id)
+);
+
+
+-- Play Thresholds Table
+CREATE TABLE play_thresholds (
+    user_id TEXT PRIMARY KEY NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    min_fraction REAL NOT NULL DEFAULT 0.5,
+    max_ms INTEGER NOT NULL DEFAULT 240000
+);
+
+
+CREATE INDEX idx_play_history_user ON play_history(user_id, started_at);
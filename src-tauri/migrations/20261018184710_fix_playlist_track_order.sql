@@ -0,0 +1,24 @@
+-- Playlist Tracks (Ordered) Table
+-- `track_order` was unique across all playlists, so it's rebuilt to be unique per playlist
+CREATE TABLE playlist_tracks_new (
+    playlist_id TEXT NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    track_id TEXT NOT NULL REFERENCES tracks(id) ON DELETE CASCADE,
+    track_order INTEGER NOT NULL,
+    PRIMARY KEY (playlist_id, track_id),
+    UNIQUE (playlist_id, track_order)
+);
+
+
+INSERT INTO playlist_tracks_new (playlist_id, track_id, track_order)
+SELECT
+    playlist_id,
+    track_id,
+    ROW_NUMBER() OVER (PARTITION BY playlist_id ORDER BY track_order) - 1
+FROM playlist_tracks
+WHERE playlist_id IS NOT NULL AND track_id IS NOT NULL;
+
+
+DROP TABLE playlist_tracks;
+
+
+ALTER TABLE playlist_tracks_new RENAME TO playlist_tracks;
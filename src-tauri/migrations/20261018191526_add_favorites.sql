@@ -0,0 +1,29 @@
+-- Records when tracks were favorited (unknown for tracks favorited before this migration)
+ALTER TABLE favorited_tracks ADD COLUMN favorited_at TEXT;
+
+
+-- Favorited Albums Table
+CREATE TABLE favorited_albums (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    album_id TEXT NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+    favorited_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, album_id)
+);
+
+
+-- Favorited Artists Table
+CREATE TABLE favorited_artists (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    artist_id TEXT NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+    favorited_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, artist_id)
+);
+
+
+-- Followed Playlists Table
+CREATE TABLE followed_playlists (
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    playlist_id TEXT NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    favorited_at TEXT NOT NULL,
+    PRIMARY KEY (user_id, playlist_id)
+);
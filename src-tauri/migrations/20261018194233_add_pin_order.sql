@@ -0,0 +1,14 @@
+-- Keeps track of the order of the user's pinned playlists
+ALTER TABLE pinned_playlists ADD COLUMN pin_order INTEGER NOT NULL DEFAULT 0;
+
+
+-- Number the playlists that are already pinned in the order they were pinned
+UPDATE pinned_playlists
+SET pin_order = (
+    SELECT COUNT(*)
+    FROM pinned_playlists pp
+    WHERE pp.user_id = pinned_playlists.user_id AND pp.rowid < pinned_playlists.rowid
+);
+
+
+CREATE UNIQUE INDEX idx_pinned_playlists_order ON pinned_playlists(user_id, pin_order);
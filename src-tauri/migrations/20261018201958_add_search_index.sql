@@ -0,0 +1,118 @@
+-- Search Index (Full-Text) Table
+-- Diacritics are removed when tokenizing, so "beyonce" matches "Beyoncé"
+CREATE VIRTUAL TABLE search_index USING fts5(
+    kind UNINDEXED,
+    item_id UNINDEXED,
+    owner_id UNINDEXED,
+    name,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+
+-- Index the items that already exist
+INSERT INTO search_index (kind, item_id, owner_id, name)
+SELECT 'track', id, NULL, title FROM tracks;
+
+
+INSERT INTO search_index (kind, item_id, owner_id, name)
+SELECT 'album', id, NULL, title FROM albums;
+
+
+INSERT INTO search_index (kind, item_id, owner_id, name)
+SELECT 'artist', id, NULL, name FROM artists;
+
+
+INSERT INTO search_index (kind, item_id, owner_id, name)
+SELECT 'playlist', id, user_id, title FROM playlists;
+
+
+INSERT INTO search_index (kind, item_id, owner_id, name)
+SELECT 'genre', name, NULL, name FROM genres;
+
+
+-- Keep the index in sync with the tracks
+CREATE TRIGGER tracks_search_insert AFTER INSERT ON tracks BEGIN
+    INSERT INTO search_index (kind, item_id, owner_id, name)
+    VALUES ('track', new.id, NULL, new.title);
+END;
+
+
+CREATE TRIGGER tracks_search_update AFTER UPDATE OF title ON tracks BEGIN
+    UPDATE search_index SET name = new.title WHERE kind = 'track' AND item_id = new.id;
+END;
+
+
+CREATE TRIGGER tracks_search_delete AFTER DELETE ON tracks BEGIN
+    DELETE FROM search_index WHERE kind = 'track' AND item_id = old.id;
+END;
+
+
+-- Keep the index in sync with the albums
+CREATE TRIGGER albums_search_insert AFTER INSERT ON albums BEGIN
+    INSERT INTO search_index (kind, item_id, owner_id, name)
+    VALUES ('album', new.id, NULL, new.title);
+END;
+
+
+CREATE TRIGGER albums_search_update AFTER UPDATE OF title ON albums BEGIN
+    UPDATE search_index SET name = new.title WHERE kind = 'album' AND item_id = new.id;
+END;
+
+
+CREATE TRIGGER albums_search_delete AFTER DELETE ON albums BEGIN
+    DELETE FROM search_index WHERE kind = 'album' AND item_id = old.id;
+END;
+
+
+-- Keep the index in sync with the artists
+CREATE TRIGGER artists_search_insert AFTER INSERT ON artists BEGIN
+    INSERT INTO search_index (kind, item_id, owner_id, name)
+    VALUES ('artist', new.id, NULL, new.name);
+END;
+
+
+CREATE TRIGGER artists_search_update AFTER UPDATE OF name ON artists BEGIN
+    UPDATE search_index SET name = new.name WHERE kind = 'artist' AND item_id = new.id;
+END;
+
+
+CREATE TRIGGER artists_search_delete AFTER DELETE ON artists BEGIN
+    DELETE FROM search_index WHERE kind = 'artist' AND item_id = old.id;
+END;
+
+
+-- Keep the index in sync with the playlists
+CREATE TRIGGER playlists_search_insert AFTER INSERT ON playlists BEGIN
+    INSERT INTO search_index (kind, item_id, owner_id, name)
+    VALUES ('playlist', new.id, new.user_id, new.title);
+END;
+
+
+CREATE TRIGGER playlists_search_update AFTER UPDATE OF title, user_id ON playlists BEGIN
+    UPDATE search_index
+    SET name = new.title, owner_id = new.user_id
+    WHERE kind = 'playlist' AND item_id = new.id;
+END;
+
+
+CREATE TRIGGER playlists_search_delete AFTER DELETE ON playlists BEGIN
+    DELETE FROM search_index WHERE kind = 'playlist' AND item_id = old.id;
+END;
+
+
+-- Keep the index in sync with the genres
+CREATE TRIGGER genres_search_insert AFTER INSERT ON genres BEGIN
+    INSERT INTO search_index (kind, item_id, owner_id, name)
+    VALUES ('genre', new.name, NULL, new.name);
+END;
+
+
+CREATE TRIGGER genres_search_update AFTER UPDATE OF name ON genres BEGIN
+    UPDATE search_index SET item_id = new.name, name = new.name
+    WHERE kind = 'genre' AND item_id = old.name;
+END;
+
+
+CREATE TRIGGER genres_search_delete AFTER DELETE ON genres BEGIN
+    DELETE FROM search_index WHERE kind = 'genre' AND item_id = old.name;
+END;
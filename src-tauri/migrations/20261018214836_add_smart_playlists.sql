@@ -0,0 +1,5 @@
+-- Smart Playlists Table
+CREATE TABLE smart_playlists (
+    playlist_id TEXT PRIMARY KEY NOT NULL REFERENCES playlists(id) ON DELETE CASCADE,
+    rules TEXT NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Identifies tracks by their contents (computed when first needed, and cleared when the file changes)
+ALTER TABLE tracks ADD COLUMN content_hash TEXT;
+
+
+CREATE INDEX idx_tracks_content_hash ON tracks(content_hash);
@@ -0,0 +1,10 @@
+-- Sessions Table (one for each auth token, so tokens can be revoked before they expire)
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+
+CREATE INDEX idx_sessions_user ON sessions(user_id);
@@ -0,0 +1,5 @@
+-- Albums are told apart by their artist, so albums with the same title (e.g. "Greatest Hits") aren't merged
+ALTER TABLE albums ADD COLUMN artist TEXT;
+
+
+CREATE INDEX idx_albums_title_artist ON albums(title, artist);
@@ -0,0 +1,145 @@
+-- Sort keys for paging through the lists in order using an index
+-- The keys that depend on other tables are stored, and kept up to date by triggers
+
+
+-- The album (title, then track number) and first artist of each track
+ALTER TABLE tracks ADD COLUMN album_sort_key TEXT NOT NULL DEFAULT '' COLLATE NOCASE;
+ALTER TABLE tracks ADD COLUMN artist_sort_key TEXT NOT NULL DEFAULT '' COLLATE NOCASE;
+
+
+UPDATE tracks
+SET album_sort_key = COALESCE((SELECT al.title FROM albums al WHERE al.id = tracks.album_id), '')
+        || char(31) || printf('%05d', COALESCE(track_number, 0)),
+    artist_sort_key = COALESCE((
+        SELECT MIN(ar.name COLLATE NOCASE)
+        FROM track_artists ta
+        JOIN artists ar ON ar.id = ta.artist_id
+        WHERE ta.track_id = tracks.id
+    ), '');
+
+
+CREATE TRIGGER tracks_album_sort_key_insert AFTER INSERT ON tracks BEGIN
+    UPDATE tracks
+    SET album_sort_key = COALESCE((SELECT al.title FROM albums al WHERE al.id = new.album_id), '')
+        || char(31) || printf('%05d', COALESCE(new.track_number, 0))
+    WHERE id = new.id;
+END;
+
+
+CREATE TRIGGER tracks_album_sort_key_update AFTER UPDATE OF album_id, track_number ON tracks BEGIN
+    UPDATE tracks
+    SET album_sort_key = COALESCE((SELECT al.title FROM albums al WHERE al.id = new.album_id), '')
+        || char(31) || printf('%05d', COALESCE(new.track_number, 0))
+    WHERE id = new.id;
+END;
+
+
+CREATE TRIGGER albums_sort_key_update AFTER UPDATE OF title ON albums BEGIN
+    UPDATE tracks
+    SET album_sort_key = new.title || char(31) || printf('%05d', COALESCE(track_number, 0))
+    WHERE album_id = new.id;
+END;
+
+
+CREATE TRIGGER track_artists_sort_key_insert AFTER INSERT ON track_artists BEGIN
+    UPDATE tracks
+    SET artist_sort_key = COALESCE((
+        SELECT MIN(ar.name COLLATE NOCASE)
+        FROM track_artists ta
+        JOIN artists ar ON ar.id = ta.artist_id
+        WHERE ta.track_id = new.track_id
+    ), '')
+    WHERE id = new.track_id;
+END;
+
+
+CREATE TRIGGER track_artists_sort_key_delete AFTER DELETE ON track_artists BEGIN
+    UPDATE tracks
+    SET artist_sort_key = COALESCE((
+        SELECT MIN(ar.name COLLATE NOCASE)
+        FROM track_artists ta
+        JOIN artists ar ON ar.id = ta.artist_id
+        WHERE ta.track_id = old.track_id
+    ), '')
+    WHERE id = old.track_id;
+END;
+
+
+CREATE TRIGGER artists_sort_key_update AFTER UPDATE OF name ON artists BEGIN
+    UPDATE tracks
+    SET artist_sort_key = COALESCE((
+        SELECT MIN(ar.name COLLATE NOCASE)
+        FROM track_artists ta
+        JOIN artists ar ON ar.id = ta.artist_id
+        WHERE ta.track_id = tracks.id
+    ), '')
+    WHERE id IN (SELECT track_id FROM track_artists WHERE artist_id = new.id);
+END;
+
+
+-- The latest release year, when the first track was added and when a track was last played for each album
+ALTER TABLE albums ADD COLUMN release_year INTEGER;
+ALTER TABLE albums ADD COLUMN added_at TEXT;
+ALTER TABLE albums ADD COLUMN last_played_at TEXT;
+
+
+CREATE INDEX idx_tracks_album ON tracks(album_id);
+
+
+UPDATE albums
+SET release_year = (SELECT MAX(t.release_year) FROM tracks t WHERE t.album_id = albums.id),
+    added_at = (SELECT MIN(t.created_at) FROM tracks t WHERE t.album_id = albums.id),
+    last_played_at = (SELECT MAX(t.last_played_at) FROM tracks t WHERE t.album_id = albums.id);
+
+
+CREATE TRIGGER tracks_album_keys_insert AFTER INSERT ON tracks BEGIN
+    UPDATE albums
+    SET release_year = (SELECT MAX(t.release_year) FROM tracks t WHERE t.album_id = albums.id),
+        added_at = (SELECT MIN(t.created_at) FROM tracks t WHERE t.album_id = albums.id),
+        last_played_at = (SELECT MAX(t.last_played_at) FROM tracks t WHERE t.album_id = albums.id)
+    WHERE id = new.album_id;
+END;
+
+
+CREATE TRIGGER tracks_album_keys_update
+AFTER UPDATE OF album_id, release_year, created_at, last_played_at ON tracks BEGIN
+    UPDATE albums
+    SET release_year = (SELECT MAX(t.release_year) FROM tracks t WHERE t.album_id = albums.id),
+        added_at = (SELECT MIN(t.created_at) FROM tracks t WHERE t.album_id = albums.id),
+        last_played_at = (SELECT MAX(t.last_played_at) FROM tracks t WHERE t.album_id = albums.id)
+    WHERE id IN (old.album_id, new.album_id);
+END;
+
+
+CREATE TRIGGER tracks_album_keys_delete AFTER DELETE ON tracks BEGIN
+    UPDATE albums
+    SET release_year = (SELECT MAX(t.release_year) FROM tracks t WHERE t.album_id = albums.id),
+        added_at = (SELECT MIN(t.created_at) FROM tracks t WHERE t.album_id = albums.id),
+        last_played_at = (SELECT MAX(t.last_played_at) FROM tracks t WHERE t.album_id = albums.id)
+    WHERE id = old.album_id;
+END;
+
+
+-- Each index matches a sort key of the list queries, with ties broken by ID
+CREATE INDEX idx_tracks_title_sort ON tracks(title COLLATE NOCASE, id);
+CREATE INDEX idx_tracks_album_sort ON tracks(album_sort_key, id);
+CREATE INDEX idx_tracks_artist_sort ON tracks(artist_sort_key, id);
+CREATE INDEX idx_tracks_year_sort ON tracks(COALESCE(release_year, 0), id);
+CREATE INDEX idx_tracks_added_sort ON tracks(created_at, id);
+CREATE INDEX idx_tracks_last_played_sort ON tracks(COALESCE(last_played_at, ''), id);
+
+
+CREATE INDEX idx_albums_title_sort ON albums(title COLLATE NOCASE, id);
+CREATE INDEX idx_albums_artist_sort ON albums(COALESCE(artist, '') COLLATE NOCASE, id);
+CREATE INDEX idx_albums_year_sort ON albums(COALESCE(release_year, 0), id);
+CREATE INDEX idx_albums_added_sort ON albums(COALESCE(added_at, ''), id);
+CREATE INDEX idx_albums_last_played_sort ON albums(COALESCE(last_played_at, ''), id);
+
+
+CREATE INDEX idx_playlists_title_sort ON playlists(user_id, title COLLATE NOCASE, id);
+CREATE INDEX idx_playlists_added_sort ON playlists(user_id, created_at, id);
+CREATE INDEX idx_playlists_last_played_sort ON playlists(user_id, COALESCE(last_played_at, ''), id);
+
+
+CREATE INDEX idx_favorited_tracks_added_sort
+ON favorited_tracks(user_id, COALESCE(favorited_at, ''), track_id);
@@ -0,0 +1,4 @@
+-- The size and modification time of each track's file when it was last imported,
+-- so its content hash is only cleared when the file changed
+ALTER TABLE tracks ADD COLUMN file_size INTEGER;
+ALTER TABLE tracks ADD COLUMN file_modified_at TEXT;
@@ -1,3 +1,4 @@
+use chrono::{Duration, Utc};
 use tauri::State;
 use validator::Validate;
 
@@ -6,11 +7,11 @@ use crate::{
         dtos::{FilterUserDto, LoginUserDto, LoginUserResponseDto, RegisterUserDto},
         utils::{
             password::{compare_password, hash_password},
-            token::Token,
+            token::{verify_token, Token},
             ApiResponse, ApiResult,
         },
     },
-    database::users::UserExt,
+    database::{sessions::SessionExt, users::UserExt},
     errors::SpotsError,
     AppState,
 };
@@ -26,7 +27,7 @@ pub async fn register_user(state: State<'_, AppState>, user: RegisterUserDto) ->
     let hashed_password = hash_password(user.password)?;
 
     // Create new user in DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     let create_user_result = db.create_user(user.username, hashed_password).await;
 
     Ok(create_user_result.map(|_| ApiResponse::success(()))?)
@@ -42,7 +43,7 @@ pub async fn login_user(
     user.validate()?;
 
     // Get user from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     let existing_user = db
         .get_user(None, Some(&user.username))
         .await?
@@ -55,8 +56,12 @@ pub async fn login_user(
     if password_match {
         let config = state.api_config.lock().await.clone();
 
+        // Start a session for the auth token, so it can be revoked
+        let expires_at = Utc::now().naive_local() + Duration::minutes(config.token_maxage_mins);
+        let session_id = db.create_session(existing_user.id, expires_at).await?;
+
         // Create auth token
-        let token = Token::try_new(config, &existing_user.id.to_string())?;
+        let token = Token::try_new(config, &existing_user.id.to_string(), session_id)?;
 
         // Create Response
         Ok(ApiResponse::success(LoginUserResponseDto {
@@ -67,3 +72,30 @@ pub async fn login_user(
         Err(SpotsError::InvalidLoginCredentials)
     }
 }
+
+/// Logs out the authenticated user, revoking the auth token.
+#[tauri::command]
+pub async fn logout_user(state: State<'_, AppState>, auth_token: String) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.delete_session(token.get_session_id())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Logs out the authenticated user on all devices, revoking all of their auth tokens (including
+/// this one).
+///
+/// Returns the number of sessions that were ended.
+#[tauri::command]
+pub async fn logout_all_devices(state: State<'_, AppState>, auth_token: String) -> ApiResult<u64> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.delete_user_sessions(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
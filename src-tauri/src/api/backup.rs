@@ -0,0 +1,88 @@
+use tauri::{AppHandle, State};
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult},
+    database::{
+        backup::{backups_dir, get_backups, stage_restore, Backup, BackupExt},
+        client::database_path,
+    },
+    errors::SpotsError,
+    AppState,
+};
+
+/// Backs up the database while the app keeps running.
+///
+/// Manual backups are kept until they are deleted, unlike the automatic ones taken when the
+/// app starts.
+#[tauri::command]
+pub async fn create_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Backup> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.backup(&backups_dir(&app)?, false)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the backups of the database (newest first).
+#[tauri::command]
+pub async fn get_database_backups(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Vec<Backup>> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    get_backups(&backups_dir(&app)?).map(ApiResponse::success)
+}
+
+/// Restores the backup with the file name, replacing everything in the database.
+///
+/// The backup is checked before anything is replaced: it has to be intact and can't be from a
+/// newer version of the app.
+///
+/// # Note
+/// The app restarts to restore the backup, since the database can't be replaced while it is
+/// open.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auth_token: String,
+    file_name: String,
+) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    // Only backups in the backups dir can be restored
+    let backups_dir = backups_dir(&app)?;
+    if !get_backups(&backups_dir)?
+        .iter()
+        .any(|backup| backup.file_name == file_name)
+    {
+        return Err(SpotsError::BackupNotFound(file_name));
+    }
+
+    stage_restore(&database_path(&app)?, &backups_dir.join(&file_name)).await?;
+    app.restart()
+}
+
+/// Checks the database for corruption, returning the problems that were found (none if the
+/// database is intact).
+#[tauri::command]
+pub async fn check_database_integrity(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Vec<String>> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.integrity_check().await.map(ApiResponse::success)
+}
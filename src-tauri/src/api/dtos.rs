@@ -1,5 +1,5 @@
 use chrono::NaiveDateTime;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use validator::Validate;
 
 use crate::database::models::User;
@@ -48,6 +48,22 @@ pub struct LoginUserDto {
     pub password: String,
 }
 
+/// The DTO used to create or rename a playlist.
+#[derive(Debug, Clone, Validate, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistTitleDto {
+    /// The title (trimmed, so it is validated as it is stored).
+    #[serde(deserialize_with = "deserialize_trimmed")]
+    #[validate(
+        length(min = 1, message = "The playlist title is required"),
+        length(
+            max = 100,
+            message = "The playlist title must be less than 100 characters"
+        )
+    )]
+    pub title: String,
+}
+
 /// The DTO returned after loggin in a user.
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -87,3 +103,30 @@ fn validate_username(username: &str) -> Result<(), validator::ValidationError> {
     }
     Ok(())
 }
+
+/// Deserializes a string without its leading and trailing whitespace.
+fn deserialize_trimmed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Ok(value.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_playlist_title_is_trimmed() {
+        let dto = |title: &str| -> PlaylistTitleDto {
+            serde_json::from_value(json!({ "title": title })).unwrap()
+        };
+        let playlist = dto("  Road Trip ");
+        assert_eq!(playlist.title, "Road Trip");
+        assert!(playlist.validate().is_ok());
+
+        assert!(dto("   ").validate().is_err());
+        assert!(dto(&format!(" {} ", "a".repeat(100))).validate().is_ok());
+        assert!(dto(&"a".repeat(101)).validate().is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult},
+    database::{
+        favorites::{FavoriteExt, FavoriteKind},
+        models::music_library::{Album, Artist, Playlist},
+    },
+    AppState,
+};
+
+/// Favorites the track, or unfavorites it if it was already favorited.
+///
+/// Returns whether the track is favorited now.
+#[tauri::command]
+pub async fn toggle_favorite_track(
+    state: State<'_, AppState>,
+    auth_token: String,
+    track_id: Uuid,
+) -> ApiResult<bool> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.toggle_favorite(token.get_user_id(), FavoriteKind::Track, track_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Favorites the album, or unfavorites it if it was already favorited.
+///
+/// Returns whether the album is favorited now.
+#[tauri::command]
+pub async fn toggle_favorite_album(
+    state: State<'_, AppState>,
+    auth_token: String,
+    album_id: Uuid,
+) -> ApiResult<bool> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.toggle_favorite(token.get_user_id(), FavoriteKind::Album, album_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Favorites the artist, or unfavorites it if it was already favorited.
+///
+/// Returns whether the artist is favorited now.
+#[tauri::command]
+pub async fn toggle_favorite_artist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    artist_id: Uuid,
+) -> ApiResult<bool> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.toggle_favorite(token.get_user_id(), FavoriteKind::Artist, artist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Follows the playlist, or unfollows it if it was already followed.
+///
+/// Returns whether the playlist is followed now.
+#[tauri::command]
+pub async fn toggle_followed_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+) -> ApiResult<bool> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.toggle_favorite(token.get_user_id(), FavoriteKind::Playlist, playlist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the authenticated user's favorited albums (most recently favorited first).
+#[tauri::command]
+pub async fn get_favorited_albums(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Vec<Album>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_favorited_albums(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the authenticated user's favorited artists (most recently favorited first).
+#[tauri::command]
+pub async fn get_favorited_artists(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Vec<Artist>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_favorited_artists(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the playlists the authenticated user follows (most recently followed first).
+#[tauri::command]
+pub async fn get_followed_playlists(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<Vec<Playlist>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_followed_playlists(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
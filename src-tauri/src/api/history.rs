@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult},
+    database::{
+        history::{HistoryExt, NewPlay, PlayThreshold},
+        models::music_library::PlayRecord,
+        DBResult,
+    },
+    playback::TrackPlay,
+    AppState,
+};
+
+/// Reports that the track was played for `ms_played` (starting at `started_at`).
+///
+/// The play is added to the listening history, and counts towards the track's play count if
+/// it reached the user's [PlayThreshold]. `playlist_id` is the playlist the track was played
+/// from, if any.
+#[tauri::command]
+pub async fn report_play(
+    state: State<'_, AppState>,
+    auth_token: String,
+    track_id: Uuid,
+    playlist_id: Option<Uuid>,
+    started_at: DateTime<Utc>,
+    ms_played: u64,
+) -> ApiResult<PlayRecord> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let play = NewPlay {
+        track_id,
+        playlist_id,
+        started_at: started_at.naive_utc(),
+        ms_played,
+    };
+    let db = &state.db;
+    db.record_play(token.get_user_id(), play)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the authenticated user's most recent plays (newest first).
+#[tauri::command]
+pub async fn get_play_history(
+    state: State<'_, AppState>,
+    auth_token: String,
+    limit: u32,
+) -> ApiResult<Vec<PlayRecord>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_play_history(token.get_user_id(), limit)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets how many times the authenticated user played the track.
+#[tauri::command]
+pub async fn get_play_count(
+    state: State<'_, AppState>,
+    auth_token: String,
+    track_id: Uuid,
+) -> ApiResult<u64> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_play_count(token.get_user_id(), track_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets how long the authenticated user has to play a track for it to count as a play.
+#[tauri::command]
+pub async fn get_play_threshold(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayThreshold> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_play_threshold(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Sets how long the authenticated user has to play a track for it to count as a play.
+#[tauri::command]
+pub async fn set_play_threshold(
+    state: State<'_, AppState>,
+    auth_token: String,
+    threshold: PlayThreshold,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.set_play_threshold(token.get_user_id(), threshold)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Adds the track that the player finished playing to the listening history of the user who
+/// played it.
+pub async fn record_track_play(state: &AppState, play: TrackPlay) -> DBResult<()> {
+    let Some(user_id) = *state.playback_user.lock().await else {
+        return Ok(());
+    };
+    let play = NewPlay {
+        track_id: play.track_id,
+        playlist_id: None,
+        started_at: play.started_at,
+        ms_played: play.ms_played,
+    };
+    state.db.record_play(user_id, play).await?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use tauri::{AppHandle, State};
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult, ResponseChannel},
+    database::{
+        bundle::{read_bundle, write_bundle, BundleExt, BundleImportReport},
+        library::LibraryExt,
+        spotify::{read_spotify_export, SpotifyExt, SpotifyImportReport},
+    },
+    errors::SpotsError,
+    library::{artwork::thumbnails_dir, import_directory, ImportProgress},
+    AppState,
+};
+
+/// Imports all of the audio files in the `directory` into the music library.
+///
+/// The directory is watched afterwards, so changes to its files are synced automatically.
+///
+/// # Note
+/// The import progress is streamed to the `channel`.
+#[tauri::command]
+pub async fn import_library(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auth_token: String,
+    directory: String,
+    channel: ResponseChannel<ImportProgress>,
+) -> ApiResult<ImportProgress> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let directory = std::fs::canonicalize(&directory)
+        .map_err(|_| SpotsError::InvalidLibraryDirectory(directory))?;
+
+    // Import the library
+    let thumbnails_dir = thumbnails_dir(&app)?;
+    let db = &state.db;
+    let progress = import_directory(
+        db,
+        token.get_user_id(),
+        directory.clone(),
+        thumbnails_dir,
+        channel,
+    )
+    .await?;
+
+    // Keep the library in sync with the directory
+    db.add_library_folder(token.get_user_id(), &directory.to_string_lossy())
+        .await?;
+    state.library_watcher.lock().await.watch(&directory)?;
+
+    Ok(ApiResponse::success(progress))
+}
+
+/// Exports the authenticated user's playlists, favorites and listening history to a bundle at
+/// `path`, which can be imported into the library on another machine.
+#[tauri::command]
+pub async fn export_library_bundle(
+    state: State<'_, AppState>,
+    auth_token: String,
+    path: String,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    let bundle = db.export_bundle(token.get_user_id()).await?;
+    write_bundle(Path::new(&path), &bundle).map(ApiResponse::success)
+}
+
+/// Imports the playlists, favorites and listening history in the bundle at `path` for the
+/// authenticated user.
+///
+/// The bundle's tracks are matched to the library's by their contents and tags; the report
+/// lists the ones that aren't in the library.
+#[tauri::command]
+pub async fn import_library_bundle(
+    state: State<'_, AppState>,
+    auth_token: String,
+    path: String,
+) -> ApiResult<BundleImportReport> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let bundle = read_bundle(Path::new(&path))?;
+    let db = &state.db;
+    db.import_bundle(token.get_user_id(), &bundle)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Imports the playlists, saved tracks, albums and artists, and streaming history from the
+/// Spotify data export that was extracted to `directory` for the authenticated user.
+///
+/// The export's items are matched to the library by their names; the report lists the ones
+/// that aren't in the library.
+#[tauri::command]
+pub async fn import_spotify_data(
+    state: State<'_, AppState>,
+    auth_token: String,
+    directory: String,
+) -> ApiResult<SpotifyImportReport> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let export = read_spotify_export(Path::new(&directory))?;
+    let db = &state.db;
+    db.import_spotify(token.get_user_id(), &export)
+        .await
+        .map(ApiResponse::success)
+}
@@ -1,4 +1,13 @@
 pub mod auth;
+pub mod backup;
 pub mod dtos;
+pub mod favorites;
+pub mod history;
+pub mod library;
 pub mod music;
+pub mod playback;
+pub mod playlists;
+pub mod queue;
+pub mod search;
+pub mod stream;
 pub mod utils;
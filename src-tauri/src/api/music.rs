@@ -4,10 +4,12 @@ use uuid::Uuid;
 use crate::{
     api::utils::{token::verify_token, ApiResponse, ApiResult, ResponseChannel},
     database::{
+        self,
         albums::AlbumExt,
         models::music_library::{Album, Artist, Genre, Playlist, PlaylistTrack, Track},
         playlists::PlaylistExt,
         tracks::TrackExt,
+        Page,
     },
     AppState,
 };
@@ -23,7 +25,7 @@ pub async fn get_playlist(
     verify_token(&state, auth_token).await?;
 
     // Get playlist from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_playlist(playlist_id)
         .await
         .map(|playlist| ApiResponse::success(playlist))
@@ -44,7 +46,7 @@ pub async fn get_playlist_tracks(
     verify_token(&state, auth_token).await?;
 
     // Get playlist from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_playlist_tracks(playlist_id, channel)
         .await
         .map(|_| ApiResponse::success(()))
@@ -60,13 +62,16 @@ pub async fn get_pinned_playlists(
     let token = verify_token(&state, auth_token).await?;
 
     // Get pinned playlists from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_pinned_playlists(token.get_user_id())
         .await
         .map(|playlists| ApiResponse::success(playlists))
 }
 
-/// Gets all of the authenticated user's playlists.
+/// Gets a page of the authenticated user's playlists (the first page of playlists sorted by
+/// title if `page` isn't set).
+///
+/// Returns the cursor of the next page (`None` on the last page).
 ///
 /// # Note
 /// The actual playlists will be sent over the `channel`.
@@ -74,16 +79,17 @@ pub async fn get_pinned_playlists(
 pub async fn get_all_playlists(
     state: State<'_, AppState>,
     auth_token: String,
+    page: Option<Page>,
     channel: ResponseChannel<Playlist>,
-) -> ApiResult<()> {
+) -> ApiResult<Option<String>> {
     // Verify auth token
     let token = verify_token(&state, auth_token).await?;
 
     // Get playlists from DB
-    let db = state.db.lock().await;
-    db.get_all_playlists(token.get_user_id(), channel)
+    let db = &state.db;
+    db.get_all_playlists(token.get_user_id(), &page.unwrap_or_default(), channel)
         .await
-        .map(|_| ApiResponse::success(()))
+        .map(ApiResponse::success)
 }
 
 /// Gets the specified track.
@@ -97,13 +103,16 @@ pub async fn get_track(
     verify_token(&state, auth_token).await?;
 
     // Get track from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_track(track_id)
         .await
         .map(|track| ApiResponse::success(track))
 }
 
-/// Gets the user's favorited tracks.
+/// Gets a page of the user's favorited tracks (the first page of the most recently favorited
+/// tracks if `page` isn't set).
+///
+/// Returns the cursor of the next page (`None` on the last page).
 ///
 /// # Note
 /// The tracks are all streamed to the `channel`.
@@ -111,16 +120,17 @@ pub async fn get_track(
 pub async fn get_favorited_tracks(
     state: State<'_, AppState>,
     auth_token: String,
+    page: Option<Page>,
     channel: ResponseChannel<Track>,
-) -> ApiResult<()> {
+) -> ApiResult<Option<String>> {
     // Verify auth token
     let token = verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
-    db.get_favorited_tracks(token.get_user_id(), channel)
+    let db = &state.db;
+    db.get_favorited_tracks(token.get_user_id(), &page.unwrap_or_default(), channel)
         .await
-        .map(|_| ApiResponse::success(()))
+        .map(ApiResponse::success)
 }
 
 /// Gets the artists for the specified track.
@@ -134,7 +144,7 @@ pub async fn get_track_artists(
     verify_token(&state, auth_token).await?;
 
     // Get artits from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_track_artists(track_id)
         .await
         .map(|artists| ApiResponse::success(artists))
@@ -151,13 +161,16 @@ pub async fn get_track_genres(
     verify_token(&state, auth_token).await?;
 
     // Get artits from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_track_genres(track_id)
         .await
         .map(|genres| ApiResponse::success(genres))
 }
 
-/// Gets the all of the tracks in the music library.
+/// Gets a page of the tracks in the music library (the first page of tracks sorted by title if
+/// `page` isn't set).
+///
+/// Returns the cursor of the next page (`None` on the last page).
 ///
 /// # Note
 /// The tracks are all streamed to the `channel`.
@@ -165,19 +178,45 @@ pub async fn get_track_genres(
 pub async fn get_all_tracks(
     state: State<'_, AppState>,
     auth_token: String,
+    page: Option<Page>,
     channel: ResponseChannel<Track>,
-) -> ApiResult<()> {
+) -> ApiResult<Option<String>> {
     // Verify auth token
     verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
-    db.get_all_tracks(channel)
+    let db = &state.db;
+    db.get_all_tracks(&page.unwrap_or_default(), channel)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the tracks that match the query (e.g. `artist:"Daft Punk" year:>2000 played:never`).
+///
+/// # Note
+/// The tracks are all streamed to the `channel`.
+#[tauri::command]
+pub async fn filter_tracks(
+    state: State<'_, AppState>,
+    auth_token: String,
+    query: String,
+    channel: ResponseChannel<Track>,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    // Get tracks from DB
+    let db = &state.db;
+    db.filter_tracks(token.get_user_id(), &query, channel)
         .await
         .map(|_| ApiResponse::success(()))
 }
 
 /// Gets the audio data of the track as bytes.
+///
+/// # Note
+/// This reads the whole file into memory; prefer streaming the track from
+/// `spots://track/<id>?token=<auth_token>` (see [crate::api::stream]).
 #[tauri::command]
 pub async fn get_audio_data(
     state: State<'_, AppState>,
@@ -188,7 +227,7 @@ pub async fn get_audio_data(
     verify_token(&state, auth_token).await?;
 
     // Get audio data
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_audio_data(track_id)
         .await
         .map(|bytes| ApiResponse::success(bytes))
@@ -204,7 +243,7 @@ pub async fn get_last_played_track(
     verify_token(&state, auth_token).await?;
 
     // Get audio data
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_last_played_track().await.map(ApiResponse::success)
 }
 
@@ -219,7 +258,7 @@ pub async fn get_album(
     verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_album(album_id)
         .await
         .map(|album| ApiResponse::success(album))
@@ -240,7 +279,7 @@ pub async fn get_album_tracks(
     verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_album_tracks(album_id, channel)
         .await
         .map(|_| ApiResponse::success(()))
@@ -261,13 +300,16 @@ pub async fn get_album_artists(
     verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
+    let db = &state.db;
     db.get_album_artists(album_id, channel)
         .await
         .map(|_| ApiResponse::success(()))
 }
 
-/// Gets the all of the albums in the music library.
+/// Gets a page of the albums in the music library (the first page of albums sorted by title if
+/// `page` isn't set).
+///
+/// Returns the cursor of the next page (`None` on the last page).
 ///
 /// # Note
 /// The albums are all streamed to the `channel`.
@@ -275,14 +317,34 @@ pub async fn get_album_artists(
 pub async fn get_all_albums(
     state: State<'_, AppState>,
     auth_token: String,
+    page: Option<Page>,
     channel: ResponseChannel<Album>,
-) -> ApiResult<()> {
+) -> ApiResult<Option<String>> {
     // Verify auth token
     verify_token(&state, auth_token).await?;
 
     // Get tracks from DB
-    let db = state.db.lock().await;
-    db.get_all_albums(channel)
+    let db = &state.db;
+    db.get_all_albums(&page.unwrap_or_default(), channel)
         .await
-        .map(|_| ApiResponse::success(()))
+        .map(ApiResponse::success)
+}
+
+/// Cancels the stream that is being sent to the channel with the ID (e.g. when the user
+/// navigates away before all the tracks arrived).
+///
+/// The stream ends with a `Cancelled` response. Returns whether a stream was cancelled.
+///
+/// # Note
+/// This doesn't use the database, so it never waits behind the stream being cancelled.
+#[tauri::command]
+pub async fn cancel_stream(
+    state: State<'_, AppState>,
+    auth_token: String,
+    channel_id: u32,
+) -> ApiResult<bool> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    Ok(ApiResponse::success(database::cancel_stream(channel_id)))
 }
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        queue::save_queue_position,
+        utils::{token::verify_token, ApiResponse, ApiResult},
+    },
+    database::tracks::TrackExt,
+    playback::PlayerStatus,
+    AppState,
+};
+
+/// Loads the track into the player and starts playing it.
+#[tauri::command]
+pub async fn play_track(
+    state: State<'_, AppState>,
+    auth_token: String,
+    track_id: Uuid,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    // Play the track's file (outside of the queue)
+    *state.queue_user.lock().await = None;
+    *state.playback_user.lock().await = Some(token.get_user_id());
+    let track = state.db.get_playable_track(track_id).await?;
+    let player = state.player.lock().await;
+    player.set_next(None)?;
+    player.load(&track, true).map(ApiResponse::success)
+}
+
+/// Resumes playing the loaded track.
+#[tauri::command]
+pub async fn resume_playback(state: State<'_, AppState>, auth_token: String) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let player = state.player.lock().await;
+    player.play().map(ApiResponse::success)
+}
+
+/// Pauses the track that is playing.
+#[tauri::command]
+pub async fn pause_playback(state: State<'_, AppState>, auth_token: String) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    save_queue_position(&state).await?;
+    let player = state.player.lock().await;
+    player.pause().map(ApiResponse::success)
+}
+
+/// Stops playback and unloads the track.
+#[tauri::command]
+pub async fn stop_playback(state: State<'_, AppState>, auth_token: String) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    save_queue_position(&state).await?;
+    let player = state.player.lock().await;
+    player.stop().map(ApiResponse::success)
+}
+
+/// Seeks to the position (in milliseconds) in the loaded track.
+#[tauri::command]
+pub async fn seek_playback(
+    state: State<'_, AppState>,
+    auth_token: String,
+    position_ms: u64,
+) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let player = state.player.lock().await;
+    player
+        .seek(Duration::from_millis(position_ms))
+        .map(ApiResponse::success)
+}
+
+/// Sets the playback volume, from `0.0` (muted) to `1.0`.
+#[tauri::command]
+pub async fn set_volume(
+    state: State<'_, AppState>,
+    auth_token: String,
+    volume: f32,
+) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let player = state.player.lock().await;
+    player.set_volume(volume).map(ApiResponse::success)
+}
+
+/// Sets how long (in seconds) tracks crossfade into each other, or `0` for gapless playback.
+///
+/// Tracks from the same album are never crossfaded.
+#[tauri::command]
+pub async fn set_crossfade(
+    state: State<'_, AppState>,
+    auth_token: String,
+    duration_secs: f32,
+) -> ApiResult<()> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let duration = Duration::try_from_secs_f32(duration_secs.max(0.0)).unwrap_or_default();
+    let player = state.player.lock().await;
+    player.set_crossfade(duration).map(ApiResponse::success)
+}
+
+/// Gets the current status of the player.
+#[tauri::command]
+pub async fn get_playback_status(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayerStatus> {
+    // Verify auth token
+    verify_token(&state, auth_token).await?;
+
+    let player = state.player.lock().await;
+    Ok(ApiResponse::success(player.status()))
+}
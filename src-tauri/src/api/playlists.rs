@@ -0,0 +1,330 @@
+use std::path::Path;
+
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    api::{
+        dtos::PlaylistTitleDto,
+        utils::{token::verify_token, ApiResponse, ApiResult},
+    },
+    database::{
+        client::DatabaseClient,
+        models::music_library::Playlist,
+        playlist_files::{PlaylistFileExt, PlaylistFileImport},
+        playlists::PlaylistExt,
+        smart_playlists::SmartRules,
+        DBResult,
+    },
+    errors::SpotsError,
+    library::artwork::{thumbnails_dir, write_thumbnail},
+    AppState,
+};
+
+/// Creates an empty playlist for the authenticated user.
+#[tauri::command]
+pub async fn create_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist: PlaylistTitleDto,
+) -> ApiResult<Playlist> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    // Validate playlist
+    playlist.validate().map_err(SpotsError::ValidationError)?;
+
+    let db = &state.db;
+    db.create_playlist(token.get_user_id(), &playlist.title)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Creates a smart playlist for the authenticated user, whose tracks are the ones that match
+/// the `rules`.
+#[tauri::command]
+pub async fn create_smart_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist: PlaylistTitleDto,
+    rules: SmartRules,
+) -> ApiResult<Playlist> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    // Validate playlist
+    playlist.validate().map_err(SpotsError::ValidationError)?;
+
+    let db = &state.db;
+    db.create_smart_playlist(token.get_user_id(), &playlist.title, &rules)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Gets the rules of the playlist (`None` if it isn't a smart playlist).
+#[tauri::command]
+pub async fn get_smart_playlist_rules(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+) -> ApiResult<Option<SmartRules>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.get_smart_rules(playlist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Replaces the rules of the smart playlist.
+#[tauri::command]
+pub async fn set_smart_playlist_rules(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    rules: SmartRules,
+) -> ApiResult<Playlist> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.set_smart_rules(playlist_id, &rules)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Renames the playlist.
+#[tauri::command]
+pub async fn rename_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    playlist: PlaylistTitleDto,
+) -> ApiResult<Playlist> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    // Validate playlist
+    playlist.validate().map_err(SpotsError::ValidationError)?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.rename_playlist(playlist_id, &playlist.title)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Sets the playlist's thumbnail to a thumbnail of the image at `image_path`.
+#[tauri::command]
+pub async fn set_playlist_thumbnail(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    image_path: String,
+) -> ApiResult<Playlist> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+
+    // Create the thumbnail
+    let image_data = std::fs::read(&image_path)?;
+    let thumbnail_path = write_thumbnail(&thumbnails_dir(&app)?, &image_data)?;
+
+    db.set_playlist_thumbnail(playlist_id, &thumbnail_path.to_string_lossy())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Deletes the playlist.
+#[tauri::command]
+pub async fn delete_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.delete_playlist(playlist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Adds the tracks to the playlist at the `index` (or at the end if it's not set).
+///
+/// Returns the IDs of the playlist's tracks in their new order.
+#[tauri::command]
+pub async fn add_playlist_tracks(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    track_ids: Vec<Uuid>,
+    index: Option<usize>,
+) -> ApiResult<Vec<Uuid>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.add_playlist_tracks(playlist_id, &track_ids, index)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Removes the tracks from the playlist.
+///
+/// Returns the IDs of the playlist's tracks in their new order.
+#[tauri::command]
+pub async fn remove_playlist_tracks(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    track_ids: Vec<Uuid>,
+) -> ApiResult<Vec<Uuid>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.remove_playlist_tracks(playlist_id, &track_ids)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Moves the track to the `index` in the playlist.
+///
+/// Returns the IDs of the playlist's tracks in their new order.
+#[tauri::command]
+pub async fn move_playlist_track(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    track_id: Uuid,
+    index: usize,
+) -> ApiResult<Vec<Uuid>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.move_playlist_track(playlist_id, track_id, index)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Pins the playlist to the authenticated user's sidebar (after the other pinned playlists).
+///
+/// Returns the user's pinned playlists.
+#[tauri::command]
+pub async fn pin_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+) -> ApiResult<Vec<Playlist>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.pin_playlist(token.get_user_id(), playlist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Unpins the playlist from the authenticated user's sidebar.
+///
+/// Returns the user's pinned playlists.
+#[tauri::command]
+pub async fn unpin_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+) -> ApiResult<Vec<Playlist>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.unpin_playlist(token.get_user_id(), playlist_id)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Moves the pinned playlist to the `index` in the authenticated user's pinned playlists.
+///
+/// Returns the user's pinned playlists.
+#[tauri::command]
+pub async fn move_pinned_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    index: usize,
+) -> ApiResult<Vec<Playlist>> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.move_pinned_playlist(token.get_user_id(), playlist_id, index)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Imports the M3U, PLS or XSPF playlist file at `path` as a new playlist for the authenticated
+/// user.
+///
+/// The file's entries are matched to the library's tracks by their paths and tags; the ones
+/// that aren't in the library are returned along with the playlist.
+#[tauri::command]
+pub async fn import_playlist_file(
+    state: State<'_, AppState>,
+    auth_token: String,
+    path: String,
+) -> ApiResult<PlaylistFileImport> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.import_playlist_file(token.get_user_id(), Path::new(&path))
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Exports the playlist to an M3U, PLS or XSPF file at `path` (in the format of its extension).
+///
+/// With `relative_paths`, the tracks' paths are written relative to the playlist file.
+#[tauri::command]
+pub async fn export_playlist_file(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    path: String,
+    relative_paths: bool,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    verify_owner(db, token.get_user_id(), playlist_id).await?;
+    db.export_playlist_file(playlist_id, Path::new(&path), relative_paths)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Makes sure the playlist belongs to the user.
+async fn verify_owner(db: &DatabaseClient, user_id: Uuid, playlist_id: Uuid) -> DBResult<()> {
+    let playlist = db
+        .get_playlist(playlist_id)
+        .await?
+        .ok_or_else(|| sqlx::Error::RowNotFound)?;
+    if playlist.user_id != Some(user_id) {
+        return Err(SpotsError::PlaylistAccessDenied(playlist_id));
+    }
+    Ok(())
+}
@@ -0,0 +1,370 @@
+use std::time::Duration;
+
+use tauri::State;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult},
+    database::{
+        albums::AlbumExt, playlists::PlaylistExt, queue::QueueExt, tracks::TrackExt, DBResult,
+    },
+    errors::SpotsError,
+    playback::{
+        queue::{PlayQueue, RepeatMode},
+        PlaybackState,
+    },
+    AppState,
+};
+
+/// Gets the user's play queue.
+#[tauri::command]
+pub async fn get_play_queue(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let db = &state.db;
+    db.get_play_queue(token.get_user_id())
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Adds the tracks to the end of the queue, or right after the current track if `play_next`
+/// is set.
+#[tauri::command]
+pub async fn enqueue_tracks(
+    state: State<'_, AppState>,
+    auth_token: String,
+    track_ids: Vec<Uuid>,
+    play_next: bool,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        add_tracks(queue, &track_ids, play_next);
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Adds the album's tracks to the end of the queue, or right after the current track if
+/// `play_next` is set.
+#[tauri::command]
+pub async fn enqueue_album(
+    state: State<'_, AppState>,
+    auth_token: String,
+    album_id: Uuid,
+    play_next: bool,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let track_ids = state.db.get_album_track_ids(album_id).await?;
+    update_queue(&state, token.get_user_id(), |queue| {
+        add_tracks(queue, &track_ids, play_next);
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Adds the playlist's tracks to the end of the queue, or right after the current track if
+/// `play_next` is set.
+#[tauri::command]
+pub async fn enqueue_playlist(
+    state: State<'_, AppState>,
+    auth_token: String,
+    playlist_id: Uuid,
+    play_next: bool,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let track_ids = state.db.get_playlist_track_ids(playlist_id).await?;
+    update_queue(&state, token.get_user_id(), |queue| {
+        add_tracks(queue, &track_ids, play_next);
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Moves the queue entry to the `index` in the queue.
+#[tauri::command]
+pub async fn move_queue_entry(
+    state: State<'_, AppState>,
+    auth_token: String,
+    entry_id: Uuid,
+    index: usize,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        queue
+            .move_entry(entry_id, index)
+            .then_some(())
+            .ok_or(SpotsError::QueueEntryNotFound(entry_id))
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Removes the entry from the queue.
+#[tauri::command]
+pub async fn remove_queue_entry(
+    state: State<'_, AppState>,
+    auth_token: String,
+    entry_id: Uuid,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        queue
+            .remove(entry_id)
+            .then_some(())
+            .ok_or(SpotsError::QueueEntryNotFound(entry_id))
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Removes all of the tracks from the queue.
+#[tauri::command]
+pub async fn clear_queue(state: State<'_, AppState>, auth_token: String) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        queue.clear();
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Sets how the queue repeats.
+#[tauri::command]
+pub async fn set_repeat_mode(
+    state: State<'_, AppState>,
+    auth_token: String,
+    repeat_mode: RepeatMode,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        queue.repeat = repeat_mode;
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Shuffles the queue, or puts it back in its original order.
+#[tauri::command]
+pub async fn set_shuffle(
+    state: State<'_, AppState>,
+    auth_token: String,
+    shuffled: bool,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    update_queue(&state, token.get_user_id(), |queue| {
+        queue.set_shuffled(shuffled);
+        Ok(())
+    })
+    .await
+    .map(ApiResponse::success)
+}
+
+/// Plays the entry in the queue.
+#[tauri::command]
+pub async fn play_queue_entry(
+    state: State<'_, AppState>,
+    auth_token: String,
+    entry_id: Uuid,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let queue = update_queue(&state, token.get_user_id(), |queue| {
+        queue
+            .jump_to(entry_id)
+            .map(|_| ())
+            .ok_or(SpotsError::QueueEntryNotFound(entry_id))
+    })
+    .await?;
+    play_current(&state, token.get_user_id(), &queue, true).await?;
+    Ok(ApiResponse::success(queue))
+}
+
+/// Skips to the next track in the queue.
+#[tauri::command]
+pub async fn play_next_track(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let queue = update_queue(&state, token.get_user_id(), |queue| {
+        // Skipping ignores repeat-one
+        let repeat = queue.repeat;
+        if repeat == RepeatMode::One {
+            queue.repeat = RepeatMode::All;
+        }
+        queue.advance();
+        queue.repeat = repeat;
+        Ok(())
+    })
+    .await?;
+    play_current(&state, token.get_user_id(), &queue, true).await?;
+    Ok(ApiResponse::success(queue))
+}
+
+/// Goes back to the previous track in the queue.
+#[tauri::command]
+pub async fn play_previous_track(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let queue = update_queue(&state, token.get_user_id(), |queue| {
+        queue.previous();
+        Ok(())
+    })
+    .await?;
+    play_current(&state, token.get_user_id(), &queue, true).await?;
+    Ok(ApiResponse::success(queue))
+}
+
+/// Loads the current track of the user's queue (paused) at the position playback was at when
+/// the app was closed.
+#[tauri::command]
+pub async fn resume_play_queue(
+    state: State<'_, AppState>,
+    auth_token: String,
+) -> ApiResult<PlayQueue> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let queue = state.db.get_play_queue(token.get_user_id()).await?;
+    play_current(&state, token.get_user_id(), &queue, false).await?;
+    Ok(ApiResponse::success(queue))
+}
+
+/// Saves the player's position in the current track of the queue that is playing, so playback
+/// can be resumed from it later.
+pub async fn save_queue_position(state: &AppState) -> DBResult<()> {
+    let Some(user_id) = *state.queue_user.lock().await else {
+        return Ok(());
+    };
+    let status = state.player.lock().await.status();
+    let db = &state.db;
+    let queue = db.get_play_queue(user_id).await?;
+    if status.track_id.is_some() && status.track_id == queue.current_track() {
+        db.save_queue_position(user_id, status.position_ms).await?;
+    }
+    Ok(())
+}
+
+/// Moves on to the next track in the queue that is playing (after the current track ended).
+pub async fn advance_queue(state: &AppState) -> DBResult<()> {
+    let Some(user_id) = *state.queue_user.lock().await else {
+        return Ok(());
+    };
+    let queue = update_queue(state, user_id, |queue| {
+        queue.advance();
+        Ok(())
+    })
+    .await?;
+
+    // The player already moved on to the preloaded track
+    let status = state.player.lock().await.status();
+    if status.state != PlaybackState::Stopped && status.track_id == queue.current_track() {
+        return preload_next(state, &queue).await;
+    }
+    play_current(state, user_id, &queue, true).await
+}
+
+/// Applies the `update` to the user's queue and saves it, unless the `update` fails.
+async fn update_queue(
+    state: &AppState,
+    user_id: Uuid,
+    update: impl FnOnce(&mut PlayQueue) -> DBResult<()> + Send,
+) -> DBResult<PlayQueue> {
+    let queue = state.db.update_play_queue(user_id, update).await?;
+
+    // The track after the current one may have changed
+    if *state.queue_user.lock().await == Some(user_id) {
+        preload_next(state, &queue).await?;
+    }
+    Ok(queue)
+}
+
+/// Adds the tracks to the queue.
+fn add_tracks(queue: &mut PlayQueue, track_ids: &[Uuid], play_next: bool) {
+    if play_next {
+        queue.play_next(track_ids);
+    } else {
+        queue.enqueue(track_ids);
+    }
+}
+
+/// Loads the current track of the queue into the player, starting at the saved position.
+///
+/// The player is stopped if there is no current track.
+async fn play_current(
+    state: &AppState,
+    user_id: Uuid,
+    queue: &PlayQueue,
+    autoplay: bool,
+) -> DBResult<()> {
+    *state.queue_user.lock().await = Some(user_id);
+    *state.playback_user.lock().await = Some(user_id);
+
+    let Some(track_id) = queue.current_track() else {
+        return state.player.lock().await.stop();
+    };
+    let track = state.db.get_playable_track(track_id).await?;
+    {
+        let player = state.player.lock().await;
+        player.load(&track, autoplay)?;
+        if queue.position_ms > 0 {
+            player.seek(Duration::from_millis(queue.position_ms))?;
+        }
+    }
+    preload_next(state, queue).await
+}
+
+/// Sets the track after the current one as the player's next track, so it plays without a gap
+/// (or crossfades in).
+///
+/// Failing to preload the track isn't an error, it's loaded again once the current track ends.
+async fn preload_next(state: &AppState, queue: &PlayQueue) -> DBResult<()> {
+    let next = match queue.peek_next() {
+        Some(track_id) => match state.db.get_playable_track(track_id).await {
+            Ok(track) => Some(track),
+            Err(e) => {
+                warn!(error = e.to_string(), "Unable to preload the next track");
+                None
+            }
+        },
+        None => None,
+    };
+    let player = state.player.lock().await;
+    if let Err(e) = player.set_next(next.as_ref()) {
+        warn!(error = e.to_string(), "Unable to preload the next track");
+    }
+    Ok(())
+}
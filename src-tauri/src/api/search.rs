@@ -0,0 +1,58 @@
+use tauri::State;
+
+use crate::{
+    api::utils::{token::verify_token, ApiResponse, ApiResult, ResponseChannel},
+    database::{
+        fuzzy::{FuzzyExt, FuzzySearchResults},
+        search::{SearchExt, SearchResult},
+    },
+    AppState,
+};
+
+/// The max number of results (of each kind) that are returned by default.
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// Searches the music library (and the authenticated user's playlists).
+///
+/// # Note
+/// The results are streamed to the `channel`, grouped by kind and ranked by relevance. At most
+/// `limit` results of each kind are sent.
+#[tauri::command]
+pub async fn search(
+    state: State<'_, AppState>,
+    auth_token: String,
+    query: String,
+    limit: Option<u32>,
+    channel: ResponseChannel<SearchResult>,
+) -> ApiResult<()> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let db = &state.db;
+    db.search(token.get_user_id(), &query, limit, channel)
+        .await
+        .map(ApiResponse::success)
+}
+
+/// Searches the music library (and the authenticated user's playlists) for names that are
+/// similar to the `query`, tolerating typos.
+///
+/// Returns at most `limit` results (most similar first), along with "did you mean"
+/// suggestions.
+#[tauri::command]
+pub async fn fuzzy_search(
+    state: State<'_, AppState>,
+    auth_token: String,
+    query: String,
+    limit: Option<u32>,
+) -> ApiResult<FuzzySearchResults> {
+    // Verify auth token
+    let token = verify_token(&state, auth_token).await?;
+
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as usize;
+    let db = &state.db;
+    db.fuzzy_search(token.get_user_id(), &query, limit)
+        .await
+        .map(ApiResponse::success)
+}
@@ -0,0 +1,455 @@
+use std::{
+    fs::File,
+    io::{ErrorKind, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use tauri::{
+    http::{header, Request, Response, StatusCode},
+    AppHandle, Manager,
+};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    api::utils::{token::verify_session_token, ApiConfig},
+    database::{client::DatabaseClient, tracks::TrackExt},
+    errors::SpotsError,
+    AppState,
+};
+
+/// The URI scheme the audio is streamed over (e.g. `spots://track/<id>?token=<auth_token>`).
+///
+/// # Note
+/// On Windows and Android the URL is `http://spots.localhost/track/<id>?token=<auth_token>`.
+pub const STREAM_URI_SCHEME: &str = "spots";
+
+/// The max number of bytes sent in a single response.
+const MAX_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// The origins the app's webview is served from, which are the only ones allowed to read the
+/// streamed audio.
+const APP_ORIGINS: [&str; 3] = [
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// The origin of the frontend's dev server (the `devUrl` in `tauri.conf.json`).
+const DEV_ORIGIN: &str = "http://localhost:1420";
+
+/// A byte range (inclusive) of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+impl ByteRange {
+    /// The number of bytes in the range.
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// Handles a request to the [STREAM_URI_SCHEME] protocol.
+///
+/// The track's audio is served in chunks, and `Range` requests are supported so the webview
+/// can seek without loading the whole file.
+pub async fn handle_stream_request(
+    app: &AppHandle,
+    request: Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    let origin = allowed_origin(&request);
+    let state = app.state::<AppState>();
+    let config = state.api_config.lock().await.clone();
+    match stream_track(&state.db, config, &request, origin).await {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(
+                uri = request.uri().to_string(),
+                error = e.to_string(),
+                "Unable to stream track"
+            );
+            error_response(&e, origin)
+        }
+    }
+}
+
+/// Builds the response for a request that couldn't be served.
+fn error_response(e: &SpotsError, origin: Option<&str>) -> Response<Vec<u8>> {
+    let status = match e {
+        SpotsError::AuthTokenExpired
+        | SpotsError::AuthTokenRevoked
+        | SpotsError::AuthTokenDecodeError { .. }
+        | SpotsError::AuthTokenDecryptError { .. }
+        | SpotsError::AuthTokenParseError { .. } => StatusCode::UNAUTHORIZED,
+        SpotsError::InvalidStreamRequest(_) => StatusCode::BAD_REQUEST,
+        SpotsError::DatabaseError(sqlx::Error::RowNotFound) | SpotsError::TrackFileMissing(_) => {
+            StatusCode::NOT_FOUND
+        }
+        SpotsError::RangeNotSatisfiable { .. } => StatusCode::RANGE_NOT_SATISFIABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    let mut builder = Response::builder().status(status);
+    if let SpotsError::RangeNotSatisfiable { file_len, .. } = e {
+        builder = builder.header(header::CONTENT_RANGE, format!("bytes */{}", file_len));
+    }
+    if let Some(origin) = origin {
+        builder = builder.header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    }
+    builder
+        .body(e.to_string().into_bytes())
+        .unwrap_or_else(|_| Response::new(vec![]))
+}
+
+/// Gets the request's `Origin` if it is the app's own, so only the app can read the response.
+fn allowed_origin(request: &Request<Vec<u8>>) -> Option<&'static str> {
+    let origin = request.headers().get(header::ORIGIN)?.to_str().ok()?;
+    APP_ORIGINS
+        .into_iter()
+        .chain(cfg!(debug_assertions).then_some(DEV_ORIGIN))
+        .find(|allowed| *allowed == origin)
+}
+
+/// Streams the requested range of the track.
+async fn stream_track(
+    db: &DatabaseClient,
+    config: ApiConfig,
+    request: &Request<Vec<u8>>,
+    origin: Option<&'static str>,
+) -> Result<Response<Vec<u8>>, SpotsError> {
+    let (track_id, auth_token) = parse_stream_uri(request)?;
+
+    // Verify auth token
+    verify_session_token(config, db, auth_token).await?;
+
+    // Find the track's file
+    let track = db.get_playable_track(track_id).await?;
+    let file_path = PathBuf::from(track.file_path);
+
+    // Read the requested bytes
+    let range_header = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|range| range.to_str().ok())
+        .map(|range| range.to_string());
+    tauri::async_runtime::spawn_blocking(move || {
+        read_range(&file_path, range_header.as_deref(), origin)
+    })
+    .await
+    .map_err(|e| std::io::Error::other(e.to_string()))?
+}
+
+/// Reads the bytes in the `Range` header from the file, or the start of the file if there is no
+/// `Range` header.
+///
+/// The response may be read by the `origin`, if there is one.
+fn read_range(
+    file_path: &Path,
+    range_header: Option<&str>,
+    origin: Option<&str>,
+) -> Result<Response<Vec<u8>>, SpotsError> {
+    let mut file = File::open(file_path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => {
+            SpotsError::TrackFileMissing(file_path.to_string_lossy().to_string())
+        }
+        _ => SpotsError::from(e),
+    })?;
+    let file_len = file.metadata()?.len();
+
+    let requested = match range_header {
+        Some(header) => Some(parse_range(header, file_len)?),
+        None if file_len == 0 => None,
+        None => Some(ByteRange {
+            start: 0,
+            end: file_len - 1,
+        }),
+    };
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, audio_mime_type(file_path))
+        .header(header::ACCEPT_RANGES, "bytes");
+    if let Some(origin) = origin {
+        builder = builder
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+            .header(header::VARY, "Origin");
+    }
+    let Some(requested) = requested else {
+        return builder
+            .status(StatusCode::OK)
+            .header(header::CONTENT_LENGTH, 0)
+            .body(vec![])
+            .map_err(|e| SpotsError::InvalidStreamRequest(e.to_string()));
+    };
+
+    // Only send a chunk at a time, the webview will request the rest
+    let range = ByteRange {
+        start: requested.start,
+        end: requested.end.min(requested.start + MAX_CHUNK_SIZE - 1),
+    };
+    let mut bytes = vec![0u8; range.len() as usize];
+    file.seek(SeekFrom::Start(range.start))?;
+    file.read_exact(&mut bytes)?;
+
+    let status = if range.len() == file_len {
+        StatusCode::OK
+    } else {
+        StatusCode::PARTIAL_CONTENT
+    };
+    builder
+        .status(status)
+        .header(header::CONTENT_LENGTH, range.len())
+        .header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", range.start, range.end, file_len),
+        )
+        .body(bytes)
+        .map_err(|e| SpotsError::InvalidStreamRequest(e.to_string()))
+}
+
+/// Gets the track ID and auth token from the request's URI.
+fn parse_stream_uri(request: &Request<Vec<u8>>) -> Result<(Uuid, String), SpotsError> {
+    let uri = request.uri();
+
+    // `spots://track/<id>` or `http://spots.localhost/track/<id>`
+    let host = uri.host().unwrap_or_default();
+    let segments: Vec<&str> = std::iter::once(host)
+        .chain(uri.path().split('/'))
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let track_id = segments
+        .iter()
+        .position(|segment| *segment == "track")
+        .and_then(|idx| segments.get(idx + 1))
+        .ok_or_else(|| SpotsError::InvalidStreamRequest(uri.to_string()))?;
+    let track_id =
+        Uuid::from_str(track_id).map_err(|_| SpotsError::InvalidStreamRequest(uri.to_string()))?;
+
+    let auth_token = form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes())
+        .find(|(key, _)| key == "token")
+        .map(|(_, token)| token.to_string())
+        .ok_or_else(|| SpotsError::InvalidStreamRequest(uri.to_string()))?;
+
+    Ok((track_id, auth_token))
+}
+
+/// Parses a `Range` header (e.g. `bytes=0-1023`, `bytes=1024-` or `bytes=-500`).
+///
+/// Only the first range is used if multiple ranges are requested.
+fn parse_range(header: &str, file_len: u64) -> Result<ByteRange, SpotsError> {
+    let not_satisfiable = || SpotsError::RangeNotSatisfiable {
+        range: header.to_string(),
+        file_len,
+    };
+
+    let spec = header
+        .trim()
+        .strip_prefix("bytes=")
+        .ok_or_else(not_satisfiable)?
+        .split(',')
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let (start, end) = spec.split_once('-').ok_or_else(not_satisfiable)?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = match (start.is_empty(), end.is_empty()) {
+        // bytes=-<suffix length>
+        (true, false) => {
+            let suffix: u64 = end.parse().map_err(|_| not_satisfiable())?;
+            if suffix == 0 || file_len == 0 {
+                return Err(not_satisfiable());
+            }
+            ByteRange {
+                start: file_len.saturating_sub(suffix),
+                end: file_len - 1,
+            }
+        }
+        // bytes=<start>-
+        (false, true) => ByteRange {
+            start: start.parse().map_err(|_| not_satisfiable())?,
+            end: file_len.saturating_sub(1),
+        },
+        // bytes=<start>-<end>
+        (false, false) => ByteRange {
+            start: start.parse().map_err(|_| not_satisfiable())?,
+            end: end
+                .parse::<u64>()
+                .map_err(|_| not_satisfiable())?
+                .min(file_len.saturating_sub(1)),
+        },
+        (true, true) => return Err(not_satisfiable()),
+    };
+
+    if range.start >= file_len || range.start > range.end {
+        return Err(not_satisfiable());
+    }
+    Ok(range)
+}
+
+/// Gets the MIME type of the audio file from its extension.
+fn audio_mime_type(file_path: &Path) -> &'static str {
+    let ext = file_path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "m4a" | "mp4" => "audio/mp4",
+        "aac" => "audio/aac",
+        "wav" => "audio/wav",
+        "aif" | "aiff" => "audio/aiff",
+        _ => {
+            warn!(
+                file = file_path.to_string_lossy().to_string(),
+                "Unknown audio file type"
+            );
+            "application/octet-stream"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use dotenvy::dotenv;
+
+    use super::*;
+    use crate::{
+        api::utils::token::Token,
+        database::{
+            sessions::SessionExt,
+            test_utils::{insert_track, TempDb},
+            users::UserExt,
+        },
+    };
+
+    #[test]
+    fn test_parse_range() -> Result<(), SpotsError> {
+        let range = |start, end| ByteRange { start, end };
+        assert_eq!(parse_range("bytes=0-99", 1000)?, range(0, 99));
+        assert_eq!(parse_range("bytes=500-", 1000)?, range(500, 999));
+        assert_eq!(parse_range("bytes=-100", 1000)?, range(900, 999));
+        assert_eq!(parse_range("bytes=900-5000", 1000)?, range(900, 999));
+        assert_eq!(parse_range("bytes=0-9, 20-29", 1000)?, range(0, 9));
+        assert!(parse_range("bytes=1000-", 1000).is_err());
+        assert!(parse_range("bytes=50-10", 1000).is_err());
+        assert!(parse_range("items=0-10", 1000).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_range() -> Result<(), SpotsError> {
+        let path = std::env::temp_dir().join(format!("spots-stream-{}.mp3", Uuid::new_v4()));
+        std::fs::write(&path, (0..100).collect::<Vec<u8>>())?;
+
+        let response = read_range(&path, Some("bytes=10-19"), Some("tauri://localhost"))?;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body(), &(10..20).collect::<Vec<u8>>());
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 10-19/100");
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "tauri://localhost"
+        );
+
+        // Out of range
+        let e = read_range(&path, Some("bytes=100-"), None).unwrap_err();
+        let response = error_response(&e, None);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */100");
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        let request = |origin: &str| {
+            Request::builder()
+                .uri("spots://track/1")
+                .header(header::ORIGIN, origin)
+                .body(vec![])
+                .unwrap()
+        };
+        assert_eq!(
+            allowed_origin(&request("http://tauri.localhost")),
+            Some("http://tauri.localhost")
+        );
+        assert_eq!(allowed_origin(&request("https://example.com")), None);
+        assert_eq!(
+            allowed_origin(&Request::builder().body(vec![]).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_stream_uri() -> Result<(), SpotsError> {
+        let track_id = Uuid::new_v4();
+        for uri in [
+            format!("spots://track/{}?token=abc%2B%2F%3D", track_id),
+            format!(
+                "http://spots.localhost/track/{}?token=abc%2B%2F%3D",
+                track_id
+            ),
+        ] {
+            let request = Request::builder().uri(uri).body(vec![]).unwrap();
+            assert_eq!(
+                parse_stream_uri(&request)?,
+                (track_id, String::from("abc+/="))
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_track_after_logging_out() {
+        tauri::async_runtime::block_on(async {
+            dotenv().unwrap();
+            let config = ApiConfig::new();
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let expires_at = Utc::now().naive_local() + Duration::minutes(60);
+            let session_id = db.create_session(user_id, expires_at).await.unwrap();
+            let token = Token::try_new(config.clone(), user_id.to_string(), session_id).unwrap();
+
+            let track_id = insert_track(db, "Track").await;
+            let path = temp.path.with_extension("mp3");
+            std::fs::write(&path, b"audio").unwrap();
+            sqlx::query("UPDATE tracks SET file_path = $1 WHERE id = $2")
+                .bind(path.to_string_lossy().to_string())
+                .bind(track_id.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            let token: String = form_urlencoded::byte_serialize(token.as_bytes()).collect();
+            let request = Request::builder()
+                .uri(format!("spots://track/{track_id}?token={token}"))
+                .body(vec![])
+                .unwrap();
+
+            let response = stream_track(db, config.clone(), &request, None)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), b"audio");
+
+            // Logging out revokes the token
+            db.delete_session(session_id).await.unwrap();
+            let e = stream_track(db, config, &request, None).await.unwrap_err();
+            assert!(matches!(e, SpotsError::AuthTokenRevoked));
+            assert_eq!(error_response(&e, None).status(), StatusCode::UNAUTHORIZED);
+
+            std::fs::remove_file(&path).unwrap();
+        });
+    }
+}
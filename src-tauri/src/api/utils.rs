@@ -13,6 +13,7 @@ pub enum ApiResponseStatus {
     Started,
     Pending,
     Completed,
+    Cancelled,
 }
 
 /// An API response.
@@ -144,11 +145,17 @@ pub mod token {
     use tauri::State;
     use uuid::Uuid;
 
-    use crate::{api::utils::ApiConfig, errors::SpotsError, AppState};
+    use crate::{
+        api::utils::ApiConfig,
+        database::{client::DatabaseClient, sessions::SessionExt},
+        errors::SpotsError,
+        AppState,
+    };
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Token {
         user_id: String,
+        session_id: String,
         issued_at: usize,
         expires_at: usize,
     }
@@ -156,17 +163,19 @@ pub mod token {
     impl Display for Token {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             f.write_fmt(format_args!(
-                "Token {{ value: {}, issued_at: {}, expires_at: {} }}",
-                self.user_id, self.issued_at, self.expires_at
+                "Token {{ value: {}, session_id: {}, issued_at: {}, expires_at: {} }}",
+                self.user_id, self.session_id, self.issued_at, self.expires_at
             ))
         }
     }
 
     impl Token {
-        /// Creates a new encrypted auth token for the user with the given ID.
+        /// Creates a new encrypted auth token for the user with the given ID, which belongs to
+        /// the session with the given ID.
         pub fn try_new(
             config: ApiConfig,
             user_id: impl Into<String>,
+            session_id: Uuid,
         ) -> Result<String, SpotsError> {
             let user_id = user_id.into();
             if user_id.is_empty() {
@@ -180,6 +189,7 @@ pub mod token {
                 (now + Duration::minutes(config.token_maxage_mins)).timestamp() as usize;
             let token = Token {
                 user_id,
+                session_id: session_id.to_string(),
                 issued_at,
                 expires_at,
             };
@@ -206,6 +216,11 @@ pub mod token {
             Uuid::from_str(&self.user_id).expect("Invalid user ID")
         }
 
+        /// Gets the ID of the session the token belongs to.
+        pub fn get_session_id(&self) -> Uuid {
+            Uuid::from_str(&self.session_id).expect("Invalid session ID")
+        }
+
         /// Makes sure the token is valid (not expired).
         pub fn is_valid(&self) -> bool {
             let now = Utc::now();
@@ -310,15 +325,34 @@ pub mod token {
     }
 
     /// Verifies the auth token.
+    ///
+    /// The token has to be unexpired, and its session can't have been ended (e.g. by logging
+    /// out).
     pub async fn verify_token(
         state: &State<'_, AppState>,
         auth_token: String,
     ) -> Result<Token, SpotsError> {
         let config = state.api_config.lock().await.clone();
+        verify_session_token(config, &state.db, auth_token).await
+    }
+
+    /// Verifies the auth token like [verify_token], with the sessions in the `db`.
+    pub async fn verify_session_token(
+        config: ApiConfig,
+        db: &DatabaseClient,
+        auth_token: String,
+    ) -> Result<Token, SpotsError> {
         let token = Token::from_encrypted(config, auth_token)?;
         if !token.is_valid() {
             return Err(SpotsError::AuthTokenExpired);
         }
+
+        if !db
+            .is_session_active(token.get_session_id(), token.get_user_id())
+            .await?
+        {
+            return Err(SpotsError::AuthTokenRevoked);
+        }
         Ok(token)
     }
 
@@ -335,10 +369,15 @@ pub mod token {
         fn test_encrypt_decrypt() -> Result<(), SpotsError> {
             dotenv().unwrap();
             let config = ApiConfig::new();
-            let encrypted = Token::try_new(config.clone(), "Me")?;
+            let session_id = Uuid::new_v4();
+            let encrypted = Token::try_new(config.clone(), "Me", session_id)?;
             let decrypted = Token::decrypt(config, encrypted)?;
             let decrypted: serde_json::Value = serde_json::from_str(&decrypted).unwrap();
             assert_eq!(*decrypted.get("user_id").unwrap(), json!("Me"));
+            assert_eq!(
+                *decrypted.get("session_id").unwrap(),
+                json!(session_id.to_string())
+            );
 
             Ok(())
         }
@@ -0,0 +1,60 @@
+//! The parts of the database that the benches measure, since the rest of the crate is private.
+
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::database::{
+    client::DatabaseClient,
+    library::{LibraryExt, NewTrack},
+    tracks::TrackExt,
+    users::UserExt,
+};
+
+/// A database for a bench to read from.
+#[derive(Clone)]
+pub struct BenchDb(DatabaseClient);
+
+impl BenchDb {
+    /// Opens the DB file at `path` (see [DatabaseClient::open]).
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        let db = DatabaseClient::open(path)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(Self(db))
+    }
+
+    /// Adds a user with `count` tracks, returning the tracks' IDs.
+    pub async fn insert_tracks(&self, count: usize) -> Result<Vec<Uuid>, String> {
+        let user_id = self
+            .0
+            .create_user("user", "hash")
+            .await
+            .map_err(|e| e.to_string())?
+            .id;
+        let mut track_ids = Vec::with_capacity(count);
+        for i in 0..count {
+            let track = NewTrack {
+                file_path: format!("/music/{i}.mp3"),
+                title: format!("Track {i}"),
+                ..Default::default()
+            };
+            let track = self
+                .0
+                .upsert_track(user_id, track)
+                .await
+                .map_err(|e| e.to_string())?;
+            track_ids.push(track.id);
+        }
+        Ok(track_ids)
+    }
+
+    /// Gets the track, like the commands that show a track do.
+    pub async fn get_track(&self, track_id: Uuid) -> Result<(), String> {
+        match self.0.get_track(track_id).await {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(format!("Track not found: {track_id}")),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     database::{
         client::DatabaseClient,
         models::music_library::{Album, Artist, Track},
-        stream_rows, DBResult,
+        parse_ids, stream_page, stream_rows, DBResult, Page, SortKey, SortOrder,
     },
 };
 
@@ -22,6 +22,9 @@ pub trait AlbumExt {
         channel: ResponseChannel<Track>,
     ) -> DBResult<()>;
 
+    /// Gets the IDs of the album's tracks, in track number order.
+    async fn get_album_track_ids(&self, album_id: Uuid) -> DBResult<Vec<Uuid>>;
+
     /// Gets the artists for the album.
     async fn get_album_artists(
         &self,
@@ -29,8 +32,18 @@ pub trait AlbumExt {
         channel: ResponseChannel<Artist>,
     ) -> DBResult<()>;
 
-    /// Gets all the album in the DB.
-    async fn get_all_albums(&self, channel: ResponseChannel<Album>) -> DBResult<()>;
+    /// Gets a page of the albums in the DB (sorted by title by default).
+    ///
+    /// Albums are sorted by their artist, or by their tracks: latest release year, first time one
+    /// was added or last time one was played. Returns the cursor of the next page.
+    ///
+    /// # Note
+    /// The albums are all streamed to the `channel`.
+    async fn get_all_albums(
+        &self,
+        page: &Page,
+        channel: ResponseChannel<Album>,
+    ) -> DBResult<Option<String>>;
 }
 
 impl AlbumExt for DatabaseClient {
@@ -63,6 +76,21 @@ impl AlbumExt for DatabaseClient {
         Ok(())
     }
 
+    async fn get_album_track_ids(&self, album_id: Uuid) -> DBResult<Vec<Uuid>> {
+        let track_ids: Vec<String> = sqlx::query_scalar(
+            "
+            SELECT t.id
+            FROM tracks t
+            WHERE t.album_id = $1
+            ORDER BY t.track_number IS NULL, t.track_number, t.title
+            ",
+        )
+        .bind(album_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        parse_ids(track_ids)
+    }
+
     async fn get_album_artists(
         &self,
         album_id: Uuid,
@@ -86,9 +114,34 @@ impl AlbumExt for DatabaseClient {
         Ok(())
     }
 
-    async fn get_all_albums(&self, channel: ResponseChannel<Album>) -> DBResult<()> {
-        let albums = sqlx::query_as::<Sqlite, Album>("SELECT * FROM albums").fetch(&self.pool);
-        stream_rows(albums, channel).await?;
-        Ok(())
+    async fn get_all_albums(
+        &self,
+        page: &Page,
+        channel: ResponseChannel<Album>,
+    ) -> DBResult<Option<String>> {
+        let order = page.order(SortOrder::BY_TITLE);
+        stream_page(
+            &self.pool,
+            "SELECT * FROM albums",
+            Vec::new(),
+            album_sort_sql(order.key),
+            order,
+            page,
+            channel,
+        )
+        .await
+    }
+}
+
+/// The sort key of an album `r` (see [stream_page]).
+///
+/// The keys that depend on the album's tracks are kept up to date by triggers.
+pub(crate) fn album_sort_sql(key: SortKey) -> &'static str {
+    match key {
+        SortKey::Title | SortKey::Album => "r.title COLLATE NOCASE",
+        SortKey::Artist => "COALESCE(r.artist, '') COLLATE NOCASE",
+        SortKey::Year => "COALESCE(r.release_year, 0)",
+        SortKey::Added => "COALESCE(r.added_at, '')",
+        SortKey::LastPlayed => "COALESCE(r.last_played_at, '')",
     }
 }
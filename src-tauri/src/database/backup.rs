@@ -0,0 +1,369 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use sqlx::{sqlite::SqliteConnectOptions, Connection, SqliteConnection};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+use crate::{
+    database::{client::DatabaseClient, DBResult},
+    errors::SpotsError,
+};
+
+/// How many automatic backups are kept (the oldest ones are deleted).
+pub const AUTO_BACKUP_COUNT: usize = 5;
+
+/// The format of the timestamp in the backups' file names.
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+
+/// Gets the directory the backups are stored in (next to the database in the app data dir).
+pub fn backups_dir(app: &AppHandle) -> Result<PathBuf, SpotsError> {
+    let mut path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| SpotsError::AppDataDirError(e.to_string()))?;
+    path.push("backups");
+    Ok(path)
+}
+
+/// A snapshot of the database.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Backup {
+    /// The name of the backup's file in the backups dir.
+    pub file_name: String,
+
+    /// Whether the backup was taken automatically (when the app started).
+    pub automatic: bool,
+
+    pub created_at: NaiveDateTime,
+
+    /// The size of the backup in bytes.
+    pub size: u64,
+}
+
+impl Backup {
+    /// Parses the backup's details from its file name (e.g. `auto-20261018-214836-123.sqlite`).
+    fn from_file_name(file_name: &str, size: u64) -> Option<Self> {
+        let name = file_name.strip_suffix(".sqlite")?;
+        let (automatic, timestamp) = if let Some(timestamp) = name.strip_prefix("auto-") {
+            (true, timestamp)
+        } else {
+            (false, name.strip_prefix("manual-")?)
+        };
+        let created_at = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).ok()?;
+        Some(Self {
+            file_name: file_name.to_string(),
+            automatic,
+            created_at,
+            size,
+        })
+    }
+}
+
+/// Database operations for backing up the database and checking it for corruption.
+pub trait BackupExt {
+    /// Writes a snapshot of the database to the `dir`, which is safe to do while the database
+    /// is in use.
+    ///
+    /// Only the last [AUTO_BACKUP_COUNT] automatic backups are kept.
+    async fn backup(&self, dir: &Path, automatic: bool) -> DBResult<Backup>;
+
+    /// Checks the database for corruption, returning the problems that were found (none if
+    /// the database is intact).
+    async fn integrity_check(&self) -> DBResult<Vec<String>>;
+}
+
+impl BackupExt for DatabaseClient {
+    async fn backup(&self, dir: &Path, automatic: bool) -> DBResult<Backup> {
+        std::fs::create_dir_all(dir)?;
+        let kind = if automatic { "auto" } else { "manual" };
+        let timestamp = Utc::now().naive_local().format(BACKUP_TIMESTAMP_FORMAT);
+        let path = dir.join(format!("{kind}-{timestamp}.sqlite"));
+
+        sqlx::query("VACUUM INTO $1")
+            .bind(path.to_string_lossy())
+            .execute(&self.pool)
+            .await?;
+        info!(path = path.to_string_lossy().as_ref(), "Database backed up");
+
+        if automatic {
+            rotate_backups(dir)?;
+        }
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        Backup::from_file_name(&file_name, std::fs::metadata(&path)?.len())
+            .ok_or_else(|| SpotsError::BackupNotFound(file_name.to_string()))
+    }
+
+    async fn integrity_check(&self) -> DBResult<Vec<String>> {
+        let mut conn = self.pool.acquire().await?;
+        integrity_problems(&mut conn).await
+    }
+}
+
+/// Gets the backups in the `dir` (newest first).
+pub fn get_backups(dir: &Path) -> DBResult<Vec<Backup>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if let Some(backup) =
+            Backup::from_file_name(&file_name.to_string_lossy(), entry.metadata()?.len())
+        {
+            backups.push(backup);
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Deletes the automatic backups in the `dir` except for the last [AUTO_BACKUP_COUNT].
+fn rotate_backups(dir: &Path) -> DBResult<()> {
+    let old_backups = get_backups(dir)?
+        .into_iter()
+        .filter(|backup| backup.automatic)
+        .skip(AUTO_BACKUP_COUNT);
+    for backup in old_backups {
+        std::fs::remove_file(dir.join(&backup.file_name))?;
+    }
+    Ok(())
+}
+
+/// Checks that the backup can be restored: it has to be intact, and each of its migrations has
+/// to be one of the app's migrations (a backup from an older version is migrated once it is
+/// restored, but one from a newer version can't be).
+pub async fn check_backup(path: &Path) -> DBResult<()> {
+    let options = SqliteConnectOptions::new().filename(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| SpotsError::InvalidBackup(e.to_string()))?;
+
+    let problems = integrity_problems(&mut conn)
+        .await
+        .map_err(|e| SpotsError::InvalidBackup(e.to_string()))?;
+    if !problems.is_empty() {
+        return Err(SpotsError::InvalidBackup(problems.join("; ")));
+    }
+
+    let applied: Vec<(i64, Vec<u8>)> =
+        sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations WHERE success = 1")
+            .fetch_all(&mut conn)
+            .await
+            .map_err(|_| SpotsError::InvalidBackup(String::from("Not a Spots database")))?;
+    let migrator = sqlx::migrate!("./migrations/");
+    let known: HashMap<i64, &[u8]> = migrator
+        .iter()
+        .map(|migration| (migration.version, migration.checksum.as_ref()))
+        .collect();
+    for (version, checksum) in applied {
+        match known.get(&version) {
+            None => {
+                return Err(SpotsError::InvalidBackup(format!(
+                    "Migration {version} is from a newer version of Spots"
+                )))
+            }
+            Some(known_checksum) if *known_checksum != checksum.as_slice() => {
+                return Err(SpotsError::InvalidBackup(format!(
+                    "Migration {version} doesn't match this version of Spots"
+                )))
+            }
+            Some(_) => {}
+        }
+    }
+
+    conn.close().await?;
+    Ok(())
+}
+
+/// The file a backup is copied to until it replaces the database at `db_path`.
+fn staged_restore_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".restore");
+    PathBuf::from(path)
+}
+
+/// Stages the backup to replace the database at `db_path` the next time the app starts (see
+/// [apply_staged_restore]), since the database can't be replaced while it is open.
+///
+/// The backup has to pass [check_backup].
+pub async fn stage_restore(db_path: &Path, backup_path: &Path) -> DBResult<()> {
+    check_backup(backup_path).await?;
+    std::fs::copy(backup_path, staged_restore_path(db_path))?;
+    info!(
+        backup = backup_path.to_string_lossy().as_ref(),
+        "Backup staged for restoring"
+    );
+    Ok(())
+}
+
+/// Replaces the database at `db_path` with the staged backup, if there is one.
+///
+/// # Note
+/// This has to be done before the database is opened.
+pub fn apply_staged_restore(db_path: &Path) -> DBResult<()> {
+    let staged_path = staged_restore_path(db_path);
+    if !staged_path.exists() {
+        return Ok(());
+    }
+
+    // The write-ahead log belongs to the database that is replaced
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!(
+                    error = e.to_string(),
+                    "Unable to remove the write-ahead log"
+                );
+            }
+        }
+    }
+    std::fs::rename(&staged_path, db_path)?;
+    info!(
+        database = db_path.to_string_lossy().as_ref(),
+        "Backup restored"
+    );
+    Ok(())
+}
+
+/// Runs SQLite's integrity check on the connection's database.
+async fn integrity_problems(conn: &mut SqliteConnection) -> DBResult<Vec<String>> {
+    let results: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut *conn)
+        .await?;
+    Ok(results
+        .into_iter()
+        .filter(|result| result != "ok")
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+    use crate::database::{
+        client::connect_options,
+        test_utils::{count, insert_track, TempDb},
+    };
+
+    #[test]
+    fn test_backup_file_name() {
+        let backup = Backup::from_file_name("auto-20261018-214836-123.sqlite", 42).unwrap();
+        assert!(backup.automatic);
+        assert_eq!(backup.created_at.to_string(), "2026-10-18 21:48:36.123");
+        assert!(
+            !Backup::from_file_name("manual-20261018-214836-123.sqlite", 0)
+                .unwrap()
+                .automatic
+        );
+        assert_eq!(Backup::from_file_name("notes.txt", 0), None);
+        assert_eq!(Backup::from_file_name("auto-yesterday.sqlite", 0), None);
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let dir = temp.path.with_extension("backups");
+            insert_track(db, "Track").await;
+
+            let backup = db.backup(&dir, false).await.unwrap();
+            assert!(!backup.automatic);
+            assert_eq!(get_backups(&dir).unwrap(), vec![backup.clone()]);
+            assert!(db.integrity_check().await.unwrap().is_empty());
+
+            // Restore the backup over a DB that changed since
+            let backup_path = dir.join(&backup.file_name);
+            check_backup(&backup_path).await.unwrap();
+            insert_track(db, "Newer Track").await;
+            temp.db.pool.close().await;
+            stage_restore(&temp.path, &backup_path).await.unwrap();
+            apply_staged_restore(&temp.path).unwrap();
+            assert!(!staged_restore_path(&temp.path).exists());
+
+            let restored = DatabaseClient {
+                pool: SqlitePool::connect_with(connect_options(&temp.path))
+                    .await
+                    .unwrap(),
+            };
+            assert_eq!(count(&restored, "tracks").await, 1);
+
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_rotate_backups() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let dir = temp.path.with_extension("backups");
+
+            let manual = temp.db.backup(&dir, false).await.unwrap();
+            let mut automatic = vec![];
+            for _ in 0..AUTO_BACKUP_COUNT + 2 {
+                automatic.push(temp.db.backup(&dir, true).await.unwrap());
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+
+            let mut expected: Vec<Backup> = automatic.into_iter().rev().collect();
+            expected.truncate(AUTO_BACKUP_COUNT);
+            expected.push(manual);
+            assert_eq!(get_backups(&dir).unwrap(), expected);
+
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_check_backup() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let dir = temp.path.with_extension("backups");
+            let backup = temp.db.backup(&dir, false).await.unwrap();
+            let backup_path = dir.join(&backup.file_name);
+
+            // A backup from a newer version
+            let mut conn =
+                SqliteConnection::connect(&format!("sqlite:{}", backup_path.to_string_lossy()))
+                    .await
+                    .unwrap();
+            sqlx::query(
+                "
+                INSERT INTO _sqlx_migrations
+                    (version, description, success, checksum, execution_time)
+                VALUES (99999999999999, 'from the future', 1, x'00', 0)
+                ",
+            )
+            .execute(&mut conn)
+            .await
+            .unwrap();
+            conn.close().await.unwrap();
+            assert!(matches!(
+                check_backup(&backup_path).await,
+                Err(SpotsError::InvalidBackup(_))
+            ));
+
+            // Not a database at all
+            std::fs::write(&backup_path, b"not a database").unwrap();
+            assert!(matches!(
+                check_backup(&backup_path).await,
+                Err(SpotsError::InvalidBackup(_))
+            ));
+
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+}
@@ -0,0 +1,899 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    path::Path,
+};
+
+use chrono::{NaiveDateTime, Utc};
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqliteRow, FromRow, Row, SqliteConnection};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        client::DatabaseClient,
+        history::import_play,
+        models::{
+            music_library::{PlayRecord, Playlist},
+            parse_timestamp,
+        },
+        playlists::{
+            insert_playlist, pinned_order, playlist_ids_by_title, playlist_order, rules_json,
+            save_pinned_order, save_playlist_order, smart_rules, MAX_PINNED_PLAYLISTS,
+        },
+        smart_playlists::SmartRules,
+        DBResult,
+    },
+    errors::SpotsError,
+};
+
+/// The version of the bundles that are exported.
+///
+/// Bump it whenever the format changes in a way older versions of the app can't read, since
+/// bundles from newer versions are rejected.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// How far apart (in seconds) the durations of two tracks can be for their tags to match.
+pub(crate) const DURATION_TOLERANCE_SECS: i64 = 2;
+
+/// A user's playlists, favorites and listening history, in a form that can be imported into
+/// the library on another machine.
+///
+/// Tracks are identified by their contents and tags instead of their file paths, which differ
+/// between machines.
+///
+/// Bundles are only stored as JSON files (see [write_bundle]), with no ZIP container, since they
+/// don't include any of the tracks' files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryBundle {
+    /// The version of the bundle's format (see [BUNDLE_VERSION]).
+    pub version: u32,
+
+    pub exported_at: NaiveDateTime,
+
+    /// The tracks the rest of the bundle refers to.
+    pub tracks: Vec<BundleTrack>,
+
+    pub playlists: Vec<BundlePlaylist>,
+
+    pub favorites: BundleFavorites,
+
+    /// The listening history (oldest first).
+    pub history: Vec<BundlePlay>,
+}
+
+/// The identity of a track in a [LibraryBundle].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleTrack {
+    /// The track's ID in the exported library, which the rest of the bundle refers to it by.
+    pub id: Uuid,
+
+    /// The SHA-256 hash of the track's file (`None` if the file was missing).
+    pub content_hash: Option<String>,
+
+    pub title: String,
+
+    pub album: Option<String>,
+
+    pub artists: Vec<String>,
+
+    pub duration_secs: Option<i64>,
+}
+
+/// A playlist in a [LibraryBundle].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundlePlaylist {
+    pub title: String,
+
+    /// The playlist's position among the pinned playlists (`None` if it isn't pinned).
+    pub pin_order: Option<usize>,
+
+    /// The rules of a smart playlist, whose tracks are computed on the target library instead
+    /// of being exported.
+    pub smart_rules: Option<SmartRules>,
+
+    /// The IDs of the playlist's tracks (in order).
+    pub tracks: Vec<Uuid>,
+}
+
+/// The favorites in a [LibraryBundle]. Albums and artists are identified by their names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleFavorites {
+    pub tracks: Vec<Favorited<Uuid>>,
+
+    pub albums: Vec<Favorited<BundleAlbum>>,
+
+    pub artists: Vec<Favorited<String>>,
+}
+
+/// An album in a [LibraryBundle], identified by its title and artist (like albums in the
+/// library).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleAlbum {
+    pub title: String,
+
+    pub artist: Option<String>,
+}
+
+/// A favorited item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorited<T> {
+    pub item: T,
+
+    /// When the item was favorited (unknown for tracks favorited before this was recorded).
+    pub favorited_at: Option<NaiveDateTime>,
+}
+
+/// A play in the listening history of a [LibraryBundle].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundlePlay {
+    /// The ID of the track that was played.
+    pub track: Uuid,
+
+    pub started_at: NaiveDateTime,
+
+    pub ms_played: u64,
+
+    pub is_completed: bool,
+}
+
+/// What was imported from a [LibraryBundle].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleImportReport {
+    /// The number of playlists that were created (ones with the same title as one of the user's
+    /// playlists are skipped).
+    pub playlists: usize,
+
+    /// The number of favorites that were added (ones that were already favorited are skipped).
+    pub favorites: usize,
+
+    /// The number of plays that were added to the history (ones that are already in it are
+    /// skipped).
+    pub plays: usize,
+
+    /// The bundle's tracks that aren't in the library. They are left out of the playlists,
+    /// favorites and history.
+    pub unresolved_tracks: Vec<BundleTrack>,
+
+    /// The favorited albums that aren't in the library.
+    pub unresolved_albums: Vec<BundleAlbum>,
+
+    /// The favorited artists that aren't in the library.
+    pub unresolved_artists: Vec<String>,
+}
+
+/// Database operations for moving a user's data between libraries with a [LibraryBundle].
+pub trait BundleExt {
+    /// Exports the user's playlists, favorites and listening history.
+    ///
+    /// # Note
+    /// The files of the exported tracks are hashed if they haven't been yet.
+    async fn export_bundle(&self, user_id: Uuid) -> DBResult<LibraryBundle>;
+
+    /// Imports the bundle's playlists, favorites and listening history for the user.
+    ///
+    /// The bundle's tracks are resolved by the hash of their contents, falling back to their
+    /// tags (title, album, artists and duration). Playlists with the same title as one of the
+    /// user's aren't imported again. Pinned playlists are pinned after the user's own, up to
+    /// [MAX_PINNED_PLAYLISTS]. Returns [SpotsError::UnsupportedBundleVersion] for a
+    /// bundle from a newer version of the app.
+    async fn import_bundle(
+        &self,
+        user_id: Uuid,
+        bundle: &LibraryBundle,
+    ) -> DBResult<BundleImportReport>;
+}
+
+impl BundleExt for DatabaseClient {
+    async fn export_bundle(&self, user_id: Uuid) -> DBResult<LibraryBundle> {
+        let mut conn = self.pool.acquire().await?;
+
+        // Playlists
+        let user_playlists: Vec<Playlist> =
+            sqlx::query_as("SELECT * FROM playlists WHERE user_id = $1 ORDER BY created_at")
+                .bind(user_id.to_string())
+                .fetch_all(&mut *conn)
+                .await?;
+        let pinned = pinned_order(&mut conn, user_id).await?;
+        let mut playlists = vec![];
+        for playlist in user_playlists {
+            let smart_rules = smart_rules(&mut conn, playlist.id).await?;
+            let tracks = if smart_rules.is_some() {
+                vec![]
+            } else {
+                playlist_order(&mut conn, playlist.id).await?
+            };
+            playlists.push(BundlePlaylist {
+                title: playlist.title,
+                pin_order: pinned.iter().position(|id| *id == playlist.id),
+                smart_rules,
+                tracks,
+            });
+        }
+
+        // Favorites
+        let favorites = BundleFavorites {
+            tracks: favorites(
+                &mut conn,
+                "SELECT track_id, favorited_at FROM favorited_tracks WHERE user_id = $1",
+                user_id,
+                |row| {
+                    let track_id: String = row.try_get("track_id")?;
+                    Uuid::parse_str(&track_id).map_err(|e| sqlx::Error::Decode(e.into()))
+                },
+            )
+            .await?,
+            albums: favorites(
+                &mut conn,
+                "
+                SELECT a.title, a.artist, fa.favorited_at
+                FROM favorited_albums fa
+                JOIN albums a ON a.id = fa.album_id
+                WHERE fa.user_id = $1
+                ",
+                user_id,
+                |row| {
+                    Ok(BundleAlbum {
+                        title: row.try_get("title")?,
+                        artist: row.try_get("artist")?,
+                    })
+                },
+            )
+            .await?,
+            artists: favorites(
+                &mut conn,
+                "
+                SELECT a.name, fa.favorited_at
+                FROM favorited_artists fa
+                JOIN artists a ON a.id = fa.artist_id
+                WHERE fa.user_id = $1
+                ",
+                user_id,
+                |row| row.try_get("name"),
+            )
+            .await?,
+        };
+
+        // History
+        let history: Vec<PlayRecord> =
+            sqlx::query_as("SELECT * FROM play_history WHERE user_id = $1 ORDER BY started_at")
+                .bind(user_id.to_string())
+                .fetch_all(&mut *conn)
+                .await?;
+        let history: Vec<BundlePlay> = history
+            .into_iter()
+            .map(|play| BundlePlay {
+                track: play.track_id,
+                started_at: play.started_at,
+                ms_played: play.ms_played,
+                is_completed: play.is_completed,
+            })
+            .collect();
+        drop(conn);
+
+        // The tracks that are referred to
+        let referenced: HashSet<Uuid> = playlists
+            .iter()
+            .flat_map(|playlist| playlist.tracks.iter().copied())
+            .chain(favorites.tracks.iter().map(|favorite| favorite.item))
+            .chain(history.iter().map(|play| play.track))
+            .collect();
+        let mut tracks = vec![];
+        for mut track in local_tracks(self).await? {
+            if referenced.contains(&track.id) {
+                hash_track(self, &mut track).await?;
+                tracks.push(BundleTrack {
+                    id: track.id,
+                    content_hash: track.content_hash,
+                    title: track.title,
+                    album: track.album,
+                    artists: track.artists,
+                    duration_secs: track.duration_secs,
+                });
+            }
+        }
+
+        Ok(LibraryBundle {
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now().naive_local(),
+            tracks,
+            playlists,
+            favorites,
+            history,
+        })
+    }
+
+    async fn import_bundle(
+        &self,
+        user_id: Uuid,
+        bundle: &LibraryBundle,
+    ) -> DBResult<BundleImportReport> {
+        if bundle.version > BUNDLE_VERSION {
+            return Err(SpotsError::UnsupportedBundleVersion(bundle.version));
+        }
+
+        let resolved = resolve_tracks(self, &bundle.tracks).await?;
+        let mut report = BundleImportReport {
+            unresolved_tracks: bundle
+                .tracks
+                .iter()
+                .filter(|track| !resolved.contains_key(&track.id))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut tx = self.begin_write().await?;
+        let now = Utc::now().naive_local();
+
+        // Playlists
+        let existing = playlist_ids_by_title(&mut tx, user_id).await?;
+        let mut pinned = pinned_order(&mut tx, user_id).await?;
+        let mut new_pins = vec![];
+        for playlist in &bundle.playlists {
+            // A playlist that was already imported is only pinned (if it isn't yet)
+            if let Some(&id) = existing.get(&playlist.title) {
+                if let Some(pin_order) = playlist.pin_order.filter(|_| !pinned.contains(&id)) {
+                    new_pins.push((pin_order, id));
+                }
+                continue;
+            }
+
+            let created = insert_playlist(&mut tx, user_id, &playlist.title).await?;
+            if let Some(rules) = &playlist.smart_rules {
+                sqlx::query("INSERT INTO smart_playlists (playlist_id, rules) VALUES ($1, $2)")
+                    .bind(created.id.to_string())
+                    .bind(rules_json(rules)?)
+                    .execute(&mut *tx)
+                    .await?;
+            } else {
+                let mut track_ids: Vec<Uuid> = vec![];
+                for track_id in playlist.tracks.iter().filter_map(|id| resolved.get(id)) {
+                    if !track_ids.contains(track_id) {
+                        track_ids.push(*track_id);
+                    }
+                }
+                save_playlist_order(&mut tx, created.id, &track_ids).await?;
+            }
+            if let Some(pin_order) = playlist.pin_order {
+                new_pins.push((pin_order, created.id));
+            }
+            report.playlists += 1;
+        }
+        new_pins.sort();
+        pinned.extend(new_pins.into_iter().map(|(_, playlist_id)| playlist_id));
+        pinned.truncate(MAX_PINNED_PLAYLISTS);
+        save_pinned_order(&mut tx, user_id, &pinned).await?;
+
+        // Favorites
+        for favorite in &bundle.favorites.tracks {
+            let Some(track_id) = resolved.get(&favorite.item) else {
+                continue;
+            };
+            let result = sqlx::query(
+                "
+                INSERT OR IGNORE INTO favorited_tracks (user_id, track_id, favorited_at)
+                VALUES ($1, $2, $3)
+                ",
+            )
+            .bind(user_id.to_string())
+            .bind(track_id.to_string())
+            .bind(favorite.favorited_at.map(|at| at.to_string()))
+            .execute(&mut *tx)
+            .await?;
+            report.favorites += result.rows_affected() as usize;
+        }
+        let mut album_ids = vec![];
+        for favorite in &bundle.favorites.albums {
+            // Albums are matched like when tracks are imported (see `upsert_album`)
+            let album = &favorite.item;
+            let id: Option<String> = sqlx::query_scalar(
+                "
+                SELECT id
+                FROM albums
+                WHERE title = $1 COLLATE NOCASE
+                    AND (artist IS $2 COLLATE NOCASE OR artist IS NULL)
+                ORDER BY artist IS NULL
+                LIMIT 1
+                ",
+            )
+            .bind(&album.title)
+            .bind(&album.artist)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match id {
+                Some(id) => album_ids.push((id, favorite.favorited_at)),
+                None => report.unresolved_albums.push(album.clone()),
+            }
+        }
+        let mut artist_ids = vec![];
+        for favorite in &bundle.favorites.artists {
+            let id: Option<String> =
+                sqlx::query_scalar("SELECT id FROM artists WHERE name = $1 COLLATE NOCASE")
+                    .bind(&favorite.item)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+            match id {
+                Some(id) => artist_ids.push((id, favorite.favorited_at)),
+                None => report.unresolved_artists.push(favorite.item.clone()),
+            }
+        }
+        for (ids, table, column) in [
+            (album_ids, "favorited_albums", "album_id"),
+            (artist_ids, "favorited_artists", "artist_id"),
+        ] {
+            for (id, favorited_at) in ids {
+                let result = sqlx::query(&format!(
+                    "
+                    INSERT OR IGNORE INTO {table} (user_id, {column}, favorited_at)
+                    VALUES ($1, $2, $3)
+                    "
+                ))
+                .bind(user_id.to_string())
+                .bind(id)
+                .bind(favorited_at.unwrap_or(now).to_string())
+                .execute(&mut *tx)
+                .await?;
+                report.favorites += result.rows_affected() as usize;
+            }
+        }
+
+        // History
+        for play in &bundle.history {
+            let Some(track_id) = resolved.get(&play.track) else {
+                continue;
+            };
+            let started_at = play.started_at.to_string();
+            if import_play(
+                &mut tx,
+                user_id,
+                *track_id,
+                &started_at,
+                play.ms_played,
+                play.is_completed,
+            )
+            .await?
+            {
+                report.plays += 1;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(report)
+    }
+}
+
+/// Writes the bundle to the file at `path` as JSON.
+pub fn write_bundle(path: &Path, bundle: &LibraryBundle) -> DBResult<()> {
+    let json =
+        serde_json::to_vec_pretty(bundle).map_err(|e| SpotsError::InvalidBundle(e.to_string()))?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// Reads the bundle from the JSON file at `path`.
+pub fn read_bundle(path: &Path) -> DBResult<LibraryBundle> {
+    let json = std::fs::read(path)?;
+    serde_json::from_slice(&json).map_err(|e| SpotsError::InvalidBundle(e.to_string()))
+}
+
+/// The user's favorited items (by their names or IDs) and when they were favorited.
+///
+/// The `sql` selects the item's columns, which `item` reads, along with `favorited_at`.
+async fn favorites<T>(
+    conn: &mut SqliteConnection,
+    sql: &str,
+    user_id: Uuid,
+    item: impl Fn(&SqliteRow) -> Result<T, sqlx::Error>,
+) -> DBResult<Vec<Favorited<T>>> {
+    let rows = sqlx::query(sql)
+        .bind(user_id.to_string())
+        .fetch_all(&mut *conn)
+        .await?;
+    rows.iter()
+        .map(|row| {
+            let favorited_at: Option<String> = row.try_get("favorited_at")?;
+            Ok(Favorited {
+                item: item(row)?,
+                favorited_at: favorited_at.and_then(|at| parse_timestamp(&at).ok()),
+            })
+        })
+        .collect()
+}
+
+/// A track in the library along with its tags, for matching it to a [BundleTrack] (or an entry
+/// of a playlist file).
+pub(crate) struct LocalTrack {
+    pub(crate) id: Uuid,
+    pub(crate) file_path: String,
+    pub(crate) is_missing: bool,
+    pub(crate) content_hash: Option<String>,
+    pub(crate) title: String,
+    pub(crate) album: Option<String>,
+    pub(crate) artists: Vec<String>,
+    pub(crate) duration_secs: Option<i64>,
+}
+
+impl<'r> FromRow<'r, SqliteRow> for LocalTrack {
+    fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+        let id: &str = row.try_get("id")?;
+        let artists: Option<String> = row.try_get("artists")?;
+        Ok(Self {
+            id: id
+                .parse()
+                .map_err(|e: uuid::Error| sqlx::Error::Decode(e.into()))?,
+            file_path: row.try_get("file_path")?,
+            is_missing: row.try_get("is_missing")?,
+            content_hash: row.try_get("content_hash")?,
+            title: row.try_get("title")?,
+            album: row.try_get("album")?,
+            artists: artists
+                .map(|artists| artists.split('\u{1f}').map(String::from).collect())
+                .unwrap_or_default(),
+            duration_secs: row.try_get("duration_secs")?,
+        })
+    }
+}
+
+impl LocalTrack {
+    /// Whether the track's tags are the same as the bundle track's (ignoring case), and their
+    /// durations are about the same.
+    fn matches_tags(&self, track: &BundleTrack) -> bool {
+        let normalize = |text: &str| text.trim().to_lowercase();
+        let artists = |artists: &[String]| {
+            let mut artists: Vec<String> = artists.iter().map(|name| normalize(name)).collect();
+            artists.sort();
+            artists
+        };
+        let durations_match = match (self.duration_secs, track.duration_secs) {
+            (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE_SECS,
+            _ => true,
+        };
+
+        normalize(&self.title) == normalize(&track.title)
+            && self.album.as_deref().map(normalize) == track.album.as_deref().map(normalize)
+            && artists(&self.artists) == artists(&track.artists)
+            && durations_match
+    }
+}
+
+/// Gets all of the tracks in the library with their tags.
+pub(crate) async fn local_tracks(db: &DatabaseClient) -> DBResult<Vec<LocalTrack>> {
+    let tracks = sqlx::query_as(
+        "
+        SELECT
+            t.id,
+            t.file_path,
+            t.is_missing,
+            t.content_hash,
+            t.title,
+            t.duration_secs,
+            al.title AS album,
+            (
+                SELECT group_concat(a.name, char(31))
+                FROM track_artists ta
+                JOIN artists a ON a.id = ta.artist_id
+                WHERE ta.track_id = t.id
+            ) AS artists
+        FROM tracks t
+        LEFT JOIN albums al ON al.id = t.album_id
+        ORDER BY t.created_at, t.id
+        ",
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    Ok(tracks)
+}
+
+/// Hashes the track's file if it hasn't been hashed yet, saving the hash.
+///
+/// The hash stays unknown if the file can't be read.
+async fn hash_track(db: &DatabaseClient, track: &mut LocalTrack) -> DBResult<()> {
+    if track.content_hash.is_some() || track.is_missing {
+        return Ok(());
+    }
+
+    let file_path = track.file_path.clone();
+    let hash = tauri::async_runtime::spawn_blocking(move || content_hash(Path::new(&file_path)))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    match hash {
+        Ok(hash) => {
+            sqlx::query("UPDATE tracks SET content_hash = $1 WHERE id = $2")
+                .bind(&hash)
+                .bind(track.id.to_string())
+                .execute(&db.pool)
+                .await?;
+            track.content_hash = Some(hash);
+        }
+        Err(e) => warn!(
+            file = track.file_path,
+            error = e.to_string(),
+            "Unable to hash the track's file"
+        ),
+    }
+    Ok(())
+}
+
+/// Gets the hex encoded SHA-256 hash of the file's contents.
+fn content_hash(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut context = Context::new(&SHA256);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(context
+        .finish()
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Finds the tracks in the library that the bundle's tracks are, mapping the IDs of the
+/// bundle's tracks to the IDs of the library's.
+async fn resolve_tracks(
+    db: &DatabaseClient,
+    tracks: &[BundleTrack],
+) -> DBResult<HashMap<Uuid, Uuid>> {
+    let mut local: Vec<LocalTrack> = local_tracks(db)
+        .await?
+        .into_iter()
+        .filter(|track| !track.is_missing)
+        .collect();
+
+    // Only hash the library if some of the tracks can't be found by the hashes that are known
+    let known_hashes: HashSet<&str> = local
+        .iter()
+        .filter_map(|track| track.content_hash.as_deref())
+        .collect();
+    let hash_library = tracks.iter().any(|track| {
+        track
+            .content_hash
+            .as_deref()
+            .is_some_and(|hash| !known_hashes.contains(hash))
+    });
+    if hash_library {
+        for track in &mut local {
+            hash_track(db, track).await?;
+        }
+    }
+
+    let by_hash: HashMap<&str, Uuid> = local
+        .iter()
+        .filter_map(|track| Some((track.content_hash.as_deref()?, track.id)))
+        .collect();
+    let resolved = tracks
+        .iter()
+        .filter_map(|track| {
+            let by_content = track
+                .content_hash
+                .as_deref()
+                .and_then(|hash| by_hash.get(hash).copied());
+            let by_tags = || {
+                local
+                    .iter()
+                    .find(|local| local.matches_tags(track))
+                    .map(|local| local.id)
+            };
+            Some((track.id, by_content.or_else(by_tags)?))
+        })
+        .collect();
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::database::{
+        favorites::{FavoriteExt, FavoriteKind},
+        history::{HistoryExt, NewPlay},
+        library::{LibraryExt, NewTrack},
+        playlists::PlaylistExt,
+        test_utils::{count, TempDb},
+        tracks::TrackExt,
+        users::UserExt,
+    };
+
+    /// Writes an audio "file" and adds it to the library.
+    async fn add_track(
+        db: &DatabaseClient,
+        user_id: Uuid,
+        dir: &Path,
+        title: &str,
+        contents: &[u8],
+    ) -> Uuid {
+        let path = dir.join(format!("{title}.mp3"));
+        std::fs::write(&path, contents).unwrap();
+        let track = NewTrack {
+            file_path: path.to_string_lossy().to_string(),
+            title: title.to_string(),
+            album: Some(String::from("Album")),
+            artists: vec![String::from("Artist")],
+            duration_secs: Some(180),
+            ..Default::default()
+        };
+        db.upsert_track(user_id, track).await.unwrap().id
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spots-bundle-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let dir = temp_dir();
+        let path = dir.join("empty");
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(
+            content_hash(&path).unwrap(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        tauri::async_runtime::block_on(async {
+            // The library that is exported
+            let (source, source_dir) = (TempDb::new().await, temp_dir());
+            let db = &source.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let same = add_track(db, user_id, &source_dir, "Same", b"same").await;
+            let retagged = add_track(db, user_id, &source_dir, "Old Title", b"retagged").await;
+            let moved = add_track(db, user_id, &source_dir, "Moved", b"moved").await;
+            let absent = add_track(db, user_id, &source_dir, "Absent", b"absent").await;
+
+            let playlist = db.create_playlist(user_id, "Mix").await.unwrap();
+            db.add_playlist_tracks(playlist.id, &[absent, retagged, same], None)
+                .await
+                .unwrap();
+            db.pin_playlist(user_id, playlist.id).await.unwrap();
+            db.toggle_favorite(user_id, FavoriteKind::Track, moved)
+                .await
+                .unwrap();
+            let album_id = db.get_track(same).await.unwrap().unwrap().album_id.unwrap();
+            db.toggle_favorite(user_id, FavoriteKind::Album, album_id)
+                .await
+                .unwrap();
+            let play = NewPlay {
+                track_id: same,
+                playlist_id: None,
+                started_at: Utc::now().naive_local(),
+                ms_played: 180_000,
+            };
+            db.record_play(user_id, play).await.unwrap();
+
+            let bundle = db.export_bundle(user_id).await.unwrap();
+            assert_eq!(bundle.version, BUNDLE_VERSION);
+            assert_eq!(bundle.tracks.len(), 4);
+            assert!(bundle
+                .tracks
+                .iter()
+                .all(|track| track.content_hash.is_some()));
+            let path = source_dir.join("bundle.json");
+            write_bundle(&path, &bundle).unwrap();
+            let bundle = read_bundle(&path).unwrap();
+
+            // The library on the other machine, where one track was retagged, one has
+            // different contents (but the same tags) and one doesn't exist. Another artist has
+            // an album with the same title, which was added first.
+            let (target, target_dir) = (TempDb::new().await, temp_dir());
+            let db = &target.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_album = NewTrack {
+                file_path: target_dir.join("Other.mp3").to_string_lossy().to_string(),
+                title: String::from("Other"),
+                album: Some(String::from("Album")),
+                artists: vec![String::from("Other Artist")],
+                ..Default::default()
+            };
+            db.upsert_track(user_id, other_album).await.unwrap();
+            let new_same = add_track(db, user_id, &target_dir, "Same", b"same").await;
+            let new_retagged = add_track(db, user_id, &target_dir, "New Title", b"retagged").await;
+            let new_moved = add_track(db, user_id, &target_dir, "Moved", b"re-encoded").await;
+
+            let report = db.import_bundle(user_id, &bundle).await.unwrap();
+            assert_eq!(report.playlists, 1);
+            assert_eq!(report.favorites, 2);
+            assert_eq!(report.plays, 1);
+            let unresolved: Vec<&str> = report
+                .unresolved_tracks
+                .iter()
+                .map(|track| track.title.as_str())
+                .collect();
+            assert_eq!(unresolved, vec!["Absent"]);
+
+            let pinned_ids = || async {
+                let pinned = db.get_pinned_playlists(user_id).await.unwrap();
+                pinned
+                    .into_iter()
+                    .map(|playlist| playlist.id)
+                    .collect::<Vec<_>>()
+            };
+            let pinned = db.get_pinned_playlists(user_id).await.unwrap();
+            assert_eq!(pinned.len(), 1);
+            assert_eq!(pinned[0].title, "Mix");
+            assert_eq!(
+                db.get_playlist_track_ids(pinned[0].id).await.unwrap(),
+                vec![new_retagged, new_same]
+            );
+            assert_eq!(db.get_play_count(user_id, new_same).await.unwrap(), 1);
+            let favorited: Vec<String> =
+                sqlx::query_scalar("SELECT track_id FROM favorited_tracks WHERE user_id = $1")
+                    .bind(user_id.to_string())
+                    .fetch_all(&db.pool)
+                    .await
+                    .unwrap();
+            assert_eq!(favorited, vec![new_moved.to_string()]);
+            let favorited: Vec<String> =
+                sqlx::query_scalar("SELECT album_id FROM favorited_albums WHERE user_id = $1")
+                    .bind(user_id.to_string())
+                    .fetch_all(&db.pool)
+                    .await
+                    .unwrap();
+            let album_id = db.get_track(new_same).await.unwrap().unwrap().album_id;
+            assert_eq!(favorited, vec![album_id.unwrap().to_string()]);
+
+            // Importing the bundle again doesn't duplicate the playlists, pins, favorites or
+            // history
+            let report = db.import_bundle(user_id, &bundle).await.unwrap();
+            assert_eq!(
+                (report.playlists, report.favorites, report.plays),
+                (0, 0, 0)
+            );
+            assert_eq!(count(db, "playlists").await, 1);
+            assert_eq!(count(db, "playlist_tracks").await, 2);
+            assert_eq!(pinned_ids().await, vec![pinned[0].id]);
+            assert_eq!(count(db, "play_history").await, 1);
+
+            // but pins the playlist again if it was unpinned
+            db.unpin_playlist(user_id, pinned[0].id).await.unwrap();
+            let report = db.import_bundle(user_id, &bundle).await.unwrap();
+            assert_eq!(report.playlists, 0);
+            assert_eq!(pinned_ids().await, vec![pinned[0].id]);
+
+            std::fs::remove_dir_all(source_dir).unwrap();
+            std::fs::remove_dir_all(target_dir).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_import_newer_bundle() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let user_id = temp.db.create_user("user", "hash").await.unwrap().id;
+            let bundle = LibraryBundle {
+                version: BUNDLE_VERSION + 1,
+                exported_at: Utc::now().naive_local(),
+                tracks: vec![],
+                playlists: vec![],
+                favorites: BundleFavorites::default(),
+                history: vec![],
+            };
+            assert!(matches!(
+                temp.db.import_bundle(user_id, &bundle).await,
+                Err(SpotsError::UnsupportedBundleVersion(_))
+            ));
+        });
+    }
+}
@@ -1,8 +1,35 @@
-use sqlx::{migrate::MigrateDatabase, sqlite::SqlitePoolOptions, Pool, Sqlite};
-use tauri::{App, Manager};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use sqlx::{
+    migrate::MigrateDatabase,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    Pool, Sqlite, Transaction,
+};
+use tauri::{App, AppHandle, Manager};
 use tracing::{error, info, span, Level};
 
+use crate::{
+    database::{backup::apply_staged_restore, DBResult},
+    errors::SpotsError,
+};
+
+/// The name of the database file in the app data dir.
+const DATABASE_FILE_NAME: &str = "spots-db.sqlite";
+
+/// How long a connection waits for another connection's write lock before the query fails.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many prepared statements each connection keeps cached.
+const STATEMENT_CACHE_CAPACITY: usize = 256;
+
 /// The database client.
+///
+/// The client is cheap to clone and can be used by many tasks at once, since queries run on a
+/// pool of connections. Reads run in parallel, while writes that read before they write use
+/// [DatabaseClient::begin_write] so they happen one at a time.
 #[derive(Clone)]
 pub struct DatabaseClient {
     pub(crate) pool: Pool<Sqlite>,
@@ -20,12 +47,18 @@ impl DatabaseClient {
             e.to_string()
         })?;
         std::fs::create_dir_all(path.clone()).map_err(|e| e.to_string())?;
-        path.push("spots-db.sqlite");
+        path.push(DATABASE_FILE_NAME);
         let db_url = path
             .to_str()
             .ok_or_else(|| String::from("Invalid database URL"))?;
         info!(path = db_url, "Database path created");
 
+        // Restore the backup that was staged before the app restarted
+        apply_staged_restore(&path).map_err(|e| {
+            error!(error = e.to_string(), "Unable to restore the backup");
+            e.to_string()
+        })?;
+
         // Create database
         info!(database = db_url, "Creating database");
         Sqlite::create_database(&format!("sqlite:{}", db_url))
@@ -36,7 +69,7 @@ impl DatabaseClient {
         // Connect to database
         info!(database = db_url, "Connecting to database");
         let pool = SqlitePoolOptions::new()
-            .connect(db_url)
+            .connect_with(connect_options(&path))
             .await
             .map_err(|e| e.to_string())?;
         info!(database = db_url, "Connected to database");
@@ -51,4 +84,221 @@ impl DatabaseClient {
 
         Ok(Self { pool })
     }
+
+    /// Opens the DB file at `path` (creating it if it doesn't exist) and applies the migrations.
+    pub async fn open(path: &Path) -> DBResult<Self> {
+        let pool = SqlitePoolOptions::new()
+            .connect_with(connect_options(path).create_if_missing(true))
+            .await?;
+        sqlx::migrate!("./migrations/")
+            .run(&pool)
+            .await
+            .map_err(sqlx::Error::from)?;
+        Ok(Self { pool })
+    }
+
+    /// Begins a transaction that writes to the DB.
+    ///
+    /// The transaction takes SQLite's write lock right away (`BEGIN IMMEDIATE`), so concurrent
+    /// writes wait for each other instead of failing when a transaction that already read
+    /// tries to write.
+    pub(crate) async fn begin_write(&self) -> DBResult<Transaction<'static, Sqlite>> {
+        Ok(self.pool.begin_with("BEGIN IMMEDIATE").await?)
+    }
+}
+
+/// Gets the path of the database file in the app data dir.
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, SpotsError> {
+    let mut path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| SpotsError::AppDataDirError(e.to_string()))?;
+    path.push(DATABASE_FILE_NAME);
+    Ok(path)
+}
+
+/// The options every connection to the DB file at `path` is opened with.
+pub(crate) fn connect_options(path: &Path) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(path)
+        // Readers don't block the writer (or each other)
+        .journal_mode(SqliteJournalMode::Wal)
+        // Without this, the `ON DELETE` actions in the migrations do nothing
+        .foreign_keys(true)
+        .busy_timeout(BUSY_TIMEOUT)
+        .statement_cache_capacity(STATEMENT_CACHE_CAPACITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::database::{
+        favorites::{FavoriteExt, FavoriteKind},
+        history::{HistoryExt, NewPlay},
+        playlists::PlaylistExt,
+        queue::QueueExt,
+        smart_playlists::SmartRules,
+        test_utils::{count, insert_track, TempDb},
+        tracks::TrackExt,
+        users::UserExt,
+    };
+
+    /// Creates a user with a favorited, queued and played track, which is also in a pinned and
+    /// followed playlist (and a smart playlist).
+    async fn populate(db: &DatabaseClient) -> (Uuid, Uuid, Uuid) {
+        let user_id = db.create_user("user", "hash").await.unwrap().id;
+        let track_id = insert_track(db, "Track").await;
+
+        let playlist = db.create_playlist(user_id, "Playlist").await.unwrap();
+        db.add_playlist_tracks(playlist.id, &[track_id], None)
+            .await
+            .unwrap();
+        db.pin_playlist(user_id, playlist.id).await.unwrap();
+        db.toggle_favorite(user_id, FavoriteKind::Playlist, playlist.id)
+            .await
+            .unwrap();
+        let rules = SmartRules {
+            match_any: false,
+            rules: vec![],
+            limit: None,
+            sort: None,
+        };
+        db.create_smart_playlist(user_id, "Smart", &rules)
+            .await
+            .unwrap();
+
+        db.toggle_favorite(user_id, FavoriteKind::Track, track_id)
+            .await
+            .unwrap();
+        db.update_play_queue(user_id, |queue| {
+            queue.enqueue(&[track_id]);
+            Ok(())
+        })
+        .await
+        .unwrap();
+        db.record_play(
+            user_id,
+            NewPlay {
+                track_id,
+                playlist_id: Some(playlist.id),
+                started_at: Utc::now().naive_local(),
+                ms_played: 1_000,
+            },
+        )
+        .await
+        .unwrap();
+
+        (user_id, track_id, playlist.id)
+    }
+
+    #[test]
+    fn test_connect_options() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let mut conn = temp.db.pool.acquire().await.unwrap();
+
+            let journal_mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            assert_eq!(journal_mode, "wal");
+            let foreign_keys: bool = sqlx::query_scalar("PRAGMA foreign_keys")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            assert!(foreign_keys);
+            let busy_timeout: i64 = sqlx::query_scalar("PRAGMA busy_timeout")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            assert_eq!(busy_timeout, BUSY_TIMEOUT.as_millis() as i64);
+        });
+    }
+
+    #[test]
+    fn test_delete_playlist_cascades() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let (_, _, playlist_id) = populate(db).await;
+            assert_eq!(count(db, "playlist_tracks").await, 1);
+            assert_eq!(count(db, "pinned_playlists").await, 1);
+            assert_eq!(count(db, "followed_playlists").await, 1);
+
+            db.delete_playlist(playlist_id).await.unwrap();
+            assert_eq!(count(db, "playlist_tracks").await, 0);
+            assert_eq!(count(db, "pinned_playlists").await, 0);
+            assert_eq!(count(db, "followed_playlists").await, 0);
+
+            // Deleting a smart playlist deletes its rules
+            let smart_playlist_id: String =
+                sqlx::query_scalar("SELECT playlist_id FROM smart_playlists")
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap();
+            db.delete_playlist(smart_playlist_id.parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(count(db, "smart_playlists").await, 0);
+        });
+    }
+
+    #[test]
+    fn test_delete_track_cascades() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let (user_id, track_id, _) = populate(db).await;
+            let other_track_id = insert_track(db, "Other").await;
+            db.toggle_favorite(user_id, FavoriteKind::Track, other_track_id)
+                .await
+                .unwrap();
+
+            sqlx::query("DELETE FROM tracks WHERE id = $1")
+                .bind(track_id.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            assert_eq!(count(db, "favorited_tracks").await, 1);
+            assert_eq!(count(db, "playlist_tracks").await, 0);
+            assert_eq!(count(db, "play_queue").await, 0);
+            assert_eq!(count(db, "play_history").await, 0);
+            assert_eq!(count(db, "track_play_counts").await, 0);
+            assert!(db.get_track(other_track_id).await.unwrap().is_some());
+        });
+    }
+
+    #[test]
+    fn test_delete_user_cascades() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let (user_id, track_id, playlist_id) = populate(db).await;
+
+            sqlx::query("DELETE FROM users WHERE id = $1")
+                .bind(user_id.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            for table in [
+                "favorited_tracks",
+                "pinned_playlists",
+                "followed_playlists",
+                "play_queue",
+                "play_queue_state",
+                "play_history",
+                "track_play_counts",
+            ] {
+                assert_eq!(count(db, table).await, 0, "{table}");
+            }
+
+            // The user's tracks and playlists are kept
+            assert!(db.get_track(track_id).await.unwrap().is_some());
+            let playlist = db.get_playlist(playlist_id).await.unwrap().unwrap();
+            assert_eq!(playlist.user_id, None);
+        });
+    }
 }
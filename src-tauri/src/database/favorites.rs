@@ -0,0 +1,240 @@
+use chrono::Utc;
+use sqlx::Sqlite;
+use uuid::Uuid;
+
+use crate::database::{
+    client::DatabaseClient,
+    models::music_library::{Album, Artist, Playlist},
+    DBResult,
+};
+
+/// The kinds of items a user can favorite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FavoriteKind {
+    Track,
+    Album,
+    Artist,
+    Playlist,
+}
+
+impl FavoriteKind {
+    /// The table the items of this kind are stored in.
+    fn item_table(&self) -> &'static str {
+        match self {
+            FavoriteKind::Track => "tracks",
+            FavoriteKind::Album => "albums",
+            FavoriteKind::Artist => "artists",
+            FavoriteKind::Playlist => "playlists",
+        }
+    }
+
+    /// The table the favorites are stored in, and the column of the favorited item's ID.
+    fn table(&self) -> (&'static str, &'static str) {
+        match self {
+            FavoriteKind::Track => ("favorited_tracks", "track_id"),
+            FavoriteKind::Album => ("favorited_albums", "album_id"),
+            FavoriteKind::Artist => ("favorited_artists", "artist_id"),
+            FavoriteKind::Playlist => ("followed_playlists", "playlist_id"),
+        }
+    }
+}
+
+/// Database operations for the user's favorite tracks, albums, artists and (followed) playlists.
+pub trait FavoriteExt {
+    /// Favorites the item, or unfavorites it if it was already favorited.
+    ///
+    /// Returns whether the item is favorited now, or [sqlx::Error::RowNotFound] if the item
+    /// doesn't exist.
+    async fn toggle_favorite(&self, user_id: Uuid, kind: FavoriteKind, id: Uuid) -> DBResult<bool>;
+
+    /// Gets the user's favorited albums (most recently favorited first).
+    async fn get_favorited_albums(&self, user_id: Uuid) -> DBResult<Vec<Album>>;
+
+    /// Gets the user's favorited artists (most recently favorited first).
+    async fn get_favorited_artists(&self, user_id: Uuid) -> DBResult<Vec<Artist>>;
+
+    /// Gets the playlists the user follows (most recently followed first).
+    async fn get_followed_playlists(&self, user_id: Uuid) -> DBResult<Vec<Playlist>>;
+}
+
+impl FavoriteExt for DatabaseClient {
+    async fn toggle_favorite(&self, user_id: Uuid, kind: FavoriteKind, id: Uuid) -> DBResult<bool> {
+        let (table, column) = kind.table();
+        let mut tx = self.begin_write().await?;
+
+        let exists: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {} WHERE id = $1)",
+            kind.item_table()
+        ))
+        .bind(id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+        if !exists {
+            return Err(sqlx::Error::RowNotFound.into());
+        }
+
+        let removed = sqlx::query(&format!(
+            "DELETE FROM {table} WHERE user_id = $1 AND {column} = $2"
+        ))
+        .bind(user_id.to_string())
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if !removed {
+            sqlx::query(&format!(
+                "INSERT INTO {table} (user_id, {column}, favorited_at) VALUES ($1, $2, $3)"
+            ))
+            .bind(user_id.to_string())
+            .bind(id.to_string())
+            .bind(Utc::now().naive_local().to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(!removed)
+    }
+
+    async fn get_favorited_albums(&self, user_id: Uuid) -> DBResult<Vec<Album>> {
+        let albums = sqlx::query_as::<Sqlite, Album>(
+            "
+            SELECT a.*
+            FROM albums a
+            JOIN favorited_albums fa ON a.id = fa.album_id
+            WHERE fa.user_id = $1
+            ORDER BY fa.favorited_at DESC
+            ",
+        )
+        .bind(user_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(albums)
+    }
+
+    async fn get_favorited_artists(&self, user_id: Uuid) -> DBResult<Vec<Artist>> {
+        let artists = sqlx::query_as::<Sqlite, Artist>(
+            "
+            SELECT a.*
+            FROM artists a
+            JOIN favorited_artists fa ON a.id = fa.artist_id
+            WHERE fa.user_id = $1
+            ORDER BY fa.favorited_at DESC
+            ",
+        )
+        .bind(user_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(artists)
+    }
+
+    async fn get_followed_playlists(&self, user_id: Uuid) -> DBResult<Vec<Playlist>> {
+        let playlists = sqlx::query_as::<Sqlite, Playlist>(
+            "
+            SELECT p.*
+            FROM playlists p
+            JOIN followed_playlists fp ON p.id = fp.playlist_id
+            WHERE fp.user_id = $1
+            ORDER BY fp.favorited_at DESC
+            ",
+        )
+        .bind(user_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(playlists)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::{
+            library::{LibraryExt, NewTrack},
+            playlists::PlaylistExt,
+            test_utils::{count, TempDb},
+            users::UserExt,
+        },
+        errors::SpotsError,
+    };
+
+    #[test]
+    fn test_toggle_favorites() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+            let mut tracks = vec![];
+            for (file_path, album, artist) in [
+                ("/music/1.mp3", "First Album", "First Artist"),
+                ("/music/2.mp3", "Second Album", "Second Artist"),
+            ] {
+                let track = NewTrack {
+                    file_path: file_path.to_string(),
+                    title: file_path.to_string(),
+                    album: Some(album.to_string()),
+                    artists: vec![artist.to_string()],
+                    ..Default::default()
+                };
+                tracks.push(db.upsert_track(user_id, track).await.unwrap());
+            }
+            let artist_ids: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM artists ORDER BY name")
+                .fetch_all(&db.pool)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|id: String| Uuid::parse_str(&id).unwrap())
+                .collect();
+            let playlist = db.create_playlist(other_id, "Mix").await.unwrap();
+
+            // Toggling on and off
+            let toggle = |kind, id| db.toggle_favorite(user_id, kind, id);
+            assert!(toggle(FavoriteKind::Track, tracks[0].id).await.unwrap());
+            assert!(!toggle(FavoriteKind::Track, tracks[0].id).await.unwrap());
+            assert!(toggle(FavoriteKind::Track, tracks[0].id).await.unwrap());
+            assert_eq!(count(db, "favorited_tracks").await, 1);
+
+            // Items that don't exist can't be favorited
+            for kind in [
+                FavoriteKind::Track,
+                FavoriteKind::Album,
+                FavoriteKind::Artist,
+                FavoriteKind::Playlist,
+            ] {
+                assert!(matches!(
+                    toggle(kind, Uuid::new_v4()).await,
+                    Err(SpotsError::DatabaseError(sqlx::Error::RowNotFound))
+                ));
+            }
+
+            // The lists are most recently favorited first
+            for track in &tracks {
+                let album_id = track.album_id.unwrap();
+                assert!(toggle(FavoriteKind::Album, album_id).await.unwrap());
+            }
+            for artist_id in &artist_ids {
+                assert!(toggle(FavoriteKind::Artist, *artist_id).await.unwrap());
+            }
+            assert!(toggle(FavoriteKind::Playlist, playlist.id).await.unwrap());
+
+            let albums = db.get_favorited_albums(user_id).await.unwrap();
+            let album_ids: Vec<Option<Uuid>> = albums.iter().rev().map(|a| Some(a.id)).collect();
+            assert_eq!(album_ids, vec![tracks[0].album_id, tracks[1].album_id]);
+            let artists = db.get_favorited_artists(user_id).await.unwrap();
+            let ids: Vec<Uuid> = artists.iter().rev().map(|artist| artist.id).collect();
+            assert_eq!(ids, artist_ids);
+            let playlists = db.get_followed_playlists(user_id).await.unwrap();
+            assert_eq!(playlists.len(), 1);
+            assert_eq!(playlists[0].id, playlist.id);
+
+            // Other users' favorites are separate
+            assert!(db.get_favorited_albums(other_id).await.unwrap().is_empty());
+            assert!(toggle(FavoriteKind::Album, tracks[0].album_id.unwrap())
+                .await
+                .is_ok_and(|favorited| !favorited));
+            assert_eq!(db.get_favorited_albums(user_id).await.unwrap().len(), 1);
+        });
+    }
+}
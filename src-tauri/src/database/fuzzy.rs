@@ -0,0 +1,233 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use sqlx::Sqlite;
+use uuid::Uuid;
+
+use crate::database::{
+    albums::AlbumExt,
+    client::DatabaseClient,
+    models::music_library::{Artist, Genre},
+    playlists::PlaylistExt,
+    search::SearchResult,
+    tracks::TrackExt,
+    DBResult,
+};
+
+/// The min similarity (from `0.0` to `1.0`) for a name to match the query.
+const MIN_SIMILARITY: f64 = 0.3;
+
+/// The max number of "did you mean" suggestions.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Characters with diacritics, and the letters they are folded into.
+const DIACRITICS: [(&str, char); 14] = [
+    ("àáâãäåāăą", 'a'),
+    ("çćĉċč", 'c'),
+    ("ďđ", 'd'),
+    ("èéêëēĕėęě", 'e'),
+    ("ĝğġģ", 'g'),
+    ("ìíîïĩīĭįı", 'i'),
+    ("ĺļľŀł", 'l'),
+    ("ñńņňŉ", 'n'),
+    ("òóôõöøōŏő", 'o'),
+    ("ŕŗř", 'r'),
+    ("śŝşšș", 's'),
+    ("ţťŧț", 't'),
+    ("ùúûüũūŭůűų", 'u'),
+    ("ýÿŷ", 'y'),
+];
+
+/// The results of a typo-tolerant search.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzySearchResults {
+    /// The items whose names are similar to the query (most similar first).
+    pub results: Vec<SearchResult>,
+
+    /// Names that are similar to the query ("did you mean ...?").
+    pub suggestions: Vec<String>,
+}
+
+/// Database operations for typo-tolerant searches of the music library.
+pub trait FuzzyExt {
+    /// Searches the names of tracks, albums, artists, playlists and genres for ones that are
+    /// similar to the `query` (e.g. "radiohed" finds "Radiohead").
+    ///
+    /// Names are compared by their trigrams, ignoring case and diacritics. Only the user's own
+    /// (or followed) playlists are searched, and at most `limit` results are returned.
+    async fn fuzzy_search(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        limit: usize,
+    ) -> DBResult<FuzzySearchResults>;
+}
+
+impl FuzzyExt for DatabaseClient {
+    async fn fuzzy_search(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        limit: usize,
+    ) -> DBResult<FuzzySearchResults> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return Ok(FuzzySearchResults {
+                results: Vec::new(),
+                suggestions: Vec::new(),
+            });
+        }
+
+        // Score every name in the search index
+        let names: Vec<(String, String, String)> = sqlx::query_as(
+            "
+            SELECT s.kind, s.item_id, s.name
+            FROM search_index s
+            WHERE s.kind != 'playlist' OR s.owner_id = $1 OR EXISTS (
+                SELECT 1
+                FROM followed_playlists fp
+                WHERE fp.playlist_id = s.item_id AND fp.user_id = $1
+            )
+            ",
+        )
+        .bind(user_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        let mut matches: Vec<(f64, String, String, String)> = names
+            .into_iter()
+            .filter_map(|(kind, item_id, name)| {
+                let score = name_similarity(&query, &normalize(&name));
+                (score >= MIN_SIMILARITY).then_some((score, kind, item_id, name))
+            })
+            .collect();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // Suggest the most similar names that aren't what the user typed
+        let mut suggestions: Vec<String> = Vec::new();
+        for (_, _, _, name) in &matches {
+            if suggestions.len() == MAX_SUGGESTIONS {
+                break;
+            }
+            if normalize(name) != query && !suggestions.contains(name) {
+                suggestions.push(name.clone());
+            }
+        }
+
+        let mut results = Vec::new();
+        for (_, kind, item_id, _) in matches.into_iter().take(limit) {
+            if let Some(result) = get_search_result(self, &kind, &item_id).await? {
+                results.push(result);
+            }
+        }
+
+        Ok(FuzzySearchResults {
+            results,
+            suggestions,
+        })
+    }
+}
+
+/// Gets the item a row of the search index refers to.
+async fn get_search_result(
+    db: &DatabaseClient,
+    kind: &str,
+    item_id: &str,
+) -> DBResult<Option<SearchResult>> {
+    let id = || Uuid::parse_str(item_id).map_err(|e| sqlx::Error::Decode(e.into()));
+    let result = match kind {
+        "track" => db.get_track(id()?).await?.map(SearchResult::Track),
+        "album" => db.get_album(id()?).await?.map(SearchResult::Album),
+        "playlist" => db.get_playlist(id()?).await?.map(SearchResult::Playlist),
+        "artist" => sqlx::query_as::<Sqlite, Artist>("SELECT * FROM artists WHERE id = $1")
+            .bind(item_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .map(SearchResult::Artist),
+        "genre" => sqlx::query_as::<Sqlite, Genre>("SELECT * FROM genres WHERE name = $1")
+            .bind(item_id)
+            .fetch_optional(&db.pool)
+            .await?
+            .map(SearchResult::Genre),
+        _ => None,
+    };
+    Ok(result)
+}
+
+/// Lowercases the text, removes diacritics and collapses everything that isn't a letter or a
+/// number into single spaces.
+pub(crate) fn normalize(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| {
+            DIACRITICS
+                .iter()
+                .find(|(chars, _)| chars.contains(c))
+                .map_or(c, |(_, folded)| *folded)
+        })
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Gets the trigrams of each word in the (normalized) text.
+///
+/// Words are padded with two spaces in front and one behind, so the start of a word weighs more
+/// than its end and even one-letter words have a trigram.
+pub(crate) fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    text.split_whitespace()
+        .flat_map(|word| {
+            let padded: Vec<char> = format!("  {} ", word).chars().collect();
+            padded
+                .windows(3)
+                .map(|w| [w[0], w[1], w[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The share of trigrams the two (normalized) texts have in common, from `0.0` to `1.0`.
+fn similarity(a: &str, b: &str) -> f64 {
+    trigram_similarity(&trigrams(a), &trigrams(b))
+}
+
+/// The share of trigrams the two sets have in common, from `0.0` to `1.0`.
+pub(crate) fn trigram_similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+/// How similar the query is to the name, or to any run of words in the name that is as long
+/// as the query (so "rapsody" matches "Bohemian Rhapsody").
+fn name_similarity(query: &str, name: &str) -> f64 {
+    let query_len = query.split_whitespace().count();
+    let words: Vec<&str> = name.split_whitespace().collect();
+    words
+        .windows(query_len.min(words.len()).max(1))
+        .map(|window| similarity(query, &window.join(" ")))
+        .fold(similarity(query, name), f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  Beyoncé -- Déjà Vu! "), "beyonce deja vu");
+        assert_eq!(normalize("AC/DC"), "ac dc");
+    }
+
+    #[test]
+    fn test_name_similarity() {
+        assert!(name_similarity("radiohed", "radiohead") >= MIN_SIMILARITY);
+        assert!(name_similarity("rapsody", "bohemian rhapsody") >= MIN_SIMILARITY);
+        assert!(name_similarity("radiohed", "portishead") < MIN_SIMILARITY);
+        assert_eq!(name_similarity("halo", "halo"), 1.0);
+    }
+}
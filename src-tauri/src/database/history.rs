@@ -0,0 +1,366 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::database::{client::DatabaseClient, models::music_library::PlayRecord, DBResult};
+
+/// How long a track has to be played for to count as a play.
+///
+/// A play counts once the track played for `min_fraction` of its duration or for `max_ms`,
+/// whichever comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayThreshold {
+    /// The fraction of the track (from `0.0` to `1.0`) that has to be played.
+    pub min_fraction: f64,
+
+    /// The time (in milliseconds) after which a play always counts.
+    pub max_ms: u64,
+}
+
+impl Default for PlayThreshold {
+    /// Half of the track or 4 minutes.
+    fn default() -> Self {
+        Self {
+            min_fraction: 0.5,
+            max_ms: 4 * 60 * 1000,
+        }
+    }
+}
+
+impl PlayThreshold {
+    /// Checks if playing a track for `ms_played` counts as a play.
+    ///
+    /// Only `max_ms` is used if the track's duration is unknown.
+    pub fn is_reached(&self, ms_played: u64, duration_ms: Option<u64>) -> bool {
+        let required_ms = match duration_ms {
+            Some(duration_ms) => {
+                let fraction_ms = (duration_ms as f64 * self.min_fraction.clamp(0.0, 1.0)) as u64;
+                fraction_ms.min(self.max_ms)
+            }
+            None => self.max_ms,
+        };
+        ms_played >= required_ms
+    }
+}
+
+/// A play that is reported to the listening history.
+#[derive(Debug, Clone)]
+pub struct NewPlay {
+    /// The track that was played.
+    pub track_id: Uuid,
+
+    /// The playlist the track was played from.
+    pub playlist_id: Option<Uuid>,
+
+    /// When the track started playing.
+    pub started_at: NaiveDateTime,
+
+    /// How long the track was played for in milliseconds.
+    pub ms_played: u64,
+}
+
+/// Database operations for the listening history ([PlayRecord]) and play counts.
+pub trait HistoryExt {
+    /// Adds the play to the user's listening history.
+    ///
+    /// If the track played long enough (see [PlayThreshold]), the user's play count of the track
+    /// goes up and the `last_played_at` of the track (and playlist) is updated.
+    async fn record_play(&self, user_id: Uuid, play: NewPlay) -> DBResult<PlayRecord>;
+
+    /// Gets the user's most recent plays (newest first).
+    async fn get_play_history(&self, user_id: Uuid, limit: u32) -> DBResult<Vec<PlayRecord>>;
+
+    /// Gets how many times the user played the track.
+    async fn get_play_count(&self, user_id: Uuid, track_id: Uuid) -> DBResult<u64>;
+
+    /// Gets the user's play threshold (the default one if it was never set).
+    async fn get_play_threshold(&self, user_id: Uuid) -> DBResult<PlayThreshold>;
+
+    /// Sets the user's play threshold.
+    async fn set_play_threshold(&self, user_id: Uuid, threshold: PlayThreshold) -> DBResult<()>;
+}
+
+impl HistoryExt for DatabaseClient {
+    async fn record_play(&self, user_id: Uuid, play: NewPlay) -> DBResult<PlayRecord> {
+        let threshold = self.get_play_threshold(user_id).await?;
+        let duration_secs: Option<i64> =
+            sqlx::query_scalar("SELECT duration_secs FROM tracks WHERE id = $1")
+                .bind(play.track_id.to_string())
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| sqlx::Error::RowNotFound)?;
+        let duration_ms = duration_secs.map(|secs| secs.max(0) as u64 * 1000);
+        let is_completed = threshold.is_reached(play.ms_played, duration_ms);
+
+        let mut tx = self.begin_write().await?;
+        let started_at = play.started_at.to_string();
+        let record = sqlx::query_as::<Sqlite, PlayRecord>(
+            "
+            INSERT INTO play_history (id, user_id, track_id, started_at, ms_played, is_completed)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            ",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(user_id.to_string())
+        .bind(play.track_id.to_string())
+        .bind(&started_at)
+        .bind(play.ms_played as i64)
+        .bind(is_completed)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if is_completed {
+            count_play(&mut tx, user_id, play.track_id, &started_at).await?;
+
+            if let Some(playlist_id) = play.playlist_id {
+                sqlx::query(
+                    "
+                    UPDATE playlists
+                    SET last_played_at = $1
+                    WHERE id = $2 AND (last_played_at IS NULL OR last_played_at < $1)
+                    ",
+                )
+                .bind(&started_at)
+                .bind(playlist_id.to_string())
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(record)
+    }
+
+    async fn get_play_history(&self, user_id: Uuid, limit: u32) -> DBResult<Vec<PlayRecord>> {
+        let history = sqlx::query_as::<Sqlite, PlayRecord>(
+            "
+            SELECT *
+            FROM play_history
+            WHERE user_id = $1
+            ORDER BY started_at DESC
+            LIMIT $2
+            ",
+        )
+        .bind(user_id.to_string())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(history)
+    }
+
+    async fn get_play_count(&self, user_id: Uuid, track_id: Uuid) -> DBResult<u64> {
+        let play_count: Option<i64> = sqlx::query_scalar(
+            "
+            SELECT play_count
+            FROM track_play_counts
+            WHERE user_id = $1 AND track_id = $2
+            ",
+        )
+        .bind(user_id.to_string())
+        .bind(track_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(play_count.unwrap_or(0).max(0) as u64)
+    }
+
+    async fn get_play_threshold(&self, user_id: Uuid) -> DBResult<PlayThreshold> {
+        let threshold: Option<(f64, i64)> = sqlx::query_as(
+            "
+            SELECT min_fraction, max_ms
+            FROM play_thresholds
+            WHERE user_id = $1
+            ",
+        )
+        .bind(user_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(threshold
+            .map(|(min_fraction, max_ms)| PlayThreshold {
+                min_fraction,
+                max_ms: max_ms.max(0) as u64,
+            })
+            .unwrap_or_default())
+    }
+
+    async fn set_play_threshold(&self, user_id: Uuid, threshold: PlayThreshold) -> DBResult<()> {
+        sqlx::query(
+            "
+            INSERT INTO play_thresholds (user_id, min_fraction, max_ms)
+            VALUES ($1, $2, $3)
+            ON CONFLICT(user_id) DO UPDATE SET
+                min_fraction = excluded.min_fraction,
+                max_ms = excluded.max_ms
+            ",
+        )
+        .bind(user_id.to_string())
+        .bind(threshold.min_fraction.clamp(0.0, 1.0))
+        .bind(threshold.max_ms as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Adds a play to the user's listening history unless they already played the track at
+/// `started_at` (e.g. it was imported before), counting it if it's completed.
+///
+/// Returns whether the play was added.
+pub(crate) async fn import_play(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    track_id: Uuid,
+    started_at: &str,
+    ms_played: u64,
+    is_completed: bool,
+) -> DBResult<bool> {
+    let result = sqlx::query(
+        "
+        INSERT INTO play_history (id, user_id, track_id, started_at, ms_played, is_completed)
+        SELECT $1, $2, $3, $4, $5, $6
+        WHERE NOT EXISTS (
+            SELECT 1
+            FROM play_history
+            WHERE user_id = $2 AND track_id = $3 AND started_at = $4
+        )
+        ",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(user_id.to_string())
+    .bind(track_id.to_string())
+    .bind(started_at)
+    .bind(ms_played as i64)
+    .bind(is_completed)
+    .execute(&mut *conn)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    if is_completed {
+        count_play(conn, user_id, track_id, started_at).await?;
+    }
+    Ok(true)
+}
+
+/// Counts a completed play of the track towards its play count and last played time.
+pub(crate) async fn count_play(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    track_id: Uuid,
+    started_at: &str,
+) -> DBResult<()> {
+    sqlx::query(
+        "
+        INSERT INTO track_play_counts (user_id, track_id, play_count, last_played_at)
+        VALUES ($1, $2, 1, $3)
+        ON CONFLICT(user_id, track_id) DO UPDATE SET
+            play_count = play_count + 1,
+            last_played_at = MAX(last_played_at, excluded.last_played_at)
+        ",
+    )
+    .bind(user_id.to_string())
+    .bind(track_id.to_string())
+    .bind(started_at)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "
+        UPDATE tracks
+        SET last_played_at = $1
+        WHERE id = $2 AND (last_played_at IS NULL OR last_played_at < $1)
+        ",
+    )
+    .bind(started_at)
+    .bind(track_id.to_string())
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::database::{
+        playlists::PlaylistExt,
+        test_utils::{count, insert_track, TempDb},
+        tracks::TrackExt,
+        users::UserExt,
+    };
+
+    #[test]
+    fn test_play_threshold() {
+        let threshold = PlayThreshold::default();
+
+        // Half of a short track
+        assert!(!threshold.is_reached(89_999, Some(180_000)));
+        assert!(threshold.is_reached(90_000, Some(180_000)));
+
+        // 4 minutes of a long track
+        assert!(!threshold.is_reached(239_999, Some(3_600_000)));
+        assert!(threshold.is_reached(240_000, Some(3_600_000)));
+
+        // Unknown duration
+        assert!(!threshold.is_reached(120_000, None));
+        assert!(threshold.is_reached(240_000, None));
+    }
+
+    #[test]
+    fn test_record_play() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let track_id = insert_track(db, "Track").await;
+            sqlx::query("UPDATE tracks SET duration_secs = 180 WHERE id = $1")
+                .bind(track_id.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            let playlist = db.create_playlist(user_id, "Playlist").await.unwrap();
+            let started_at =
+                NaiveDateTime::parse_from_str("2026-10-18 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+            let play = |started_at: NaiveDateTime, ms_played| NewPlay {
+                track_id,
+                playlist_id: Some(playlist.id),
+                started_at,
+                ms_played,
+            };
+
+            // Skipping the track adds it to the history without counting it
+            let record = db
+                .record_play(user_id, play(started_at, 30_000))
+                .await
+                .unwrap();
+            assert!(!record.is_completed);
+            assert_eq!(db.get_play_count(user_id, track_id).await.unwrap(), 0);
+            let track = db.get_track(track_id).await.unwrap().unwrap();
+            assert_eq!(track.last_played_at, None);
+
+            // Playing half of it counts
+            let later = started_at + Duration::minutes(5);
+            let record = db.record_play(user_id, play(later, 90_000)).await.unwrap();
+            assert!(record.is_completed);
+            db.record_play(user_id, play(started_at, 180_000))
+                .await
+                .unwrap();
+            assert_eq!(db.get_play_count(user_id, track_id).await.unwrap(), 2);
+            assert_eq!(count(db, "play_history").await, 3);
+
+            // The last played times only move forward
+            let track = db.get_track(track_id).await.unwrap().unwrap();
+            assert_eq!(track.last_played_at, Some(later));
+            let playlist = db.get_playlist(playlist.id).await.unwrap().unwrap();
+            assert_eq!(playlist.last_played_at, Some(later));
+
+            let history = db.get_play_history(user_id, 2).await.unwrap();
+            assert_eq!(history.len(), 2);
+            assert_eq!(history[0].started_at, later);
+        });
+    }
+}
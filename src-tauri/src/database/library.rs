@@ -0,0 +1,523 @@
+use std::path::MAIN_SEPARATOR_STR;
+
+use chrono::Utc;
+use sqlx::{Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::database::{
+    client::DatabaseClient,
+    models::music_library::{LibraryFolder, Track},
+    parse_ids, DBResult,
+};
+
+/// A track found in the music library that is ready to be imported.
+#[derive(Debug, Clone, Default)]
+pub struct NewTrack {
+    /// The path to the actual track.
+    pub file_path: String,
+
+    /// Title of the track.
+    pub title: String,
+
+    /// Title of the album the track belongs to.
+    pub album: Option<String>,
+
+    /// Name of the album's artist (the track's first artist is used if it's unknown).
+    pub album_artist: Option<String>,
+
+    /// Names of the track's artists.
+    pub artists: Vec<String>,
+
+    /// Names of the track's genres.
+    pub genres: Vec<String>,
+
+    /// The track number of this track (in its album).
+    pub track_number: Option<i64>,
+
+    /// The year this track was released.
+    pub release_year: Option<i64>,
+
+    /// The duration of the track in seconds.
+    pub duration_secs: Option<i64>,
+
+    /// The path to the thumbnail/image for track.
+    pub thumbnail_path: String,
+
+    /// The size of the track's file in bytes.
+    pub file_size: Option<i64>,
+
+    /// When the track's file was last modified.
+    pub file_modified_at: Option<String>,
+}
+
+/// Database operations for importing tracks into the music library.
+pub trait LibraryExt {
+    /// Inserts the track, or updates it if a track with the same `file_path` already exists.
+    ///
+    /// An updated track's content hash is cleared if its file's size or modification time changed
+    /// (or is unknown).
+    ///
+    /// The track's album, artists and genres are created if they don't exist yet. Albums are
+    /// identified by their title and artist, so different artists' albums with the same title
+    /// are kept apart.
+    async fn upsert_track(&self, user_id: Uuid, track: NewTrack) -> DBResult<Track>;
+
+    /// Adds the folder to the folders in the music library.
+    async fn add_library_folder(&self, user_id: Uuid, path: &str) -> DBResult<()>;
+
+    /// Gets all of the folders in the music library.
+    async fn get_library_folders(&self) -> DBResult<Vec<LibraryFolder>>;
+
+    /// Updates the paths of the tracks at `from` (a file or a directory) to be at `to` instead.
+    ///
+    /// Returns the IDs of the moved tracks.
+    async fn move_tracks(&self, from: &str, to: &str) -> DBResult<Vec<Uuid>>;
+
+    /// Marks the tracks at `path` (a file or a directory) as missing.
+    ///
+    /// Returns the IDs of the tracks that were marked.
+    async fn mark_tracks_missing(&self, path: &str) -> DBResult<Vec<Uuid>>;
+}
+
+impl LibraryExt for DatabaseClient {
+    async fn upsert_track(&self, user_id: Uuid, track: NewTrack) -> DBResult<Track> {
+        let mut tx = self.begin_write().await?;
+
+        // Album
+        let album_artist = track
+            .album_artist
+            .as_deref()
+            .or(track.artists.first().map(String::as_str));
+        let album_id = match &track.album {
+            Some(title) => {
+                Some(upsert_album(&mut tx, title, album_artist, &track.thumbnail_path).await?)
+            }
+            None => None,
+        };
+
+        // Track
+        let now = Utc::now().naive_local().to_string();
+        let upserted = sqlx::query_as::<Sqlite, Track>(
+            "
+            INSERT INTO tracks (
+                id,
+                user_id,
+                title,
+                album_id,
+                track_number,
+                release_year,
+                duration_secs,
+                file_path,
+                thumbnail_path,
+                file_size,
+                file_modified_at,
+                created_at,
+                updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            ON CONFLICT(file_path) DO UPDATE SET
+                title = excluded.title,
+                album_id = excluded.album_id,
+                track_number = excluded.track_number,
+                release_year = excluded.release_year,
+                duration_secs = excluded.duration_secs,
+                thumbnail_path = excluded.thumbnail_path,
+                updated_at = excluded.updated_at,
+                is_missing = 0,
+                content_hash = CASE
+                    WHEN excluded.file_size = file_size
+                        AND excluded.file_modified_at = file_modified_at
+                    THEN content_hash
+                END,
+                file_size = excluded.file_size,
+                file_modified_at = excluded.file_modified_at
+            RETURNING *
+            ",
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(user_id.to_string())
+        .bind(&track.title)
+        .bind(album_id.map(|id| id.to_string()))
+        .bind(track.track_number)
+        .bind(track.release_year)
+        .bind(track.duration_secs)
+        .bind(&track.file_path)
+        .bind(&track.thumbnail_path)
+        .bind(track.file_size)
+        .bind(&track.file_modified_at)
+        .bind(&now)
+        .bind(&now)
+        .fetch_one(&mut *tx)
+        .await?;
+        let track_id = upserted.id.to_string();
+
+        // Artists
+        sqlx::query("DELETE FROM track_artists WHERE track_id = $1")
+            .bind(&track_id)
+            .execute(&mut *tx)
+            .await?;
+        for name in &track.artists {
+            let artist_id = upsert_artist(&mut tx, name).await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO track_artists (track_id, artist_id) VALUES ($1, $2)",
+            )
+            .bind(&track_id)
+            .bind(artist_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Genres
+        sqlx::query("DELETE FROM track_genres WHERE track_id = $1")
+            .bind(&track_id)
+            .execute(&mut *tx)
+            .await?;
+        for name in &track.genres {
+            sqlx::query("INSERT OR IGNORE INTO genres (name) VALUES ($1)")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT OR IGNORE INTO track_genres (track_id, genre) VALUES ($1, $2)")
+                .bind(&track_id)
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(upserted)
+    }
+
+    async fn add_library_folder(&self, user_id: Uuid, path: &str) -> DBResult<()> {
+        sqlx::query(
+            "
+            INSERT OR IGNORE INTO library_folders (path, user_id, added_at)
+            VALUES ($1, $2, $3)
+            ",
+        )
+        .bind(path)
+        .bind(user_id.to_string())
+        .bind(Utc::now().naive_local().to_string())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_library_folders(&self) -> DBResult<Vec<LibraryFolder>> {
+        let folders: Vec<LibraryFolder> = sqlx::query_as("SELECT * FROM library_folders")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(folders)
+    }
+
+    async fn move_tracks(&self, from: &str, to: &str) -> DBResult<Vec<Uuid>> {
+        let moved: Vec<String> = sqlx::query_scalar(
+            "
+            UPDATE tracks
+            SET
+                file_path = $2 || substr(file_path, length($1) + 1),
+                updated_at = $4,
+                is_missing = 0
+            WHERE file_path = $1 OR substr(file_path, 1, length($1) + 1) = $1 || $3
+            RETURNING id
+            ",
+        )
+        .bind(from)
+        .bind(to)
+        .bind(MAIN_SEPARATOR_STR)
+        .bind(Utc::now().naive_local().to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        parse_ids(moved)
+    }
+
+    async fn mark_tracks_missing(&self, path: &str) -> DBResult<Vec<Uuid>> {
+        let missing: Vec<String> = sqlx::query_scalar(
+            "
+            UPDATE tracks
+            SET is_missing = 1, updated_at = $3
+            WHERE
+                is_missing = 0 AND
+                (file_path = $1 OR substr(file_path, 1, length($1) + 1) = $1 || $2)
+            RETURNING id
+            ",
+        )
+        .bind(path)
+        .bind(MAIN_SEPARATOR_STR)
+        .bind(Utc::now().naive_local().to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        parse_ids(missing)
+    }
+}
+
+/// Gets the ID of the album with the given title and artist, creating the album if it doesn't
+/// exist.
+///
+/// Albums from before albums had artists are claimed by the first artist whose track is on them.
+/// The album's thumbnail is set from the track's if the album doesn't have one yet.
+async fn upsert_album(
+    conn: &mut SqliteConnection,
+    title: &str,
+    artist: Option<&str>,
+    thumbnail_path: &str,
+) -> Result<Uuid, sqlx::Error> {
+    let existing: Option<String> = sqlx::query_scalar(
+        "
+        SELECT id
+        FROM albums
+        WHERE title = $1 AND (artist IS $2 OR artist IS NULL)
+        ORDER BY artist IS NULL
+        LIMIT 1
+        ",
+    )
+    .bind(title)
+    .bind(artist)
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(id) = existing {
+        sqlx::query("UPDATE albums SET artist = $1 WHERE id = $2 AND artist IS NULL")
+            .bind(artist)
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+        if !thumbnail_path.is_empty() {
+            sqlx::query(
+                "UPDATE albums SET thumbnail_path = $1 WHERE id = $2 AND thumbnail_path = ''",
+            )
+            .bind(thumbnail_path)
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        return Uuid::parse_str(&id).map_err(|e| sqlx::Error::Decode(e.into()));
+    }
+
+    let album_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO albums (id, title, artist, thumbnail_path) VALUES ($1, $2, $3, $4)")
+        .bind(album_id.to_string())
+        .bind(title)
+        .bind(artist)
+        .bind(thumbnail_path)
+        .execute(&mut *conn)
+        .await?;
+    Ok(album_id)
+}
+
+/// Gets the ID of the artist with the given name, creating the artist if it doesn't exist.
+async fn upsert_artist(conn: &mut SqliteConnection, name: &str) -> Result<Uuid, sqlx::Error> {
+    let existing: Option<String> = sqlx::query_scalar("SELECT id FROM artists WHERE name = $1")
+        .bind(name)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(id) = existing {
+        return Uuid::parse_str(&id).map_err(|e| sqlx::Error::Decode(e.into()));
+    }
+
+    let artist_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO artists (id, name) VALUES ($1, $2)")
+        .bind(artist_id.to_string())
+        .bind(name)
+        .execute(&mut *conn)
+        .await?;
+    Ok(artist_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        test_utils::{count, TempDb},
+        users::UserExt,
+    };
+
+    fn new_track(file_path: &str, title: &str, album: &str, artist: &str) -> NewTrack {
+        NewTrack {
+            file_path: file_path.to_string(),
+            title: title.to_string(),
+            album: Some(album.to_string()),
+            artists: vec![artist.to_string()],
+            genres: vec![String::from("Rock")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_upsert_track_is_idempotent() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+
+            let track = new_track("/music/a.mp3", "A", "Album", "Artist");
+            let first = db.upsert_track(user_id, track.clone()).await.unwrap();
+            let second = db
+                .upsert_track(
+                    user_id,
+                    NewTrack {
+                        title: String::from("A (Remastered)"),
+                        ..track
+                    },
+                )
+                .await
+                .unwrap();
+            assert_eq!(first.id, second.id);
+            assert_eq!(second.title, "A (Remastered)");
+            assert_eq!(second.album_id, first.album_id);
+            for (table, rows) in [
+                ("tracks", 1),
+                ("albums", 1),
+                ("artists", 1),
+                ("track_artists", 1),
+                ("genres", 1),
+                ("track_genres", 1),
+            ] {
+                assert_eq!(count(db, table).await, rows, "{table}");
+            }
+        });
+    }
+
+    #[test]
+    fn test_upsert_track_groups_albums_by_artist() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let upsert = |track: NewTrack| db.upsert_track(user_id, track);
+
+            let queen_1 = upsert(new_track("/q/1.mp3", "1", "Greatest Hits", "Queen"))
+                .await
+                .unwrap();
+            let queen_2 = upsert(new_track("/q/2.mp3", "2", "Greatest Hits", "Queen"))
+                .await
+                .unwrap();
+            let abba = upsert(new_track("/a/1.mp3", "1", "Greatest Hits", "ABBA"))
+                .await
+                .unwrap();
+            assert_eq!(queen_1.album_id, queen_2.album_id);
+            assert_ne!(queen_1.album_id, abba.album_id);
+
+            // A compilation's tracks are grouped by its album artist
+            let mut compilation = vec![];
+            for (file_path, artist) in [("/c/1.mp3", "Queen"), ("/c/2.mp3", "ABBA")] {
+                let track = NewTrack {
+                    album_artist: Some(String::from("Various Artists")),
+                    ..new_track(file_path, "1", "Greatest Hits", artist)
+                };
+                compilation.push(upsert(track).await.unwrap().album_id);
+            }
+            assert_eq!(compilation[0], compilation[1]);
+            assert_eq!(count(db, "albums").await, 3);
+        });
+    }
+
+    #[test]
+    fn test_move_and_mark_tracks_by_path_prefix() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let upsert = |file_path: &str| {
+                db.upsert_track(user_id, new_track(file_path, "1", "Album", "Artist"))
+            };
+            let in_dir = upsert("/music/a/1.mp3").await.unwrap();
+            let file = upsert("/music/a").await.unwrap();
+            let sibling = upsert("/music/ab/1.mp3").await.unwrap();
+            let file_path = |track_id: Uuid| async move {
+                sqlx::query_scalar::<_, String>("SELECT file_path FROM tracks WHERE id = $1")
+                    .bind(track_id.to_string())
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap()
+            };
+
+            // `/music/a` is the file itself or a directory, but not a prefix of `/music/ab`
+            let mut moved = db.move_tracks("/music/a", "/music/c").await.unwrap();
+            moved.sort();
+            let mut expected = vec![in_dir.id, file.id];
+            expected.sort();
+            assert_eq!(moved, expected);
+            assert_eq!(file_path(in_dir.id).await, "/music/c/1.mp3");
+            assert_eq!(file_path(file.id).await, "/music/c");
+            assert_eq!(file_path(sibling.id).await, "/music/ab/1.mp3");
+
+            let mut missing = db.mark_tracks_missing("/music/c").await.unwrap();
+            missing.sort();
+            assert_eq!(missing, expected);
+            assert!(db.mark_tracks_missing("/music/a").await.unwrap().is_empty());
+            assert_eq!(
+                db.mark_tracks_missing("/music/ab").await.unwrap(),
+                vec![sibling.id]
+            );
+
+            // Moving a missing track back brings it back
+            assert_eq!(
+                db.move_tracks("/music/ab/1.mp3", "/music/b/1.mp3")
+                    .await
+                    .unwrap(),
+                vec![sibling.id]
+            );
+            assert_eq!(
+                db.mark_tracks_missing("/music/b").await.unwrap(),
+                vec![sibling.id]
+            );
+        });
+    }
+
+    #[test]
+    fn test_upsert_track_keeps_content_hash_of_unchanged_files() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let track = NewTrack {
+                file_size: Some(1024),
+                file_modified_at: Some(String::from("2026-01-01 00:00:00")),
+                ..new_track("/music/a.mp3", "A", "Album", "Artist")
+            };
+            db.upsert_track(user_id, track.clone()).await.unwrap();
+
+            // Hashes the imported track, then gets its hash after importing it again
+            let rescan = |track: NewTrack| async move {
+                sqlx::query("UPDATE tracks SET content_hash = 'abc'")
+                    .execute(&db.pool)
+                    .await
+                    .unwrap();
+                db.upsert_track(user_id, track).await.unwrap();
+                sqlx::query_scalar::<_, Option<String>>("SELECT content_hash FROM tracks")
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap()
+            };
+
+            let retagged = NewTrack {
+                title: String::from("A (Remastered)"),
+                ..track.clone()
+            };
+            assert_eq!(rescan(retagged).await.as_deref(), Some("abc"));
+
+            let resized = NewTrack {
+                file_size: Some(2048),
+                ..track.clone()
+            };
+            assert_eq!(rescan(resized).await, None);
+            db.upsert_track(user_id, track.clone()).await.unwrap();
+
+            let modified = NewTrack {
+                file_modified_at: Some(String::from("2026-01-02 00:00:00")),
+                ..track.clone()
+            };
+            assert_eq!(rescan(modified).await, None);
+            db.upsert_track(user_id, track.clone()).await.unwrap();
+
+            // Without the file's size and modification time it can't tell whether it changed
+            let unknown = NewTrack {
+                file_size: None,
+                file_modified_at: None,
+                ..track
+            };
+            assert_eq!(rescan(unknown).await, None);
+        });
+    }
+}
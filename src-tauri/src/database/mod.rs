@@ -1,70 +1,785 @@
-use futures_util::{Stream, StreamExt};
-use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+};
+
+use base64::{prelude::BASE64_URL_SAFE_NO_PAD, Engine};
+use futures_util::{
+    stream::{AbortHandle, Abortable},
+    Stream, StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    sqlite::{SqliteArguments, SqliteRow},
+    Arguments, FromRow, Pool, Row, Sqlite, TypeInfo, ValueRef,
+};
+use uuid::Uuid;
 
 use crate::{
     api::utils::{ApiResponse, ApiResponseStatus, ResponseChannel},
+    database::query::{bind, SqlArg},
     errors::SpotsError,
 };
 
 pub mod albums;
+pub mod backup;
+pub mod bundle;
 pub mod client;
+pub mod favorites;
+pub mod fuzzy;
+pub mod history;
+pub mod library;
 pub mod models;
+pub mod playlist_files;
 pub mod playlists;
+pub mod query;
+pub mod queue;
+pub mod search;
+pub mod sessions;
+pub mod smart_playlists;
+pub mod spotify;
 pub mod tracks;
 pub mod users;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 pub type DBResult<T> = Result<T, SpotsError>;
 
+/// Parses the IDs returned by a query.
+pub fn parse_ids(ids: Vec<String>) -> DBResult<Vec<Uuid>> {
+    ids.iter()
+        .map(|id| Uuid::parse_str(id).map_err(|e| sqlx::Error::Decode(e.into()).into()))
+        .collect()
+}
+
+/// Sends a single response to the given channel.
+pub fn send_response<T>(
+    channel: &ResponseChannel<T>,
+    response: ApiResponse<Option<T>>,
+) -> Result<(), SpotsError>
+where
+    T: Serialize,
+{
+    channel
+        .send(response)
+        .map_err(|e| SpotsError::ChannelError {
+            channel_id: channel.id(),
+            error: e.to_string(),
+        })
+}
+
+/// The streams that are being sent to a channel, keyed by the channel's ID.
+///
+/// Each stream also has a unique ID, since a channel can be reused once its stream ends.
+static ACTIVE_STREAMS: Mutex<BTreeMap<u32, (u64, AbortHandle)>> = Mutex::new(BTreeMap::new());
+
+/// The ID of the next stream that is started.
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Cancels the stream that is being sent to the channel.
+///
+/// The stream stops (dropping its database cursor) and ends with
+/// [ApiResponseStatus::Cancelled]. Returns whether a stream was cancelled (`false` if the stream
+/// hasn't started or already ended).
+pub fn cancel_stream(channel_id: u32) -> bool {
+    let stream = active_streams().remove(&channel_id);
+    stream.map(|(_, handle)| handle.abort()).is_some()
+}
+
+fn active_streams() -> MutexGuard<'static, BTreeMap<u32, (u64, AbortHandle)>> {
+    ACTIVE_STREAMS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A stream in [ACTIVE_STREAMS], which is removed once the stream ends.
+struct ActiveStream {
+    channel_id: u32,
+    stream_id: u64,
+}
+
+impl ActiveStream {
+    /// Adds the stream to [ACTIVE_STREAMS], replacing the channel's previous stream.
+    fn start(channel_id: u32, handle: AbortHandle) -> Self {
+        let stream_id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+        active_streams().insert(channel_id, (stream_id, handle));
+        Self {
+            channel_id,
+            stream_id,
+        }
+    }
+}
+
+impl Drop for ActiveStream {
+    fn drop(&mut self) {
+        // The channel may have been cancelled and reused by another stream since
+        let mut streams = active_streams();
+        if streams
+            .get(&self.channel_id)
+            .is_some_and(|(stream_id, _)| *stream_id == self.stream_id)
+        {
+            streams.remove(&self.channel_id);
+        }
+    }
+}
+
 /// Streams the rows to the given channel.
+///
+/// The stream can be cancelled with [cancel_stream].
 pub async fn stream_rows<T>(
-    mut rows: impl Stream<Item = Result<T, sqlx::Error>> + Send + Unpin,
+    rows: impl Stream<Item = Result<T, sqlx::Error>> + Send + Unpin,
     channel: ResponseChannel<T>,
 ) -> Result<(), SpotsError>
 where
     T: Serialize,
 {
+    send_rows(rows, channel).await?;
+    Ok(())
+}
+
+/// Streams the rows to the given channel, returning `false` if the stream was cancelled.
+async fn send_rows<T>(
+    rows: impl Stream<Item = Result<T, sqlx::Error>> + Send + Unpin,
+    channel: ResponseChannel<T>,
+) -> Result<bool, SpotsError>
+where
+    T: Serialize,
+{
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let _active_stream = ActiveStream::start(channel.id(), abort_handle);
+    let mut rows = Abortable::new(rows, abort_registration);
+
     // Signals the start of the stream
-    channel
-        .send(ApiResponse {
+    send_response(
+        &channel,
+        ApiResponse {
             status: ApiResponseStatus::Started,
             value: None,
-        })
-        .map_err(|e| SpotsError::ChannelError {
-            channel_id: channel.id(),
-            error: e.to_string(),
-        })?;
+        },
+    )?;
 
     // The actual stream
     while let Some(row) = rows.next().await {
         match row {
-            Ok(value) => channel
-                .send(ApiResponse::pending(Some(value)))
-                .map_err(|e| SpotsError::ChannelError {
-                    channel_id: channel.id(),
-                    error: e.to_string(),
-                })?,
+            Ok(value) => send_response(&channel, ApiResponse::pending(Some(value)))?,
             Err(err) => {
-                channel
-                    .send(ApiResponse::failure(None))
-                    .map_err(|e| SpotsError::ChannelError {
-                        channel_id: channel.id(),
-                        error: e.to_string(),
-                    })?;
+                send_response(&channel, ApiResponse::failure(None))?;
                 return Err(SpotsError::from(err));
             }
         }
     }
 
-    // Signals the end of the stream.
-    channel
-        .send(ApiResponse {
-            status: ApiResponseStatus::Completed,
+    // Signals the end of the stream, dropping the rows first if it was cancelled
+    let is_cancelled = rows.is_aborted();
+    drop(rows);
+    let status = if is_cancelled {
+        ApiResponseStatus::Cancelled
+    } else {
+        ApiResponseStatus::Completed
+    };
+    send_response(
+        &channel,
+        ApiResponse {
+            status,
             value: None,
-        })
-        .map_err(|e| SpotsError::ChannelError {
-            channel_id: channel.id(),
-            error: e.to_string(),
-        })?;
+        },
+    )?;
 
-    Ok(())
+    Ok(!is_cancelled)
+}
+
+/// The number of rows in a page if it isn't set.
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// The max number of rows in a page.
+pub const MAX_PAGE_SIZE: u32 = 1000;
+
+/// What a list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortKey {
+    Title,
+    Album,
+    Artist,
+    Year,
+    Added,
+    LastPlayed,
+}
+
+/// The order of a list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SortOrder {
+    /// What the list is sorted by.
+    pub key: SortKey,
+
+    /// Whether the list is sorted in descending order.
+    pub descending: bool,
+}
+
+impl SortOrder {
+    /// Sorted by title (A to Z), the default order of most lists.
+    pub const BY_TITLE: SortOrder = SortOrder {
+        key: SortKey::Title,
+        descending: false,
+    };
+}
+
+/// Which page of a list to get, and how the list is sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page {
+    /// What the list is sorted by (the list's default order is used if it isn't set).
+    pub sort: Option<SortKey>,
+
+    /// Whether the list is sorted in descending order (ascending by default, unless the list's
+    /// default order is used).
+    pub descending: Option<bool>,
+
+    /// The cursor returned with the previous page (`None` for the first page).
+    pub cursor: Option<String>,
+
+    /// The max number of rows in the page (see [DEFAULT_PAGE_SIZE] and [MAX_PAGE_SIZE]).
+    pub page_size: Option<u32>,
+}
+
+/// Where a page starts: the sort key and ID of the last row of the previous page.
+///
+/// The cursor is sent to the frontend as base64 encoded JSON, which should treat it as opaque.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Cursor {
+    order: SortOrder,
+    key: CursorKey,
+    id: String,
+}
+
+/// The sort key of the last row of a page (a number or text, depending on the [SortKey]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum CursorKey {
+    Int(i64),
+    Text(String),
+}
+
+impl Page {
+    /// The order of the page, which is the list's `default` order if the page doesn't set it.
+    pub fn order(&self, default: SortOrder) -> SortOrder {
+        match self.sort {
+            Some(key) => SortOrder {
+                key,
+                descending: self.descending.unwrap_or(false),
+            },
+            None => SortOrder {
+                descending: self.descending.unwrap_or(default.descending),
+                ..default
+            },
+        }
+    }
+
+    /// Encodes the cursor of the page that starts after the row.
+    fn next_cursor(&self, order: SortOrder, key: CursorKey, id: String) -> DBResult<String> {
+        let cursor = Cursor { order, key, id };
+        let json = serde_json::to_vec(&cursor).map_err(|e| sqlx::Error::Encode(e.into()))?;
+        Ok(BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+
+    /// Decodes the cursor of the page.
+    ///
+    /// The cursor has to come from a page with the same sort order.
+    fn decode_cursor(&self, order: SortOrder) -> DBResult<Option<Cursor>> {
+        let Some(cursor_str) = &self.cursor else {
+            return Ok(None);
+        };
+        let invalid = || SpotsError::InvalidPageCursor(cursor_str.clone());
+        let json = BASE64_URL_SAFE_NO_PAD
+            .decode(cursor_str)
+            .map_err(|_| invalid())?;
+        let cursor: Cursor = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        if cursor.order != order {
+            return Err(invalid());
+        }
+        Ok(Some(cursor))
+    }
+}
+
+/// Streams a page of the rows of the query to the given channel.
+///
+/// The rows of the `sql` query (which need an `id` column) are sorted in the `order` by
+/// `sort_sql`, an expression on a row `r`, with ties broken by ID. The expression has to match
+/// an index (e.g. `r.title COLLATE NOCASE` for an index on `title COLLATE NOCASE, id`), so
+/// SQLite can seek to the start of the page instead of sorting the whole list.
+///
+/// Returns the cursor of the next page, or `None` if this was the last page (or the stream was
+/// cancelled).
+pub async fn stream_page<T>(
+    pool: &Pool<Sqlite>,
+    sql: &str,
+    mut args: Vec<SqlArg>,
+    sort_sql: &str,
+    order: SortOrder,
+    page: &Page,
+    channel: ResponseChannel<T>,
+) -> DBResult<Option<String>>
+where
+    T: Serialize + for<'r> FromRow<'r, SqliteRow>,
+{
+    let page_size = page
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let cursor = page.decode_cursor(order)?;
+    let sql = page_sql(sql, &mut args, sort_sql, order, cursor, page_size);
+    let mut rows = sqlx::query_with(&sql, sql_arguments(args)?).fetch(pool);
+
+    // Stream the page, keeping track of where it ends
+    let mut last = None;
+    let page_rows = rows.by_ref().take(page_size as usize).map(|row| {
+        let row = row?;
+        last = Some((page_key(&row)?, row.try_get("id")?));
+        T::from_row(&row)
+    });
+    if !send_rows(page_rows, channel).await? {
+        return Ok(None);
+    }
+
+    match (rows.next().await, last) {
+        (Some(_), Some((key, id))) => page.next_cursor(order, key, id).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Collects the arguments of a compiled query, to bind them in order (e.g. with
+/// [sqlx::query_as_with]).
+pub(crate) fn sql_arguments(args: Vec<SqlArg>) -> DBResult<SqliteArguments<'static>> {
+    let mut arguments = SqliteArguments::default();
+    for arg in args {
+        match arg {
+            SqlArg::Text(text) => arguments.add(text),
+            SqlArg::Int(int) => arguments.add(int),
+        }
+        .map_err(sqlx::Error::Encode)?;
+    }
+    Ok(arguments)
+}
+
+/// Builds the query for a page of [stream_page], which starts after the `cursor`.
+///
+/// The values are added to `args`. One more row than the `page_size` is selected, to know if
+/// there is a next page.
+fn page_sql(
+    sql: &str,
+    args: &mut Vec<SqlArg>,
+    sort_sql: &str,
+    order: SortOrder,
+    cursor: Option<Cursor>,
+    page_size: u32,
+) -> String {
+    let (direction, comparison) = if order.descending {
+        ("DESC", "<")
+    } else {
+        ("ASC", ">")
+    };
+
+    // The first comparison is redundant, but (unlike the row value) lets SQLite seek the index
+    let condition = match cursor {
+        Some(cursor) => {
+            let key = bind(
+                args,
+                match cursor.key {
+                    CursorKey::Int(int) => SqlArg::Int(int),
+                    CursorKey::Text(text) => SqlArg::Text(text),
+                },
+            );
+            let id = bind(args, SqlArg::Text(cursor.id));
+            format!("WHERE p.page_key {comparison}= {key} AND (p.page_key, p.id) {comparison} ({key}, {id})")
+        }
+        None => String::new(),
+    };
+
+    let limit = bind(args, SqlArg::Int(i64::from(page_size) + 1));
+    format!(
+        "
+        SELECT *
+        FROM (SELECT r.*, {sort_sql} AS page_key FROM ({sql}) r) p
+        {condition}
+        ORDER BY p.page_key {direction}, p.id {direction}
+        LIMIT {limit}
+        "
+    )
+}
+
+/// Gets the sort key of a row of [stream_page].
+fn page_key(row: &SqliteRow) -> Result<CursorKey, sqlx::Error> {
+    let is_int = row.try_get_raw("page_key")?.type_info().name() == "INTEGER";
+    if is_int {
+        row.try_get("page_key").map(CursorKey::Int)
+    } else {
+        row.try_get("page_key").map(CursorKey::Text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        future::Future,
+        sync::{atomic::AtomicU32, Arc},
+        time::Duration,
+    };
+
+    use serde_json::Value;
+    use tauri::ipc::{Channel, InvokeResponseBody};
+
+    use super::*;
+    use crate::database::{
+        albums::AlbumExt,
+        favorites::{FavoriteExt, FavoriteKind},
+        library::{LibraryExt, NewTrack},
+        test_utils::{count, response_channel, streamed_values, Responses, TempDb},
+        tracks::TrackExt,
+        users::UserExt,
+    };
+
+    #[test]
+    fn test_page_cursor() -> DBResult<()> {
+        let order = SortOrder {
+            key: SortKey::Year,
+            descending: true,
+        };
+        let page = Page {
+            sort: Some(SortKey::Year),
+            descending: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(page.order(SortOrder::BY_TITLE), order);
+        let cursor = page.next_cursor(order, CursorKey::Int(2001), String::from("id"))?;
+        let next_page = Page {
+            cursor: Some(cursor),
+            ..page.clone()
+        };
+        assert_eq!(
+            next_page.decode_cursor(order)?,
+            Some(Cursor {
+                order,
+                key: CursorKey::Int(2001),
+                id: String::from("id"),
+            })
+        );
+
+        // The cursor only works with the same sort order
+        assert!(next_page.decode_cursor(SortOrder::BY_TITLE).is_err());
+
+        // The list's default order is used if the page doesn't set one
+        let default_page = Page::default();
+        assert_eq!(default_page.order(order), order);
+        let ascending_page = Page {
+            descending: Some(false),
+            ..Default::default()
+        };
+        assert!(!ascending_page.order(order).descending);
+        Ok(())
+    }
+
+    /// Gets every page of a list, returning the IDs of the rows.
+    async fn walk_pages<F, Fut>(page: &Page, mut get_page: F) -> Vec<String>
+    where
+        F: FnMut(Page) -> Fut,
+        Fut: Future<Output = (Option<String>, Vec<Value>)>,
+    {
+        let mut page = page.clone();
+        let mut ids = Vec::new();
+        loop {
+            let (cursor, rows) = get_page(page.clone()).await;
+            ids.extend(
+                rows.iter()
+                    .map(|row| row["id"].as_str().unwrap().to_owned()),
+            );
+            match cursor {
+                Some(cursor) => page.cursor = Some(cursor),
+                None => return ids,
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_page() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            for i in 0..20 {
+                let track = NewTrack {
+                    file_path: format!("/music/{i}.mp3"),
+                    title: String::from(["Same", "same", "alpha", "Beta"][i % 4]),
+                    album: (i % 5 != 0).then(|| String::from(["B", "a", "C"][i % 3])),
+                    artists: match i % 7 {
+                        0 => Vec::new(),
+                        n => vec![String::from(["Zed", "amy", "Bob"][n % 3])],
+                    },
+                    track_number: Some((i % 4) as i64),
+                    release_year: (i % 4 != 0).then_some(1990 + (i % 3) as i64),
+                    ..Default::default()
+                };
+                db.upsert_track(user_id, track).await.unwrap();
+            }
+            sqlx::query("UPDATE tracks SET last_played_at = created_at WHERE track_number = 1")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+
+            // Every row is in exactly one page, in the same order as a single page
+            let keys = [
+                SortKey::Title,
+                SortKey::Album,
+                SortKey::Artist,
+                SortKey::Year,
+                SortKey::Added,
+                SortKey::LastPlayed,
+            ];
+            for key in keys {
+                for descending in [false, true] {
+                    let page = |page_size| Page {
+                        sort: Some(key),
+                        descending: Some(descending),
+                        cursor: None,
+                        page_size: Some(page_size),
+                    };
+                    let get_tracks = |page: Page| async move {
+                        let (channel, responses) = response_channel();
+                        let cursor = db.get_all_tracks(&page, channel).await.unwrap();
+                        (cursor, streamed_values(&responses))
+                    };
+                    let all_tracks = walk_pages(&page(MAX_PAGE_SIZE), get_tracks).await;
+                    assert_eq!(all_tracks.len(), 20);
+                    assert_eq!(all_tracks.iter().collect::<HashSet<_>>().len(), 20);
+                    let tracks = walk_pages(&page(3), get_tracks).await;
+                    assert_eq!(tracks, all_tracks, "{key:?} (descending: {descending})");
+
+                    let get_albums = |page: Page| async move {
+                        let (channel, responses) = response_channel();
+                        let cursor = db.get_all_albums(&page, channel).await.unwrap();
+                        (cursor, streamed_values(&responses))
+                    };
+                    let all_albums = walk_pages(&page(MAX_PAGE_SIZE), get_albums).await;
+                    assert_eq!(all_albums.len() as i64, count(db, "albums").await);
+                    let albums = walk_pages(&page(1), get_albums).await;
+                    assert_eq!(albums, all_albums, "{key:?} (descending: {descending})");
+                }
+            }
+
+            // Titles are sorted ignoring case
+            let (channel, responses) = response_channel();
+            db.get_all_tracks(&Page::default(), channel).await.unwrap();
+            let titles: Vec<String> = streamed_values(&responses)
+                .iter()
+                .map(|track| track["title"].as_str().unwrap().to_lowercase())
+                .collect();
+            assert!(titles.windows(2).all(|pair| pair[0] <= pair[1]));
+
+            // The sort keys follow renamed albums and artists
+            sqlx::query("UPDATE albums SET title = 'D' WHERE title = 'a'")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE artists SET name = 'Ann' WHERE name = 'amy'")
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            let stale: i64 = sqlx::query_scalar(
+                "
+                SELECT COUNT(*)
+                FROM tracks
+                WHERE album_sort_key LIKE 'a' || char(31) || '%' OR artist_sort_key = 'amy'
+                ",
+            )
+            .fetch_one(&db.pool)
+            .await
+            .unwrap();
+            assert_eq!(stale, 0);
+            let page = Page {
+                sort: Some(SortKey::Album),
+                descending: Some(true),
+                ..Default::default()
+            };
+            let (channel, responses) = response_channel();
+            db.get_all_albums(&page, channel).await.unwrap();
+            assert_eq!(streamed_values(&responses)[0]["title"], "D");
+        });
+    }
+
+    #[test]
+    fn test_page_query_plans() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let keys = [
+                SortKey::Title,
+                SortKey::Album,
+                SortKey::Artist,
+                SortKey::Year,
+                SortKey::Added,
+                SortKey::LastPlayed,
+            ];
+            let lists = [
+                ("SELECT * FROM tracks", tracks::track_sort_sql as fn(_) -> _),
+                ("SELECT * FROM albums", albums::album_sort_sql),
+            ];
+
+            // Pages are read in order from an index, instead of sorting the whole list
+            for (list_sql, sort_sql) in lists {
+                for key in keys {
+                    let order = SortOrder {
+                        key,
+                        descending: true,
+                    };
+                    let cursor = Cursor {
+                        order,
+                        key: CursorKey::Text(String::new()),
+                        id: String::new(),
+                    };
+                    let mut args = Vec::new();
+                    let sql = page_sql(list_sql, &mut args, sort_sql(key), order, Some(cursor), 10);
+                    let sql = format!("EXPLAIN QUERY PLAN {sql}");
+                    let plan: Vec<(i64, i64, i64, String)> =
+                        sqlx::query_as_with(&sql, sql_arguments(args).unwrap())
+                            .fetch_all(&db.pool)
+                            .await
+                            .unwrap();
+                    let plan = plan
+                        .into_iter()
+                        .map(|(.., detail)| detail)
+                        .collect::<Vec<_>>();
+                    assert!(
+                        matches!(&plan[..], [detail] if detail.starts_with("SEARCH")),
+                        "{list_sql} by {key:?}: {plan:?}"
+                    );
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_favorited_tracks_order() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let mut track_ids = Vec::new();
+            for (i, title) in ["B", "A", "C"].into_iter().enumerate() {
+                let track = NewTrack {
+                    file_path: format!("/music/{title}.mp3"),
+                    title: String::from(title),
+                    ..Default::default()
+                };
+                let track_id = db.upsert_track(user_id, track).await.unwrap().id;
+                db.toggle_favorite(user_id, FavoriteKind::Track, track_id)
+                    .await
+                    .unwrap();
+                sqlx::query("UPDATE favorited_tracks SET favorited_at = $1 WHERE track_id = $2")
+                    .bind(format!("2026-10-0{} 12:00:00", i + 1))
+                    .bind(track_id.to_string())
+                    .execute(&db.pool)
+                    .await
+                    .unwrap();
+                track_ids.push(track_id.to_string());
+            }
+            let get_favorites = |page: Page| async move {
+                let (channel, responses) = response_channel();
+                let cursor = db
+                    .get_favorited_tracks(user_id, &page, channel)
+                    .await
+                    .unwrap();
+                (cursor, streamed_values(&responses))
+            };
+
+            // The most recently favorited tracks come first by default
+            let page = Page {
+                page_size: Some(2),
+                ..Default::default()
+            };
+            let mut expected = track_ids.clone();
+            expected.reverse();
+            assert_eq!(walk_pages(&page, get_favorites).await, expected);
+
+            let page = Page {
+                sort: Some(SortKey::Title),
+                page_size: Some(2),
+                ..Default::default()
+            };
+            let expected = vec![
+                track_ids[1].clone(),
+                track_ids[0].clone(),
+                track_ids[2].clone(),
+            ];
+            assert_eq!(walk_pages(&page, get_favorites).await, expected);
+        });
+    }
+
+    #[test]
+    fn test_cancel_stream() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+
+            // Cancels the stream from the channel once a couple of rows were sent
+            let responses = Responses::default();
+            let channel_id = Arc::new(AtomicU32::new(0));
+            let (sent, id) = (responses.clone(), channel_id.clone());
+            let channel: ResponseChannel<i64> = Channel::new(move |body| {
+                let InvokeResponseBody::Json(json) = body else {
+                    unreachable!()
+                };
+                let mut sent = sent.lock().unwrap();
+                sent.push(serde_json::from_str(&json).unwrap());
+                if sent.len() == 3 {
+                    assert!(cancel_stream(id.load(Ordering::SeqCst)));
+                }
+                Ok(())
+            });
+            channel_id.store(channel.id(), Ordering::SeqCst);
+            let rows = sqlx::query_scalar(
+                "
+                WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n)
+                SELECT i FROM n LIMIT 1000000
+                ",
+            )
+            .fetch(&db.pool);
+            stream_rows(rows, channel).await.unwrap();
+
+            let statuses: Vec<Value> = responses
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|response| response["status"].clone())
+                .collect();
+            assert_eq!(statuses, ["Started", "Pending", "Pending", "Cancelled"]);
+            assert!(!cancel_stream(channel_id.load(Ordering::SeqCst)));
+
+            // The stream's connection goes back to the pool (which happens in the background)
+            for _ in 0..100 {
+                if db.pool.num_idle() == db.pool.size() as usize {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(db.pool.num_idle(), db.pool.size() as usize);
+        });
+    }
+
+    #[test]
+    fn test_active_streams() {
+        let channel_id = u32::MAX;
+        let first = ActiveStream::start(channel_id, AbortHandle::new_pair().0);
+
+        // The first stream is cancelled, and the channel reused before the first stream ends
+        assert!(cancel_stream(channel_id));
+        let second = ActiveStream::start(channel_id, AbortHandle::new_pair().0);
+        drop(first);
+        assert!(active_streams().contains_key(&channel_id));
+        drop(second);
+        assert!(!active_streams().contains_key(&channel_id));
+    }
 }
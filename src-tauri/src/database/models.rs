@@ -5,6 +5,15 @@ use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, sqlite::SqliteRow, Row};
 use uuid::Uuid;
 
+/// Parses a timestamp stored in the DB.
+///
+/// Timestamps are written with `NaiveDateTime::to_string` (`YYYY-MM-DD HH:MM:SS.f`), but the
+/// `YYYY-MM-DDTHH:MM:SS.f` form is accepted as well.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Result<NaiveDateTime, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::from_str(timestamp))
+}
+
 /// Represents a user.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
@@ -35,12 +44,8 @@ impl<'r> FromRow<'r, SqliteRow> for User {
             id: Uuid::from_str(id).map_err(|e| sqlx::error::Error::Decode(e.into()))?,
             username,
             password_hash,
-            created_at: created_at
-                .map(|t| NaiveDateTime::from_str(&t).ok())
-                .flatten(),
-            updated_at: updated_at
-                .map(|t| NaiveDateTime::from_str(&t).ok())
-                .flatten(),
+            created_at: created_at.map(|t| parse_timestamp(&t).ok()).flatten(),
+            updated_at: updated_at.map(|t| parse_timestamp(&t).ok()).flatten(),
         })
     }
 }
@@ -54,6 +59,8 @@ pub mod music_library {
     use sqlx::{sqlite::SqliteRow, FromRow, Row};
     use uuid::Uuid;
 
+    use super::parse_timestamp;
+
     /// Represents an audio track.
     #[derive(Debug, Serialize, Deserialize, Clone)]
     pub struct Track {
@@ -92,6 +99,9 @@ pub mod music_library {
 
         /// Timestamp for when the track was last played.
         pub last_played_at: Option<NaiveDateTime>,
+
+        /// Whether the track's file can no longer be found on disk.
+        pub is_missing: bool,
     }
 
     impl<'r> FromRow<'r, SqliteRow> for Track {
@@ -108,6 +118,7 @@ pub mod music_library {
             let created_at: &str = row.try_get("created_at")?;
             let updated_at: &str = row.try_get("updated_at")?;
             let last_played_at: Option<&str> = row.try_get("last_played_at")?;
+            let is_missing: bool = row.try_get("is_missing")?;
 
             Ok(Self {
                 id: Uuid::from_str(id).map_err(|e| sqlx::Error::Decode(e.into()))?,
@@ -119,13 +130,12 @@ pub mod music_library {
                 duration_secs,
                 file_path,
                 thumbnail_path,
-                created_at: NaiveDateTime::from_str(created_at)
+                created_at: parse_timestamp(created_at)
                     .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                updated_at: NaiveDateTime::from_str(updated_at)
+                updated_at: parse_timestamp(updated_at)
                     .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                last_played_at: last_played_at
-                    .map(|t| NaiveDateTime::from_str(t).ok())
-                    .flatten(),
+                last_played_at: last_played_at.map(|t| parse_timestamp(t).ok()).flatten(),
+                is_missing,
             })
         }
     }
@@ -227,13 +237,32 @@ pub mod music_library {
                 user_id: user_id.map(|uid| Uuid::from_str(uid).ok()).flatten(),
                 title,
                 thumbnail_path,
-                created_at: NaiveDateTime::from_str(created_at)
+                created_at: parse_timestamp(created_at)
                     .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                updated_at: NaiveDateTime::from_str(updated_at)
+                updated_at: parse_timestamp(updated_at)
                     .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                last_played_at: last_played_at
-                    .map(|t| NaiveDateTime::from_str(t).ok())
-                    .flatten(),
+                last_played_at: last_played_at.map(|t| parse_timestamp(t).ok()).flatten(),
+            })
+        }
+    }
+
+    /// A folder that was imported into the music library.
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    pub struct LibraryFolder {
+        /// The path to the folder.
+        pub path: String,
+
+        /// The user who imported the folder.
+        pub user_id: Option<Uuid>,
+    }
+
+    impl<'r> FromRow<'r, SqliteRow> for LibraryFolder {
+        fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+            let path: String = row.try_get("path")?;
+            let user_id: Option<&str> = row.try_get("user_id")?;
+            Ok(Self {
+                path,
+                user_id: user_id.and_then(|uid| Uuid::from_str(uid).ok()),
             })
         }
     }
@@ -260,6 +289,7 @@ pub mod music_library {
             let created_at: &str = row.try_get("created_at")?;
             let updated_at: &str = row.try_get("updated_at")?;
             let last_played_at: Option<&str> = row.try_get("last_played_at")?;
+            let is_missing: bool = row.try_get("is_missing")?;
 
             Ok(Self {
                 order,
@@ -273,15 +303,95 @@ pub mod music_library {
                     duration_secs,
                     file_path,
                     thumbnail_path,
-                    created_at: NaiveDateTime::from_str(created_at)
+                    created_at: parse_timestamp(created_at)
                         .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                    updated_at: NaiveDateTime::from_str(updated_at)
+                    updated_at: parse_timestamp(updated_at)
                         .map_err(|e| sqlx::Error::Decode(e.into()))?,
-                    last_played_at: last_played_at
-                        .map(|t| NaiveDateTime::from_str(t).ok())
-                        .flatten(),
+                    last_played_at: last_played_at.map(|t| parse_timestamp(t).ok()).flatten(),
+                    is_missing,
                 },
             })
         }
     }
+
+    /// A track in a user's play queue.
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct QueueEntry {
+        /// The ID of the entry (the same track can be queued more than once).
+        pub id: Uuid,
+
+        /// The queued track.
+        pub track_id: Uuid,
+
+        /// The entry's position in the queue before it was shuffled.
+        pub original_position: usize,
+    }
+
+    impl QueueEntry {
+        /// Creates a new entry for the track.
+        pub fn new(track_id: Uuid, original_position: usize) -> Self {
+            Self {
+                id: Uuid::new_v4(),
+                track_id,
+                original_position,
+            }
+        }
+    }
+
+    impl<'r> FromRow<'r, SqliteRow> for QueueEntry {
+        fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+            let id: &str = row.try_get("id")?;
+            let track_id: &str = row.try_get("track_id")?;
+            let original_position: i64 = row.try_get("original_position")?;
+            Ok(Self {
+                id: Uuid::from_str(id).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                track_id: Uuid::from_str(track_id).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                original_position: original_position as usize,
+            })
+        }
+    }
+
+    /// A play of a track (in the user's listening history).
+    #[derive(Debug, Serialize, Deserialize, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PlayRecord {
+        /// The ID of the play.
+        pub id: Uuid,
+
+        /// The user who played the track.
+        pub user_id: Uuid,
+
+        /// The track that was played.
+        pub track_id: Uuid,
+
+        /// Timestamp for when the track started playing.
+        pub started_at: NaiveDateTime,
+
+        /// How long the track was played for in milliseconds.
+        pub ms_played: u64,
+
+        /// Whether the track was played long enough to count as a play.
+        pub is_completed: bool,
+    }
+
+    impl<'r> FromRow<'r, SqliteRow> for PlayRecord {
+        fn from_row(row: &'r SqliteRow) -> Result<Self, sqlx::Error> {
+            let id: &str = row.try_get("id")?;
+            let user_id: &str = row.try_get("user_id")?;
+            let track_id: &str = row.try_get("track_id")?;
+            let started_at: &str = row.try_get("started_at")?;
+            let ms_played: i64 = row.try_get("ms_played")?;
+            let is_completed: bool = row.try_get("is_completed")?;
+            Ok(Self {
+                id: Uuid::from_str(id).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                user_id: Uuid::from_str(user_id).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                track_id: Uuid::from_str(track_id).map_err(|e| sqlx::Error::Decode(e.into()))?,
+                started_at: parse_timestamp(started_at)
+                    .map_err(|e| sqlx::Error::Decode(e.into()))?,
+                ms_played: ms_played.max(0) as u64,
+                is_completed,
+            })
+        }
+    }
 }
@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        bundle::{local_tracks, LocalTrack, DURATION_TOLERANCE_SECS},
+        client::DatabaseClient,
+        fuzzy::normalize,
+        models::music_library::Playlist,
+        playlists::{insert_playlist, save_playlist_order, PlaylistExt, MAX_PLAYLIST_TITLE_CHARS},
+        DBResult,
+    },
+    library::playlist_files::{
+        decode_playlist, normalize_path, parse_playlist, relative_path, write_playlist,
+        PlaylistEntry, PlaylistFile, PlaylistFormat,
+    },
+};
+
+/// The result of importing a playlist file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistFileImport {
+    /// The playlist that was created.
+    pub playlist: Playlist,
+
+    /// The file's entries that aren't in the library. They are left out of the playlist.
+    pub unresolved: Vec<PlaylistEntry>,
+}
+
+/// Database operations for importing and exporting M3U, PLS and XSPF playlist files.
+pub trait PlaylistFileExt {
+    /// Imports the playlist file at `path` as a new playlist for the user, in the format of its
+    /// extension.
+    ///
+    /// The file's entries are resolved by their path (relative to the playlist file unless it
+    /// is absolute), falling back to the end of their path (for a library that was moved) and
+    /// then to their title, artist and duration. Entries that resolve to a track that is
+    /// already in the playlist are skipped.
+    ///
+    /// The playlist's title is the file's title or name, cut to [MAX_PLAYLIST_TITLE_CHARS].
+    async fn import_playlist_file(
+        &self,
+        user_id: Uuid,
+        path: &Path,
+    ) -> DBResult<PlaylistFileImport>;
+
+    /// Exports the playlist to the file at `path`, in the format of its extension.
+    ///
+    /// With `relative_paths`, the tracks' paths are written relative to the playlist file's
+    /// directory so the file keeps working when it is moved along with the library.
+    async fn export_playlist_file(
+        &self,
+        playlist_id: Uuid,
+        path: &Path,
+        relative_paths: bool,
+    ) -> DBResult<()>;
+}
+
+impl PlaylistFileExt for DatabaseClient {
+    async fn import_playlist_file(
+        &self,
+        user_id: Uuid,
+        path: &Path,
+    ) -> DBResult<PlaylistFileImport> {
+        let format = PlaylistFormat::from_path(path)?;
+        let contents = std::fs::read(path)?;
+        let file = parse_playlist(format, &decode_playlist(path, contents))?;
+        let file_name = path.file_stem().map(|stem| stem.to_string_lossy());
+        let title = [file.title.as_deref(), file_name.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(str::trim)
+            .find(|title| !title.is_empty())
+            .unwrap_or(UNTITLED_PLAYLIST);
+        let title: String = title.chars().take(MAX_PLAYLIST_TITLE_CHARS).collect();
+
+        // Resolve the entries
+        let tracks: Vec<LocalTrack> = local_tracks(self)
+            .await?
+            .into_iter()
+            .filter(|track| !track.is_missing)
+            .collect();
+        let index = TrackIndex::new(&tracks);
+        let playlist_dir = path.parent().unwrap_or(Path::new(""));
+        let mut track_ids: Vec<Uuid> = vec![];
+        let mut unresolved = vec![];
+        for entry in file.entries {
+            match resolve_entry(&entry, playlist_dir, &index) {
+                Some(track_id) => {
+                    if !track_ids.contains(&track_id) {
+                        track_ids.push(track_id);
+                    }
+                }
+                None => unresolved.push(entry),
+            }
+        }
+
+        let mut tx = self.begin_write().await?;
+        let playlist = insert_playlist(&mut tx, user_id, title.trim_end()).await?;
+        save_playlist_order(&mut tx, playlist.id, &track_ids).await?;
+        tx.commit().await?;
+
+        Ok(PlaylistFileImport {
+            playlist: self
+                .get_playlist(playlist.id)
+                .await?
+                .ok_or(sqlx::Error::RowNotFound)?,
+            unresolved,
+        })
+    }
+
+    async fn export_playlist_file(
+        &self,
+        playlist_id: Uuid,
+        path: &Path,
+        relative_paths: bool,
+    ) -> DBResult<()> {
+        let format = PlaylistFormat::from_path(path)?;
+        let playlist = self
+            .get_playlist(playlist_id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+        let mut tracks: HashMap<Uuid, LocalTrack> = local_tracks(self)
+            .await?
+            .into_iter()
+            .map(|track| (track.id, track))
+            .collect();
+
+        let playlist_dir = path.parent().unwrap_or(Path::new(""));
+        let entries = self
+            .get_playlist_track_ids(playlist_id)
+            .await?
+            .into_iter()
+            .filter_map(|track_id| tracks.remove(&track_id))
+            .map(|track| {
+                let file_path = PathBuf::from(&track.file_path);
+                PlaylistEntry {
+                    path: Some(if relative_paths {
+                        relative_path(playlist_dir, &file_path)
+                    } else {
+                        file_path
+                    }),
+                    title: Some(track.title),
+                    artist: Some(track.artists.join(", ")).filter(|artist| !artist.is_empty()),
+                    duration_secs: track.duration_secs,
+                }
+            })
+            .collect();
+
+        let file = PlaylistFile {
+            title: Some(playlist.title),
+            entries,
+        };
+        std::fs::write(path, write_playlist(format, &file))?;
+        Ok(())
+    }
+}
+
+/// The title of a playlist that is imported from a file without a title or name.
+const UNTITLED_PLAYLIST: &str = "Untitled Playlist";
+
+/// The library's tracks, indexed by their paths for resolving the entries of a playlist file.
+struct TrackIndex<'a> {
+    tracks: &'a [LocalTrack],
+
+    /// The tracks' IDs by their (normalized) paths.
+    by_path: HashMap<PathBuf, Uuid>,
+
+    /// The names in the tracks' paths (see [path_names]) and their IDs, by their file names.
+    by_file_name: HashMap<&'a OsStr, Vec<(Vec<&'a OsStr>, Uuid)>>,
+}
+
+impl<'a> TrackIndex<'a> {
+    fn new(tracks: &'a [LocalTrack]) -> Self {
+        let mut by_path = HashMap::new();
+        let mut by_file_name: HashMap<_, Vec<_>> = HashMap::new();
+        for track in tracks {
+            let path = Path::new(&track.file_path);
+            by_path.insert(normalize_path(path), track.id);
+            let names = path_names(path);
+            if let Some(file_name) = names.last() {
+                by_file_name
+                    .entry(*file_name)
+                    .or_default()
+                    .push((names, track.id));
+            }
+        }
+        Self {
+            tracks,
+            by_path,
+            by_file_name,
+        }
+    }
+}
+
+/// Finds the track in the library that the playlist file's entry is.
+fn resolve_entry(entry: &PlaylistEntry, playlist_dir: &Path, index: &TrackIndex) -> Option<Uuid> {
+    if let Some(path) = &entry.path {
+        // The exact path
+        let full_path = normalize_path(&playlist_dir.join(path));
+        if let Some(track_id) = index.by_path.get(&full_path) {
+            return Some(*track_id);
+        }
+
+        // The longest end of the path that only one track's path ends with (which can only be
+        // a track with the same file name)
+        let names = path_names(path);
+        let candidates = names
+            .last()
+            .and_then(|file_name| index.by_file_name.get(file_name))
+            .map_or(&[][..], Vec::as_slice);
+        for start in 0..names.len() {
+            let suffix = &names[start..];
+            let mut matches = candidates
+                .iter()
+                .filter(|(track_names, _)| track_names.ends_with(suffix));
+            match (matches.next(), matches.next()) {
+                (Some((_, track_id)), None) => return Some(*track_id),
+                (Some(_), Some(_)) => break,
+                _ => {}
+            }
+        }
+    }
+
+    // The tags
+    let title = normalize(entry.title.as_deref()?);
+    let artist = entry.artist.as_deref().map(normalize);
+    index
+        .tracks
+        .iter()
+        .find(|track| {
+            let durations_match = match (track.duration_secs, entry.duration_secs) {
+                (Some(a), Some(b)) => (a - b).abs() <= DURATION_TOLERANCE_SECS,
+                _ => true,
+            };
+            let artists_match = artist.as_ref().is_none_or(|artist| {
+                *artist == normalize(&track.artists.join(" "))
+                    || track.artists.iter().any(|name| *artist == normalize(name))
+            });
+            normalize(&track.title) == title && artists_match && durations_match
+        })
+        .map(|track| track.id)
+}
+
+/// Gets the names of the directories and file in the path (without its root).
+fn path_names(path: &Path) -> Vec<&OsStr> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        playlists::PlaylistExt,
+        test_utils::{insert_track, TempDb},
+        users::UserExt,
+    };
+
+    #[test]
+    fn test_import_and_export_playlist_file() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let dir = temp.path.with_extension("playlists");
+            let music_dir = dir.join("music");
+            std::fs::create_dir_all(&music_dir).unwrap();
+
+            let exact = insert_track(db, "Exact").await;
+            let moved = insert_track(db, "Moved").await;
+            let tagged = insert_track(db, "Tagged").await;
+            for (track_id, file_path, duration_secs) in [
+                (exact, music_dir.join("Artist/exact.mp3"), 100),
+                (
+                    moved,
+                    PathBuf::from("/new/library/Artist/Album/moved.mp3"),
+                    200,
+                ),
+                (tagged, music_dir.join("tagged.mp3"), 300),
+            ] {
+                sqlx::query("UPDATE tracks SET file_path = $1, duration_secs = $2 WHERE id = $3")
+                    .bind(file_path.to_string_lossy().to_string())
+                    .bind(duration_secs)
+                    .bind(track_id.to_string())
+                    .execute(&db.pool)
+                    .await
+                    .unwrap();
+            }
+
+            let m3u_path = dir.join("mix.m3u8");
+            std::fs::write(
+                &m3u_path,
+                "#EXTM3U\n\
+                 music/Artist/exact.mp3\n\
+                 /old/library/Artist/Album/moved.mp3\n\
+                 #EXTINF:301,Tagged\n\
+                 /somewhere/else.mp3\n\
+                 #EXTINF:120,Nobody - Missing\n\
+                 missing.mp3\n\
+                 music/Artist/exact.mp3\n",
+            )
+            .unwrap();
+            let import = db.import_playlist_file(user_id, &m3u_path).await.unwrap();
+            assert_eq!(import.playlist.title, "mix");
+            assert_eq!(
+                db.get_playlist_track_ids(import.playlist.id).await.unwrap(),
+                vec![exact, moved, tagged]
+            );
+            assert_eq!(import.unresolved.len(), 1);
+            assert_eq!(import.unresolved[0].title.as_deref(), Some("Missing"));
+
+            // The exported paths resolve to the same tracks
+            for (file_name, relative_paths) in [("relative.xspf", true), ("absolute.pls", false)] {
+                let path = dir.join(file_name);
+                db.export_playlist_file(import.playlist.id, &path, relative_paths)
+                    .await
+                    .unwrap();
+                let contents = std::fs::read_to_string(&path).unwrap();
+                assert!(contents.contains("music/Artist/exact.mp3"));
+                assert_eq!(
+                    contents.contains(&music_dir.to_string_lossy().to_string()),
+                    !relative_paths,
+                    "{contents}"
+                );
+
+                let reimport = db.import_playlist_file(user_id, &path).await.unwrap();
+                assert_eq!(
+                    db.get_playlist_track_ids(reimport.playlist.id)
+                        .await
+                        .unwrap(),
+                    vec![exact, moved, tagged]
+                );
+                assert!(reimport.unresolved.is_empty());
+            }
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_import_playlist_file_title() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let dir = temp.path.with_extension("playlists");
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let long_title = "a".repeat(MAX_PLAYLIST_TITLE_CHARS + 1);
+            for (file_name, title, expected) in [
+                ("long.m3u", long_title.as_str(), &long_title[1..]),
+                ("Road Trip.m3u", "   ", "Road Trip"),
+                ("   .m3u", "", UNTITLED_PLAYLIST),
+            ] {
+                let path = dir.join(file_name);
+                std::fs::write(&path, format!("#EXTM3U\n#PLAYLIST:{title}\n")).unwrap();
+                let import = db.import_playlist_file(user_id, &path).await.unwrap();
+                assert_eq!(import.playlist.title, expected);
+            }
+
+            std::fs::remove_dir_all(&dir).unwrap();
+        });
+    }
+}
@@ -1,4 +1,7 @@
-use sqlx::Sqlite;
+use std::collections::HashMap;
+
+use chrono::Utc;
+use sqlx::{Sqlite, SqliteConnection};
 use uuid::Uuid;
 
 use crate::{
@@ -6,10 +9,20 @@ use crate::{
     database::{
         client::DatabaseClient,
         models::music_library::{Playlist, PlaylistTrack},
-        stream_rows, DBResult,
+        parse_ids,
+        query::SqlArg,
+        smart_playlists::SmartRules,
+        sql_arguments, stream_page, stream_rows, DBResult, Page, SortKey, SortOrder,
     },
+    errors::SpotsError,
 };
 
+/// The max number of playlists a user can pin.
+pub const MAX_PINNED_PLAYLISTS: usize = 10;
+
+/// The max number of characters in a playlist's title (like the limit of `PlaylistTitleDto`).
+pub const MAX_PLAYLIST_TITLE_CHARS: usize = 100;
+
 /// Database operations for [Playlist].
 pub trait PlaylistExt {
     /// Gets the specified playlist from the DB.
@@ -17,7 +30,8 @@ pub trait PlaylistExt {
 
     /// Gets all the tracks in the playlist.
     ///
-    /// The tracks are accompanied by their order in the playlist.
+    /// The tracks are accompanied by their order in the playlist. The tracks of a smart playlist
+    /// are the ones that currently match its rules.
     ///
     /// # Note
     /// The tracks are all streamed to the `channel`.
@@ -27,15 +41,112 @@ pub trait PlaylistExt {
         channel: ResponseChannel<PlaylistTrack>,
     ) -> DBResult<()>;
 
-    /// Gets all of the user's pinned playlists.
+    /// Gets the IDs of the playlist's tracks, in playlist order.
+    ///
+    /// The tracks of a smart playlist are the ones that currently match its rules.
+    async fn get_playlist_track_ids(&self, playlist_id: Uuid) -> DBResult<Vec<Uuid>>;
+
+    /// Gets all of the user's pinned playlists, in the order the user chose.
     async fn get_pinned_playlists(&self, user_id: Uuid) -> DBResult<Vec<Playlist>>;
 
-    /// Gets all of the user's playlists.
+    /// Pins the playlist after the user's other pinned playlists.
+    ///
+    /// Only playlists the user owns or follows can be pinned ([SpotsError::PlaylistAccessDenied]
+    /// otherwise). Pinning a playlist that is already pinned does nothing. Returns the user's
+    /// pinned playlists, or [SpotsError::PinLimitReached] if [MAX_PINNED_PLAYLISTS] are already
+    /// pinned.
+    async fn pin_playlist(&self, user_id: Uuid, playlist_id: Uuid) -> DBResult<Vec<Playlist>>;
+
+    /// Unpins the playlist.
+    ///
+    /// Returns the user's pinned playlists.
+    async fn unpin_playlist(&self, user_id: Uuid, playlist_id: Uuid) -> DBResult<Vec<Playlist>>;
+
+    /// Moves the pinned playlist to the `index` in the user's pinned playlists.
+    ///
+    /// Returns the user's pinned playlists.
+    async fn move_pinned_playlist(
+        &self,
+        user_id: Uuid,
+        playlist_id: Uuid,
+        index: usize,
+    ) -> DBResult<Vec<Playlist>>;
+
+    /// Gets a page of the user's playlists.
+    ///
+    /// Playlists can only be sorted by title, when they were added or when they were last
+    /// played, otherwise [SpotsError::UnsupportedSortKey] is returned. Returns the cursor of the
+    /// next page.
+    ///
+    /// # Note
+    /// The playlists are all streamed to the `channel`.
     async fn get_all_playlists(
         &self,
         user_id: Uuid,
+        page: &Page,
         channel: ResponseChannel<Playlist>,
-    ) -> DBResult<()>;
+    ) -> DBResult<Option<String>>;
+
+    /// Creates an empty playlist for the user.
+    async fn create_playlist(&self, user_id: Uuid, title: &str) -> DBResult<Playlist>;
+
+    /// Creates a smart playlist for the user, whose tracks are the ones that match the rules.
+    async fn create_smart_playlist(
+        &self,
+        user_id: Uuid,
+        title: &str,
+        rules: &SmartRules,
+    ) -> DBResult<Playlist>;
+
+    /// Gets the rules of the playlist (`None` if it isn't a smart playlist).
+    async fn get_smart_rules(&self, playlist_id: Uuid) -> DBResult<Option<SmartRules>>;
+
+    /// Replaces the rules of the smart playlist.
+    async fn set_smart_rules(&self, playlist_id: Uuid, rules: &SmartRules) -> DBResult<Playlist>;
+
+    /// Renames the playlist.
+    async fn rename_playlist(&self, playlist_id: Uuid, title: &str) -> DBResult<Playlist>;
+
+    /// Sets the path to the playlist's thumbnail.
+    async fn set_playlist_thumbnail(
+        &self,
+        playlist_id: Uuid,
+        thumbnail_path: &str,
+    ) -> DBResult<Playlist>;
+
+    /// Deletes the playlist (and its list of tracks).
+    async fn delete_playlist(&self, playlist_id: Uuid) -> DBResult<()>;
+
+    /// Adds the tracks to the playlist at the `index` (or at the end if `None`).
+    ///
+    /// Tracks that are already in the playlist are skipped. Returns the IDs of the playlist's
+    /// tracks in their new order, or [SpotsError::SmartPlaylistNotEditable] for a smart
+    /// playlist (the same goes for removing and moving tracks).
+    async fn add_playlist_tracks(
+        &self,
+        playlist_id: Uuid,
+        track_ids: &[Uuid],
+        index: Option<usize>,
+    ) -> DBResult<Vec<Uuid>>;
+
+    /// Removes the tracks from the playlist.
+    ///
+    /// Returns the IDs of the playlist's tracks in their new order.
+    async fn remove_playlist_tracks(
+        &self,
+        playlist_id: Uuid,
+        track_ids: &[Uuid],
+    ) -> DBResult<Vec<Uuid>>;
+
+    /// Moves the track to the `index` in the playlist.
+    ///
+    /// Returns the IDs of the playlist's tracks in their new order.
+    async fn move_playlist_track(
+        &self,
+        playlist_id: Uuid,
+        track_id: Uuid,
+        index: usize,
+    ) -> DBResult<Vec<Uuid>>;
 }
 
 impl PlaylistExt for DatabaseClient {
@@ -52,12 +163,21 @@ impl PlaylistExt for DatabaseClient {
         playlist_id: Uuid,
         channel: ResponseChannel<PlaylistTrack>,
     ) -> DBResult<()> {
+        if let Some((sql, args)) = smart_tracks_sql(self, playlist_id).await? {
+            let playlist_tracks =
+                sqlx::query_as_with::<Sqlite, PlaylistTrack, _>(&sql, sql_arguments(args)?)
+                    .fetch(&self.pool);
+            stream_rows(playlist_tracks, channel).await?;
+            return Ok(());
+        }
+
         let playlist_tracks = sqlx::query_as::<Sqlite, PlaylistTrack>(
             "
             SELECT pt.track_order, t.*
             FROM tracks t
             LEFT JOIN playlist_tracks pt ON t.id = pt.track_id
             WHERE pt.playlist_id = $1
+            ORDER BY pt.track_order
             ",
         )
         .bind(playlist_id.to_string())
@@ -69,6 +189,29 @@ impl PlaylistExt for DatabaseClient {
         Ok(())
     }
 
+    async fn get_playlist_track_ids(&self, playlist_id: Uuid) -> DBResult<Vec<Uuid>> {
+        if let Some((sql, args)) = smart_tracks_sql(self, playlist_id).await? {
+            let sql = format!("SELECT s.track_order, s.id FROM ({sql}) s");
+            let track_ids: Vec<(i64, String)> = sqlx::query_as_with(&sql, sql_arguments(args)?)
+                .fetch_all(&self.pool)
+                .await?;
+            return parse_ids(track_ids.into_iter().map(|(_, id)| id).collect());
+        }
+
+        let track_ids: Vec<String> = sqlx::query_scalar(
+            "
+            SELECT pt.track_id
+            FROM playlist_tracks pt
+            WHERE pt.playlist_id = $1
+            ORDER BY pt.track_order
+            ",
+        )
+        .bind(playlist_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        parse_ids(track_ids)
+    }
+
     async fn get_pinned_playlists(&self, user_id: Uuid) -> DBResult<Vec<Playlist>> {
         let pinned_playlists: Vec<Playlist> = sqlx::query_as(
             "
@@ -76,6 +219,7 @@ impl PlaylistExt for DatabaseClient {
             FROM playlists p
             LEFT JOIN pinned_playlists pp ON p.id = pp.playlist_id
             WHERE pp.user_id = $1
+            ORDER BY pp.pin_order
             ",
         )
         .bind(user_id.to_string())
@@ -88,21 +232,706 @@ impl PlaylistExt for DatabaseClient {
     async fn get_all_playlists(
         &self,
         user_id: Uuid,
+        page: &Page,
         channel: ResponseChannel<Playlist>,
-    ) -> DBResult<()> {
-        let playlists = sqlx::query_as::<Sqlite, Playlist>(
+    ) -> DBResult<Option<String>> {
+        let order = page.order(SortOrder::BY_TITLE);
+        let sort_sql = match order.key {
+            SortKey::Title => "r.title COLLATE NOCASE",
+            SortKey::Added => "r.created_at",
+            SortKey::LastPlayed => "COALESCE(r.last_played_at, '')",
+            key => return Err(SpotsError::UnsupportedSortKey(key)),
+        };
+        stream_page(
+            &self.pool,
+            "SELECT * FROM playlists WHERE user_id = $1",
+            vec![SqlArg::Text(user_id.to_string())],
+            sort_sql,
+            order,
+            page,
+            channel,
+        )
+        .await
+    }
+
+    async fn create_playlist(&self, user_id: Uuid, title: &str) -> DBResult<Playlist> {
+        let mut conn = self.pool.acquire().await?;
+        insert_playlist(&mut conn, user_id, title).await
+    }
+
+    async fn create_smart_playlist(
+        &self,
+        user_id: Uuid,
+        title: &str,
+        rules: &SmartRules,
+    ) -> DBResult<Playlist> {
+        let mut tx = self.begin_write().await?;
+        let playlist = insert_playlist(&mut tx, user_id, title).await?;
+        sqlx::query("INSERT INTO smart_playlists (playlist_id, rules) VALUES ($1, $2)")
+            .bind(playlist.id.to_string())
+            .bind(rules_json(rules)?)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(playlist)
+    }
+
+    async fn get_smart_rules(&self, playlist_id: Uuid) -> DBResult<Option<SmartRules>> {
+        let mut conn = self.pool.acquire().await?;
+        smart_rules(&mut conn, playlist_id).await
+    }
+
+    async fn set_smart_rules(&self, playlist_id: Uuid, rules: &SmartRules) -> DBResult<Playlist> {
+        let mut tx = self.begin_write().await?;
+        let result = sqlx::query("UPDATE smart_playlists SET rules = $1 WHERE playlist_id = $2")
+            .bind(rules_json(rules)?)
+            .bind(playlist_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound.into());
+        }
+        let playlist: Playlist = sqlx::query_as(
             "
-            SELECT *
-            FROM playlists 
-            WHERE user_id = $1
+            UPDATE playlists
+            SET updated_at = $1
+            WHERE id = $2
+            RETURNING *
             ",
         )
-        .bind(user_id.to_string())
-        .fetch(&self.pool);
+        .bind(Utc::now().naive_local().to_string())
+        .bind(playlist_id.to_string())
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(playlist)
+    }
 
-        // Stream track to the channel
-        stream_rows(playlists, channel).await?;
+    async fn rename_playlist(&self, playlist_id: Uuid, title: &str) -> DBResult<Playlist> {
+        let playlist: Playlist = sqlx::query_as(
+            "
+            UPDATE playlists
+            SET title = $1, updated_at = $2
+            WHERE id = $3
+            RETURNING *
+            ",
+        )
+        .bind(title)
+        .bind(Utc::now().naive_local().to_string())
+        .bind(playlist_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(playlist)
+    }
+
+    async fn set_playlist_thumbnail(
+        &self,
+        playlist_id: Uuid,
+        thumbnail_path: &str,
+    ) -> DBResult<Playlist> {
+        let playlist: Playlist = sqlx::query_as(
+            "
+            UPDATE playlists
+            SET thumbnail_path = $1, updated_at = $2
+            WHERE id = $3
+            RETURNING *
+            ",
+        )
+        .bind(thumbnail_path)
+        .bind(Utc::now().naive_local().to_string())
+        .bind(playlist_id.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(playlist)
+    }
 
+    async fn delete_playlist(&self, playlist_id: Uuid) -> DBResult<()> {
+        let result = sqlx::query("DELETE FROM playlists WHERE id = $1")
+            .bind(playlist_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(sqlx::Error::RowNotFound.into());
+        }
         Ok(())
     }
+
+    async fn add_playlist_tracks(
+        &self,
+        playlist_id: Uuid,
+        track_ids: &[Uuid],
+        index: Option<usize>,
+    ) -> DBResult<Vec<Uuid>> {
+        let mut tx = self.begin_write().await?;
+        let mut order = playlist_order(&mut tx, playlist_id).await?;
+        let new_tracks: Vec<Uuid> = track_ids
+            .iter()
+            .enumerate()
+            .filter(|(i, id)| !order.contains(id) && !track_ids[..*i].contains(id))
+            .map(|(_, id)| *id)
+            .collect();
+        let index = index.unwrap_or(order.len()).min(order.len());
+        order.splice(index..index, new_tracks);
+        save_playlist_order(&mut tx, playlist_id, &order).await?;
+        tx.commit().await?;
+        Ok(order)
+    }
+
+    async fn remove_playlist_tracks(
+        &self,
+        playlist_id: Uuid,
+        track_ids: &[Uuid],
+    ) -> DBResult<Vec<Uuid>> {
+        let mut tx = self.begin_write().await?;
+        let mut order = playlist_order(&mut tx, playlist_id).await?;
+        order.retain(|id| !track_ids.contains(id));
+        save_playlist_order(&mut tx, playlist_id, &order).await?;
+        tx.commit().await?;
+        Ok(order)
+    }
+
+    async fn move_playlist_track(
+        &self,
+        playlist_id: Uuid,
+        track_id: Uuid,
+        index: usize,
+    ) -> DBResult<Vec<Uuid>> {
+        let mut tx = self.begin_write().await?;
+        let mut order = playlist_order(&mut tx, playlist_id).await?;
+        let from = order
+            .iter()
+            .position(|id| *id == track_id)
+            .ok_or_else(|| sqlx::Error::RowNotFound)?;
+        let track_id = order.remove(from);
+        order.insert(index.min(order.len()), track_id);
+        save_playlist_order(&mut tx, playlist_id, &order).await?;
+        tx.commit().await?;
+        Ok(order)
+    }
+
+    async fn pin_playlist(&self, user_id: Uuid, playlist_id: Uuid) -> DBResult<Vec<Playlist>> {
+        let mut tx = self.begin_write().await?;
+        let has_access: Option<bool> = sqlx::query_scalar(
+            "
+            SELECT user_id IS $2 OR EXISTS (
+                SELECT 1
+                FROM followed_playlists
+                WHERE user_id = $2 AND playlist_id = $1
+            )
+            FROM playlists
+            WHERE id = $1
+            ",
+        )
+        .bind(playlist_id.to_string())
+        .bind(user_id.to_string())
+        .fetch_optional(&mut *tx)
+        .await?;
+        match has_access {
+            None => return Err(sqlx::Error::RowNotFound.into()),
+            Some(false) => return Err(SpotsError::PlaylistAccessDenied(playlist_id)),
+            Some(true) => {}
+        }
+
+        let mut order = pinned_order(&mut tx, user_id).await?;
+        if !order.contains(&playlist_id) {
+            if order.len() >= MAX_PINNED_PLAYLISTS {
+                return Err(SpotsError::PinLimitReached(MAX_PINNED_PLAYLISTS));
+            }
+            order.push(playlist_id);
+            save_pinned_order(&mut tx, user_id, &order).await?;
+        }
+        tx.commit().await?;
+        self.get_pinned_playlists(user_id).await
+    }
+
+    async fn unpin_playlist(&self, user_id: Uuid, playlist_id: Uuid) -> DBResult<Vec<Playlist>> {
+        let mut tx = self.begin_write().await?;
+        let mut order = pinned_order(&mut tx, user_id).await?;
+        order.retain(|id| *id != playlist_id);
+        save_pinned_order(&mut tx, user_id, &order).await?;
+        tx.commit().await?;
+        self.get_pinned_playlists(user_id).await
+    }
+
+    async fn move_pinned_playlist(
+        &self,
+        user_id: Uuid,
+        playlist_id: Uuid,
+        index: usize,
+    ) -> DBResult<Vec<Playlist>> {
+        let mut tx = self.begin_write().await?;
+        let mut order = pinned_order(&mut tx, user_id).await?;
+        let from = order
+            .iter()
+            .position(|id| *id == playlist_id)
+            .ok_or_else(|| sqlx::Error::RowNotFound)?;
+        let playlist_id = order.remove(from);
+        order.insert(index.min(order.len()), playlist_id);
+        save_pinned_order(&mut tx, user_id, &order).await?;
+        tx.commit().await?;
+        self.get_pinned_playlists(user_id).await
+    }
+}
+
+/// Gets the IDs of the user's pinned playlists (in order) within a transaction.
+pub(crate) async fn pinned_order(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+) -> DBResult<Vec<Uuid>> {
+    let playlist_ids: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT playlist_id
+        FROM pinned_playlists
+        WHERE user_id = $1
+        ORDER BY pin_order
+        ",
+    )
+    .bind(user_id.to_string())
+    .fetch_all(&mut *conn)
+    .await?;
+    parse_ids(playlist_ids)
+}
+
+/// Replaces the user's pinned playlists, numbering their `pin_order` from `0`.
+pub(crate) async fn save_pinned_order(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    playlist_ids: &[Uuid],
+) -> DBResult<()> {
+    sqlx::query("DELETE FROM pinned_playlists WHERE user_id = $1")
+        .bind(user_id.to_string())
+        .execute(&mut *conn)
+        .await?;
+    for (order, playlist_id) in playlist_ids.iter().enumerate() {
+        sqlx::query(
+            "
+            INSERT INTO pinned_playlists (user_id, playlist_id, pin_order)
+            VALUES ($1, $2, $3)
+            ",
+        )
+        .bind(user_id.to_string())
+        .bind(playlist_id.to_string())
+        .bind(order as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Inserts an empty playlist for the user.
+pub(crate) async fn insert_playlist(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    title: &str,
+) -> DBResult<Playlist> {
+    let now = Utc::now().naive_local().to_string();
+    let playlist: Playlist = sqlx::query_as(
+        "
+        INSERT INTO playlists (id, user_id, title, thumbnail_path, created_at, updated_at)
+        VALUES ($1, $2, $3, '', $4, $4)
+        RETURNING *
+        ",
+    )
+    .bind(Uuid::new_v4().to_string())
+    .bind(user_id.to_string())
+    .bind(title)
+    .bind(now)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(playlist)
+}
+
+/// Gets the IDs of the user's playlists by their titles (for imports to skip the playlists they
+/// already created).
+pub(crate) async fn playlist_ids_by_title(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+) -> DBResult<HashMap<String, Uuid>> {
+    let (titles, ids): (Vec<String>, Vec<String>) =
+        sqlx::query_as::<_, (String, String)>("SELECT title, id FROM playlists WHERE user_id = $1")
+            .bind(user_id.to_string())
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .unzip();
+    Ok(titles.into_iter().zip(parse_ids(ids)?).collect())
+}
+
+/// Gets the rules of the playlist (`None` if it isn't a smart playlist).
+pub(crate) async fn smart_rules(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+) -> DBResult<Option<SmartRules>> {
+    let rules: Option<String> =
+        sqlx::query_scalar("SELECT rules FROM smart_playlists WHERE playlist_id = $1")
+            .bind(playlist_id.to_string())
+            .fetch_optional(&mut *conn)
+            .await?;
+    rules
+        .map(|rules| serde_json::from_str(&rules).map_err(|e| sqlx::Error::Decode(e.into()).into()))
+        .transpose()
+}
+
+/// Serializes the rules of a smart playlist to JSON.
+pub(crate) fn rules_json(rules: &SmartRules) -> DBResult<String> {
+    serde_json::to_string(rules).map_err(|e| sqlx::Error::Encode(e.into()).into())
+}
+
+/// Builds the query for the tracks of a smart playlist (`None` if it isn't a smart playlist).
+///
+/// The query selects the `track_order` and columns of the tracks that match the rules. The
+/// play counts of the playlist's owner are used, so a playlist without an owner is an error.
+async fn smart_tracks_sql(
+    db: &DatabaseClient,
+    playlist_id: Uuid,
+) -> DBResult<Option<(String, Vec<SqlArg>)>> {
+    let mut conn = db.pool.acquire().await?;
+    let Some(rules) = smart_rules(&mut conn, playlist_id).await? else {
+        return Ok(None);
+    };
+    let user_id: Option<String> = sqlx::query_scalar("SELECT user_id FROM playlists WHERE id = $1")
+        .bind(playlist_id.to_string())
+        .fetch_one(&mut *conn)
+        .await?;
+
+    // The rules use the owner's play counts, which are gone if the owner was deleted
+    let Some(user_id) = user_id else {
+        return Err(SpotsError::SmartPlaylistWithoutOwner(playlist_id));
+    };
+    let user_id = Uuid::parse_str(&user_id).map_err(|e| sqlx::Error::Decode(e.into()))?;
+
+    let mut args = Vec::new();
+    let (condition, order) = rules.to_sql(user_id, &mut args);
+    args.push(SqlArg::Int(rules.limit.map_or(-1, i64::from)));
+    let sql = format!(
+        "
+        SELECT ROW_NUMBER() OVER (ORDER BY {order}) - 1 AS track_order, t.*
+        FROM tracks t
+        WHERE {condition}
+        ORDER BY track_order
+        LIMIT ${}
+        ",
+        args.len()
+    );
+    Ok(Some((sql, args)))
+}
+
+/// Gets the IDs of the playlist's tracks (in order) within a transaction.
+///
+/// Returns [SpotsError::SmartPlaylistNotEditable] for a smart playlist, since its tracks
+/// aren't stored.
+pub(crate) async fn playlist_order(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+) -> DBResult<Vec<Uuid>> {
+    if smart_rules(conn, playlist_id).await?.is_some() {
+        return Err(SpotsError::SmartPlaylistNotEditable(playlist_id));
+    }
+
+    let track_ids: Vec<String> = sqlx::query_scalar(
+        "
+        SELECT track_id
+        FROM playlist_tracks
+        WHERE playlist_id = $1
+        ORDER BY track_order
+        ",
+    )
+    .bind(playlist_id.to_string())
+    .fetch_all(&mut *conn)
+    .await?;
+    parse_ids(track_ids)
+}
+
+/// Replaces the playlist's tracks, numbering their `track_order` from `0`.
+///
+/// The rows are rewritten instead of shifted in place, since shifting them would temporarily
+/// break the uniqueness of `track_order` within the playlist.
+pub(crate) async fn save_playlist_order(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+    track_ids: &[Uuid],
+) -> DBResult<()> {
+    sqlx::query("DELETE FROM playlist_tracks WHERE playlist_id = $1")
+        .bind(playlist_id.to_string())
+        .execute(&mut *conn)
+        .await?;
+    for (order, track_id) in track_ids.iter().enumerate() {
+        sqlx::query(
+            "
+            INSERT INTO playlist_tracks (playlist_id, track_id, track_order)
+            VALUES ($1, $2, $3)
+            ",
+        )
+        .bind(playlist_id.to_string())
+        .bind(track_id.to_string())
+        .bind(order as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let result = sqlx::query("UPDATE playlists SET updated_at = $1 WHERE id = $2")
+        .bind(Utc::now().naive_local().to_string())
+        .bind(playlist_id.to_string())
+        .execute(&mut *conn)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(sqlx::Error::RowNotFound.into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        favorites::{FavoriteExt, FavoriteKind},
+        history::{HistoryExt, NewPlay},
+        library::{LibraryExt, NewTrack},
+        test_utils::{insert_track, response_channel, streamed_values, TempDb},
+        users::UserExt,
+    };
+
+    /// Gets the playlist's `(track_id, track_order)` rows in order.
+    async fn track_orders(db: &DatabaseClient, playlist_id: Uuid) -> Vec<(Uuid, i64)> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "
+            SELECT track_id, track_order
+            FROM playlist_tracks
+            WHERE playlist_id = $1
+            ORDER BY track_order
+            ",
+        )
+        .bind(playlist_id.to_string())
+        .fetch_all(&db.pool)
+        .await
+        .unwrap();
+        rows.into_iter()
+            .map(|(track_id, order)| (Uuid::parse_str(&track_id).unwrap(), order))
+            .collect()
+    }
+
+    /// Checks that the playlist's tracks are in the `expected` order, numbered without gaps.
+    async fn assert_order(db: &DatabaseClient, playlist_id: Uuid, expected: &[Uuid]) {
+        let expected: Vec<(Uuid, i64)> = expected
+            .iter()
+            .enumerate()
+            .map(|(order, track_id)| (*track_id, order as i64))
+            .collect();
+        assert_eq!(track_orders(db, playlist_id).await, expected);
+    }
+
+    #[test]
+    fn test_pinned_playlists() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+            let mut playlists = vec![];
+            for i in 0..=MAX_PINNED_PLAYLISTS {
+                let title = format!("Playlist {i}");
+                playlists.push(db.create_playlist(user_id, &title).await.unwrap().id);
+            }
+            let pinned_ids = |pinned: Vec<Playlist>| -> Vec<Uuid> {
+                pinned.into_iter().map(|playlist| playlist.id).collect()
+            };
+
+            // Only playlists the user owns or follows can be pinned
+            let other = db.create_playlist(other_id, "Other").await.unwrap().id;
+            assert!(matches!(
+                db.pin_playlist(user_id, other).await,
+                Err(SpotsError::PlaylistAccessDenied(_))
+            ));
+            assert!(matches!(
+                db.pin_playlist(user_id, Uuid::new_v4()).await,
+                Err(SpotsError::DatabaseError(sqlx::Error::RowNotFound))
+            ));
+            db.toggle_favorite(user_id, FavoriteKind::Playlist, other)
+                .await
+                .unwrap();
+            assert_eq!(
+                pinned_ids(db.pin_playlist(user_id, other).await.unwrap()),
+                vec![other]
+            );
+
+            // Up to the cap, pinning the same playlist twice doesn't count
+            for playlist_id in &playlists[..MAX_PINNED_PLAYLISTS - 1] {
+                db.pin_playlist(user_id, *playlist_id).await.unwrap();
+            }
+            db.pin_playlist(user_id, other).await.unwrap();
+            assert!(matches!(
+                db.pin_playlist(user_id, playlists[MAX_PINNED_PLAYLISTS - 1])
+                    .await,
+                Err(SpotsError::PinLimitReached(MAX_PINNED_PLAYLISTS))
+            ));
+            let pinned = pinned_ids(db.unpin_playlist(user_id, other).await.unwrap());
+            assert_eq!(pinned, playlists[..MAX_PINNED_PLAYLISTS - 1]);
+            db.pin_playlist(user_id, playlists[MAX_PINNED_PLAYLISTS - 1])
+                .await
+                .unwrap();
+
+            // Reordering
+            let (first, last) = (playlists[0], playlists[MAX_PINNED_PLAYLISTS - 1]);
+            let pinned = pinned_ids(db.move_pinned_playlist(user_id, first, 100).await.unwrap());
+            assert_eq!(pinned.last(), Some(&first));
+            let pinned = pinned_ids(db.move_pinned_playlist(user_id, last, 0).await.unwrap());
+            assert_eq!(pinned[0], last);
+            assert_eq!(pinned[1], playlists[1]);
+            assert_eq!(pinned.len(), MAX_PINNED_PLAYLISTS);
+            assert_eq!(
+                pinned_ids(db.get_pinned_playlists(user_id).await.unwrap()),
+                pinned
+            );
+            assert!(db
+                .move_pinned_playlist(user_id, playlists[MAX_PINNED_PLAYLISTS], 0)
+                .await
+                .is_err());
+
+            // Pins are per user
+            assert!(db.get_pinned_playlists(other_id).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_playlist_track_order() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let mut tracks = vec![];
+            for title in ["A", "B", "C", "D"] {
+                tracks.push(insert_track(db, title).await);
+            }
+            let [a, b, c, d] = tracks[..] else {
+                unreachable!()
+            };
+
+            // Both playlists start at `track_order` 0
+            let first = db.create_playlist(user_id, "First").await.unwrap().id;
+            let second = db.create_playlist(user_id, "Second").await.unwrap().id;
+            db.add_playlist_tracks(first, &[a, b], None).await.unwrap();
+            db.add_playlist_tracks(second, &[a, c], None).await.unwrap();
+            assert_order(db, first, &[a, b]).await;
+            assert_order(db, second, &[a, c]).await;
+
+            // Adding at an index, skipping tracks that are already in the playlist
+            let order = db
+                .add_playlist_tracks(first, &[c, a, d, c], Some(1))
+                .await
+                .unwrap();
+            assert_eq!(order, vec![a, c, d, b]);
+            assert_order(db, first, &order).await;
+
+            // Removing closes the gap
+            let order = db.remove_playlist_tracks(first, &[c]).await.unwrap();
+            assert_eq!(order, vec![a, d, b]);
+            assert_order(db, first, &order).await;
+
+            // Moving forwards and backwards
+            let order = db.move_playlist_track(first, a, 2).await.unwrap();
+            assert_eq!(order, vec![d, b, a]);
+            assert_order(db, first, &order).await;
+            let order = db.move_playlist_track(first, b, 0).await.unwrap();
+            assert_eq!(order, vec![b, d, a]);
+            assert_order(db, first, &order).await;
+            assert!(db.move_playlist_track(first, c, 0).await.is_err());
+
+            // The other playlist is left as is
+            assert_order(db, second, &[a, c]).await;
+        });
+    }
+
+    #[test]
+    fn test_smart_playlist_tracks() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+
+            // `(title, genre, days since it was added, the owner's plays, other plays)`
+            let mut track_ids = vec![];
+            for (title, genre, days_ago, plays, other_plays) in [
+                ("A", "Jazz", 1, 3, 0),
+                ("B", "Jazz", 2, 1, 5),
+                ("C", "Smooth jazz", 3, 2, 0),
+                ("D", "Jazz", 60, 5, 0),
+                ("E", "Rock", 1, 9, 0),
+            ] {
+                let track = NewTrack {
+                    file_path: format!("/music/{title}.mp3"),
+                    title: String::from(title),
+                    genres: vec![String::from(genre)],
+                    duration_secs: Some(180),
+                    ..Default::default()
+                };
+                let track_id = db.upsert_track(user_id, track).await.unwrap().id;
+                let added_at = Utc::now().naive_local() - chrono::Duration::days(days_ago);
+                sqlx::query("UPDATE tracks SET created_at = $1 WHERE id = $2")
+                    .bind(added_at.to_string())
+                    .bind(track_id.to_string())
+                    .execute(&db.pool)
+                    .await
+                    .unwrap();
+                for (user_id, plays) in [(user_id, plays), (other_id, other_plays)] {
+                    for _ in 0..plays {
+                        let play = NewPlay {
+                            track_id,
+                            playlist_id: None,
+                            started_at: Utc::now().naive_local(),
+                            ms_played: 180_000,
+                        };
+                        db.record_play(user_id, play).await.unwrap();
+                    }
+                }
+                track_ids.push(track_id);
+            }
+
+            // The owner's most played jazz tracks that were added in the last 30 days
+            let rules: SmartRules = serde_json::from_str(
+                r#"{
+                    "rules": [
+                        { "field": "genre", "value": "jazz" },
+                        { "field": "added", "days": 30 }
+                    ],
+                    "limit": 2,
+                    "sort": { "by": "playCount", "descending": true }
+                }"#,
+            )
+            .unwrap();
+            let playlist = db
+                .create_smart_playlist(user_id, "Jazz", &rules)
+                .await
+                .unwrap();
+            let expected = vec![track_ids[0], track_ids[2]];
+            assert_eq!(
+                db.get_playlist_track_ids(playlist.id).await.unwrap(),
+                expected
+            );
+            let (channel, responses) = response_channel();
+            db.get_playlist_tracks(playlist.id, channel).await.unwrap();
+            let streamed: Vec<(i64, String)> = streamed_values(&responses)
+                .iter()
+                .map(|row| {
+                    let id = row["track"]["id"].as_str().unwrap().to_owned();
+                    (row["order"].as_i64().unwrap(), id)
+                })
+                .collect();
+            assert_eq!(
+                streamed,
+                vec![(0, track_ids[0].to_string()), (1, track_ids[2].to_string())]
+            );
+
+            // Without an owner, there are no play counts to use
+            sqlx::query("UPDATE playlists SET user_id = NULL WHERE id = $1")
+                .bind(playlist.id.to_string())
+                .execute(&db.pool)
+                .await
+                .unwrap();
+            assert!(matches!(
+                db.get_playlist_track_ids(playlist.id).await,
+                Err(SpotsError::SmartPlaylistWithoutOwner(id)) if id == playlist.id
+            ));
+        });
+    }
 }
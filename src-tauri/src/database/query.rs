@@ -0,0 +1,625 @@
+//! A small query language for filtering the tracks in the library, e.g.
+//! `artist:"Daft Punk" year:>2000 genre:house duration:<300 played:never`.
+//!
+//! A query is a list of filters that all have to match. Filters can be negated with `-`,
+//! combined with `OR` and grouped with parentheses. The supported filters are:
+//!
+//! - Bare words or `"quoted text"`: matches the title, album or an artist of the track.
+//! - `title:`, `album:`, `artist:` and `genre:` followed by text: matches that field.
+//! - `year:`, `duration:` (in seconds) and `played:` (the user's play count) followed by a
+//!   number, a comparison (`>2000`, `<=300`) or a range (`1990..1999`). `played:never` is the
+//!   same as `played:0`.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::SpotsError;
+
+/// A range of characters in the query (`start` inclusive, `end` exclusive).
+///
+/// The offsets count characters, not bytes. [parse_query] reports them in UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    fn error(self, message: impl Into<String>) -> SpotsError {
+        SpotsError::QueryParseError {
+            message: message.into(),
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+/// A parsed query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// All of the expressions have to match (matches everything if empty).
+    And(Vec<Expr>),
+
+    /// Any of the expressions has to match.
+    Or(Vec<Expr>),
+
+    /// The expression must not match.
+    Not(Box<Expr>),
+
+    Filter(Filter),
+}
+
+/// A single filter of a query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Matches the title, album or an artist of the track.
+    Text(String),
+    Title(String),
+    Album(String),
+    Artist(String),
+    Genre(String),
+    Year(NumberFilter),
+
+    /// The track's duration in seconds.
+    Duration(NumberFilter),
+
+    /// How many times the user played the track.
+    Played(NumberFilter),
+}
+
+/// A filter on a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFilter {
+    Compare(Comparison, i64),
+
+    /// Between the two numbers (inclusive).
+    Range(i64, i64),
+}
+
+/// How a number is compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+/// A value that is bound to a compiled query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlArg {
+    Text(String),
+    Int(i64),
+}
+
+/// Parses the query.
+///
+/// An empty query matches every track. Errors are returned as [SpotsError::QueryParseError]
+/// with the span of the invalid part of the query, in UTF-16 code units (like the indices of a
+/// JavaScript string).
+pub fn parse_query(query: &str) -> Result<Expr, SpotsError> {
+    parse_tokens(query).map_err(|error| match error {
+        SpotsError::QueryParseError {
+            message,
+            start,
+            end,
+        } => SpotsError::QueryParseError {
+            message,
+            start: utf16_offset(query, start),
+            end: utf16_offset(query, end),
+        },
+        error => error,
+    })
+}
+
+fn parse_tokens(query: &str) -> Result<Expr, SpotsError> {
+    let tokens = tokenize(query)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+    match parser.next() {
+        Some(token) => Err(token.span.error("Unmatched `)`")),
+        None => Ok(expr),
+    }
+}
+
+/// Converts an offset in characters into an offset in UTF-16 code units.
+fn utf16_offset(query: &str, offset: usize) -> usize {
+    query.chars().take(offset).map(char::len_utf16).sum()
+}
+
+impl Expr {
+    /// Compiles the expression into an SQL condition on the track `t`.
+    ///
+    /// The values are added to `args` and referenced as `$N` parameters (so they can be bound
+    /// in order), and `user_id` is the user whose play counts are used.
+    pub fn to_sql(&self, user_id: Uuid, args: &mut Vec<SqlArg>) -> String {
+        match self {
+            Expr::And(exprs) if exprs.is_empty() => String::from("1"),
+            Expr::And(exprs) => join_sql(exprs, " AND ", user_id, args),
+            Expr::Or(exprs) => join_sql(exprs, " OR ", user_id, args),
+            Expr::Not(expr) => format!("NOT ({})", expr.to_sql(user_id, args)),
+            Expr::Filter(filter) => filter.to_sql(user_id, args),
+        }
+    }
+}
+
+impl Filter {
+    fn to_sql(&self, user_id: Uuid, args: &mut Vec<SqlArg>) -> String {
+        match self {
+            Filter::Text(text) => {
+                let pattern = bind(args, SqlArg::Text(like_pattern(text)));
+                format!(
+                    "(t.title LIKE {pattern} ESCAPE '\\' OR {} OR {})",
+                    album_sql(&pattern),
+                    artist_sql(&pattern)
+                )
+            }
+            Filter::Title(title) => {
+                let pattern = bind(args, SqlArg::Text(like_pattern(title)));
+                format!("t.title LIKE {pattern} ESCAPE '\\'")
+            }
+            Filter::Album(album) => album_sql(&bind(args, SqlArg::Text(like_pattern(album)))),
+            Filter::Artist(artist) => artist_sql(&bind(args, SqlArg::Text(like_pattern(artist)))),
+            Filter::Genre(genre) => {
+                let pattern = bind(args, SqlArg::Text(like_pattern(genre)));
+                format!(
+                    "EXISTS (
+                        SELECT 1
+                        FROM track_genres tg
+                        WHERE tg.track_id = t.id AND tg.genre LIKE {pattern} ESCAPE '\\'
+                    )"
+                )
+            }
+            Filter::Year(filter) => filter.to_sql("t.release_year", args),
+            Filter::Duration(filter) => filter.to_sql("t.duration_secs", args),
+            Filter::Played(filter) => {
+                let play_count = play_count_sql(user_id, args);
+                filter.to_sql(&play_count, args)
+            }
+        }
+    }
+}
+
+impl NumberFilter {
+    fn to_sql(self, column: &str, args: &mut Vec<SqlArg>) -> String {
+        match self {
+            NumberFilter::Compare(comparison, value) => {
+                let value = bind(args, SqlArg::Int(value));
+                format!("{column} {} {value}", comparison.as_sql())
+            }
+            NumberFilter::Range(start, end) => {
+                let start = bind(args, SqlArg::Int(start));
+                let end = bind(args, SqlArg::Int(end));
+                format!("{column} BETWEEN {start} AND {end}")
+            }
+        }
+    }
+}
+
+/// The SQL for how many times the user played the track `t`.
+pub fn play_count_sql(user_id: Uuid, args: &mut Vec<SqlArg>) -> String {
+    let user_id = bind(args, SqlArg::Text(user_id.to_string()));
+    format!(
+        "COALESCE((
+            SELECT pc.play_count
+            FROM track_play_counts pc
+            WHERE pc.track_id = t.id AND pc.user_id = {user_id}
+        ), 0)"
+    )
+}
+
+/// Adds the value to the `args`, returning its parameter (e.g. `$3`).
+pub fn bind(args: &mut Vec<SqlArg>, arg: SqlArg) -> String {
+    args.push(arg);
+    format!("${}", args.len())
+}
+
+fn join_sql(exprs: &[Expr], separator: &str, user_id: Uuid, args: &mut Vec<SqlArg>) -> String {
+    let conditions: Vec<String> = exprs
+        .iter()
+        .map(|expr| format!("({})", expr.to_sql(user_id, args)))
+        .collect();
+    conditions.join(separator)
+}
+
+fn album_sql(pattern: &str) -> String {
+    format!(
+        "EXISTS (
+            SELECT 1
+            FROM albums al
+            WHERE al.id = t.album_id AND al.title LIKE {pattern} ESCAPE '\\'
+        )"
+    )
+}
+
+fn artist_sql(pattern: &str) -> String {
+    format!(
+        "EXISTS (
+            SELECT 1
+            FROM track_artists ta
+            JOIN artists ar ON ar.id = ta.artist_id
+            WHERE ta.track_id = t.id AND ar.name LIKE {pattern} ESCAPE '\\'
+        )"
+    )
+}
+
+/// Makes a `LIKE` pattern that matches any text containing `text`.
+fn like_pattern(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Not,
+    Or,
+    Term {
+        field: Option<(String, Span)>,
+        value: String,
+        value_span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+/// Splits the query into tokens.
+fn tokenize(query: &str) -> Result<Vec<Token>, SpotsError> {
+    let chars: Vec<char> = query.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let kind = match chars[pos] {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            '-' => TokenKind::Not,
+            _ => {
+                let token = read_term(&chars, pos)?;
+                pos = token.span.end;
+                tokens.push(token);
+                continue;
+            }
+        };
+        tokens.push(Token {
+            kind,
+            span: Span::new(pos, pos + 1),
+        });
+        pos += 1;
+    }
+    Ok(tokens)
+}
+
+/// Reads a word, a quoted string or a `field:value` filter starting at `start`.
+fn read_term(chars: &[char], start: usize) -> Result<Token, SpotsError> {
+    if chars[start] == '"' {
+        let (value, end) = read_quoted(chars, start)?;
+        return Ok(Token {
+            kind: TokenKind::Term {
+                field: None,
+                value,
+                value_span: Span::new(start, end),
+            },
+            span: Span::new(start, end),
+        });
+    }
+
+    let is_end = |c: char| c.is_whitespace() || c == '(' || c == ')';
+    let mut end = start;
+    while end < chars.len() && !is_end(chars[end]) && chars[end] != ':' {
+        end += 1;
+    }
+    let word: String = chars[start..end].iter().collect();
+
+    // `field:value`
+    let is_field = !word.is_empty() && word.chars().all(char::is_alphabetic);
+    if chars.get(end) == Some(&':') && is_field {
+        let field_span = Span::new(start, end);
+        let value_start = end + 1;
+        let (value, value_end) = match chars.get(value_start) {
+            Some('"') => read_quoted(chars, value_start)?,
+            _ => {
+                let mut value_end = value_start;
+                while value_end < chars.len() && !is_end(chars[value_end]) {
+                    value_end += 1;
+                }
+                let value = chars[value_start..value_end].iter().collect();
+                (value, value_end)
+            }
+        };
+        if value.is_empty() {
+            return Err(
+                Span::new(start, value_start).error(format!("Expected a value after `{}:`", word))
+            );
+        }
+        return Ok(Token {
+            kind: TokenKind::Term {
+                field: Some((word, field_span)),
+                value,
+                value_span: Span::new(value_start, value_end),
+            },
+            span: Span::new(start, value_end),
+        });
+    }
+
+    // Words that just contain a colon (e.g. `12:30`)
+    while end < chars.len() && !is_end(chars[end]) {
+        end += 1;
+    }
+    let word: String = chars[start..end].iter().collect();
+    let kind = if word == "OR" {
+        TokenKind::Or
+    } else {
+        TokenKind::Term {
+            field: None,
+            value: word,
+            value_span: Span::new(start, end),
+        }
+    };
+    Ok(Token {
+        kind,
+        span: Span::new(start, end),
+    })
+}
+
+/// Reads the string between the quote at `start` and the closing quote.
+///
+/// Returns the string and the position after the closing quote.
+fn read_quoted(chars: &[char], start: usize) -> Result<(String, usize), SpotsError> {
+    let Some(len) = chars[start + 1..].iter().position(|c| *c == '"') else {
+        return Err(Span::new(start, chars.len()).error("Missing a closing `\"`"));
+    };
+    let value = chars[start + 1..start + 1 + len].iter().collect();
+    Ok((value, start + len + 2))
+}
+
+/// A recursive descent parser for the tokens of a query.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// `and ("OR" and)*`
+    fn parse_or(&mut self) -> Result<Expr, SpotsError> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.peek() == Some(&TokenKind::Or) {
+            let or = self.next().map(|token| token.span);
+            let expr = self.parse_and()?;
+            if expr == Expr::And(Vec::new()) || exprs[0] == Expr::And(Vec::new()) {
+                let span = or.unwrap_or(Span::new(0, 0));
+                return Err(span.error("Expected a filter on both sides of `OR`"));
+            }
+            exprs.push(expr);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    /// `unary*`
+    fn parse_and(&mut self) -> Result<Expr, SpotsError> {
+        let mut exprs = Vec::new();
+        while let Some(kind) = self.peek() {
+            if matches!(kind, TokenKind::Or | TokenKind::RParen) {
+                break;
+            }
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    /// `"-" unary | "(" or ")" | term`
+    fn parse_unary(&mut self) -> Result<Expr, SpotsError> {
+        let Some(token) = self.next() else {
+            return Err(Span::new(0, 0).error("Expected a filter"));
+        };
+        match token.kind {
+            TokenKind::Not => match self.peek() {
+                Some(TokenKind::Or | TokenKind::RParen) | None => {
+                    Err(token.span.error("Expected a filter after `-`"))
+                }
+                Some(_) => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            },
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        span,
+                    }) => {
+                        if expr == Expr::And(Vec::new()) {
+                            return Err(Span::new(token.span.start, span.end)
+                                .error("Expected a filter inside the parentheses"));
+                        }
+                        Ok(expr)
+                    }
+                    _ => Err(token.span.error("Unclosed `(`")),
+                }
+            }
+            TokenKind::Term {
+                field,
+                value,
+                value_span,
+            } => parse_filter(field, value, value_span).map(Expr::Filter),
+            TokenKind::RParen | TokenKind::Or => Err(token.span.error("Expected a filter")),
+        }
+    }
+}
+
+/// Parses the value of the `field` into a filter.
+fn parse_filter(
+    field: Option<(String, Span)>,
+    value: String,
+    value_span: Span,
+) -> Result<Filter, SpotsError> {
+    let Some((field, field_span)) = field else {
+        return Ok(Filter::Text(value));
+    };
+    let filter = match field.to_lowercase().as_str() {
+        "title" => Filter::Title(value),
+        "album" => Filter::Album(value),
+        "artist" => Filter::Artist(value),
+        "genre" => Filter::Genre(value),
+        "year" => Filter::Year(parse_number_filter(&value, value_span)?),
+        "duration" => Filter::Duration(parse_number_filter(&value, value_span)?),
+        "played" if value.eq_ignore_ascii_case("never") => {
+            Filter::Played(NumberFilter::Compare(Comparison::Eq, 0))
+        }
+        "played" => Filter::Played(parse_number_filter(&value, value_span)?),
+        _ => return Err(field_span.error(format!("Unknown filter `{}`", field))),
+    };
+    Ok(filter)
+}
+
+/// Parses a number (`2000`), comparison (`>2000`) or range (`1990..1999`).
+fn parse_number_filter(value: &str, span: Span) -> Result<NumberFilter, SpotsError> {
+    let number = |text: &str| {
+        text.trim()
+            .parse::<i64>()
+            .map_err(|_| span.error(format!("Expected a number, found `{}`", text)))
+    };
+
+    if let Some((start, end)) = value.split_once("..") {
+        return Ok(NumberFilter::Range(number(start)?, number(end)?));
+    }
+    let (comparison, number_text) = [
+        (">=", Comparison::Ge),
+        ("<=", Comparison::Le),
+        (">", Comparison::Gt),
+        ("<", Comparison::Lt),
+        ("=", Comparison::Eq),
+    ]
+    .into_iter()
+    .find_map(|(prefix, comparison)| value.strip_prefix(prefix).map(|rest| (comparison, rest)))
+    .unwrap_or((Comparison::Eq, value));
+    Ok(NumberFilter::Compare(comparison, number(number_text)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span_of(error: SpotsError) -> (usize, usize) {
+        match error {
+            SpotsError::QueryParseError { start, end, .. } => (start, end),
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_query() -> Result<(), SpotsError> {
+        let expr =
+            parse_query(r#"artist:"Daft Punk" year:>2000 genre:house duration:<300 played:never"#)?;
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Filter(Filter::Artist(String::from("Daft Punk"))),
+                Expr::Filter(Filter::Year(NumberFilter::Compare(Comparison::Gt, 2000))),
+                Expr::Filter(Filter::Genre(String::from("house"))),
+                Expr::Filter(Filter::Duration(NumberFilter::Compare(Comparison::Lt, 300))),
+                Expr::Filter(Filter::Played(NumberFilter::Compare(Comparison::Eq, 0))),
+            ])
+        );
+
+        let expr = parse_query("-(jazz OR year:1990..1999) 12:30")?;
+        assert_eq!(
+            expr,
+            Expr::And(vec![
+                Expr::Not(Box::new(Expr::Or(vec![
+                    Expr::Filter(Filter::Text(String::from("jazz"))),
+                    Expr::Filter(Filter::Year(NumberFilter::Range(1990, 1999))),
+                ]))),
+                Expr::Filter(Filter::Text(String::from("12:30"))),
+            ])
+        );
+        assert_eq!(parse_query("  ")?, Expr::And(Vec::new()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(span_of(parse_query("year:>abc").unwrap_err()), (5, 9));
+        assert_eq!(span_of(parse_query("mood:happy").unwrap_err()), (0, 4));
+        assert_eq!(
+            span_of(parse_query("é artist:\"Daft").unwrap_err()),
+            (9, 14)
+        );
+        assert_eq!(span_of(parse_query("(jazz").unwrap_err()), (0, 1));
+        assert_eq!(span_of(parse_query("jazz)").unwrap_err()), (4, 5));
+        assert_eq!(span_of(parse_query("jazz OR").unwrap_err()), (5, 7));
+        assert_eq!(span_of(parse_query("genre:").unwrap_err()), (0, 6));
+
+        // Characters outside of the BMP take two UTF-16 code units
+        assert_eq!(span_of(parse_query("🎵 year:x").unwrap_err()), (8, 9));
+    }
+
+    #[test]
+    fn test_to_sql() -> Result<(), SpotsError> {
+        let user_id = Uuid::new_v4();
+        let mut args = Vec::new();
+        let sql = parse_query("year:1990..1999 OR -played:>=5")?.to_sql(user_id, &mut args);
+        assert!(sql.starts_with("(t.release_year BETWEEN $1 AND $2) OR (NOT ("));
+        assert!(sql.contains("pc.user_id = $3"));
+        assert!(sql.ends_with(">= $4))"));
+        assert_eq!(
+            args,
+            vec![
+                SqlArg::Int(1990),
+                SqlArg::Int(1999),
+                SqlArg::Text(user_id.to_string()),
+                SqlArg::Int(5),
+            ]
+        );
+
+        let mut args = Vec::new();
+        parse_query("title:100%")?.to_sql(user_id, &mut args);
+        assert_eq!(args, vec![SqlArg::Text(String::from("%100\\%%"))]);
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use sqlx::{Sqlite, SqliteConnection};
+use uuid::Uuid;
+
+use crate::{
+    database::{client::DatabaseClient, models::music_library::QueueEntry, DBResult},
+    playback::queue::{PlayQueue, RepeatMode},
+};
+
+/// Database operations for the [PlayQueue].
+pub trait QueueExt {
+    /// Gets the user's play queue (an empty queue if the user hasn't queued anything yet).
+    async fn get_play_queue(&self, user_id: Uuid) -> DBResult<PlayQueue>;
+
+    /// Saves the user's play queue, replacing the previous one.
+    async fn save_play_queue(&self, user_id: Uuid, queue: &PlayQueue) -> DBResult<()>;
+
+    /// Applies the `update` to the user's play queue and saves it.
+    ///
+    /// Concurrent updates of the same queue happen one at a time, so none of them are lost.
+    /// The queue is left as is if the `update` fails. Returns the updated queue.
+    async fn update_play_queue(
+        &self,
+        user_id: Uuid,
+        update: impl FnOnce(&mut PlayQueue) -> DBResult<()> + Send,
+    ) -> DBResult<PlayQueue>;
+
+    /// Saves how far into the current track of the user's queue playback got.
+    async fn save_queue_position(&self, user_id: Uuid, position_ms: u64) -> DBResult<()>;
+}
+
+impl QueueExt for DatabaseClient {
+    async fn get_play_queue(&self, user_id: Uuid) -> DBResult<PlayQueue> {
+        let mut conn = self.pool.acquire().await?;
+        read_play_queue(&mut conn, user_id).await
+    }
+
+    async fn save_play_queue(&self, user_id: Uuid, queue: &PlayQueue) -> DBResult<()> {
+        let mut tx = self.begin_write().await?;
+        write_play_queue(&mut tx, user_id, queue).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn update_play_queue(
+        &self,
+        user_id: Uuid,
+        update: impl FnOnce(&mut PlayQueue) -> DBResult<()> + Send,
+    ) -> DBResult<PlayQueue> {
+        let mut tx = self.begin_write().await?;
+        let mut queue = read_play_queue(&mut tx, user_id).await?;
+        update(&mut queue)?;
+        write_play_queue(&mut tx, user_id, &queue).await?;
+        tx.commit().await?;
+        Ok(queue)
+    }
+
+    async fn save_queue_position(&self, user_id: Uuid, position_ms: u64) -> DBResult<()> {
+        sqlx::query("UPDATE play_queue_state SET position_ms = $1 WHERE user_id = $2")
+            .bind(position_ms as i64)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Reads the user's play queue.
+async fn read_play_queue(conn: &mut SqliteConnection, user_id: Uuid) -> DBResult<PlayQueue> {
+    let entries = sqlx::query_as::<Sqlite, QueueEntry>(
+        "
+        SELECT *
+        FROM play_queue
+        WHERE user_id = $1
+        ORDER BY position
+        ",
+    )
+    .bind(user_id.to_string())
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let state: Option<(Option<String>, i64, String, bool)> = sqlx::query_as(
+        "
+        SELECT current_entry_id, position_ms, repeat_mode, is_shuffled
+        FROM play_queue_state
+        WHERE user_id = $1
+        ",
+    )
+    .bind(user_id.to_string())
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((current_entry_id, position_ms, repeat_mode, shuffled)) = state else {
+        return Ok(PlayQueue {
+            entries,
+            ..Default::default()
+        });
+    };
+
+    let current =
+        current_entry_id.and_then(|id| entries.iter().position(|entry| entry.id.to_string() == id));
+    Ok(PlayQueue {
+        entries,
+        current,
+        repeat: RepeatMode::from_name(&repeat_mode),
+        shuffled,
+        position_ms: position_ms.max(0) as u64,
+    })
+}
+
+/// Replaces the user's play queue.
+async fn write_play_queue(
+    conn: &mut SqliteConnection,
+    user_id: Uuid,
+    queue: &PlayQueue,
+) -> DBResult<()> {
+    sqlx::query("DELETE FROM play_queue WHERE user_id = $1")
+        .bind(user_id.to_string())
+        .execute(&mut *conn)
+        .await?;
+    for (position, entry) in queue.entries.iter().enumerate() {
+        sqlx::query(
+            "
+            INSERT INTO play_queue (id, user_id, track_id, position, original_position)
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(entry.id.to_string())
+        .bind(user_id.to_string())
+        .bind(entry.track_id.to_string())
+        .bind(position as i64)
+        .bind(entry.original_position as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    let current_entry_id = queue
+        .current
+        .and_then(|idx| queue.entries.get(idx))
+        .map(|entry| entry.id.to_string());
+    sqlx::query(
+        "
+        INSERT INTO play_queue_state (
+            user_id,
+            current_entry_id,
+            position_ms,
+            repeat_mode,
+            is_shuffled
+        )
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT(user_id) DO UPDATE SET
+            current_entry_id = excluded.current_entry_id,
+            position_ms = excluded.position_ms,
+            repeat_mode = excluded.repeat_mode,
+            is_shuffled = excluded.is_shuffled
+        ",
+    )
+    .bind(user_id.to_string())
+    .bind(current_entry_id)
+    .bind(queue.position_ms as i64)
+    .bind(queue.repeat.as_str())
+    .bind(queue.shuffled)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        database::{
+            test_utils::{insert_track, TempDb},
+            users::UserExt,
+        },
+        errors::SpotsError,
+    };
+
+    #[test]
+    fn test_update_play_queue() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let tracks = [insert_track(db, "A").await, insert_track(db, "B").await];
+
+            let queue = db
+                .update_play_queue(user_id, |queue| {
+                    queue.enqueue(&tracks);
+                    queue.advance();
+                    Ok(())
+                })
+                .await
+                .unwrap();
+            assert_eq!(queue.current_track(), Some(tracks[0]));
+            assert_eq!(db.get_play_queue(user_id).await.unwrap(), queue);
+
+            // A failed update isn't saved
+            let missing = Uuid::new_v4();
+            let result = db
+                .update_play_queue(user_id, |queue| {
+                    queue.clear();
+                    queue
+                        .remove(missing)
+                        .then_some(())
+                        .ok_or(SpotsError::QueueEntryNotFound(missing))
+                })
+                .await;
+            assert!(matches!(result, Err(SpotsError::QueueEntryNotFound(_))));
+            assert_eq!(db.get_play_queue(user_id).await.unwrap(), queue);
+
+            db.save_queue_position(user_id, 1_500).await.unwrap();
+            assert_eq!(db.get_play_queue(user_id).await.unwrap().position_ms, 1_500);
+        });
+    }
+}
@@ -0,0 +1,327 @@
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use sqlx::Sqlite;
+use uuid::Uuid;
+
+use crate::{
+    api::utils::ResponseChannel,
+    database::{
+        client::DatabaseClient,
+        models::music_library::{Album, Artist, Genre, Playlist, Track},
+        stream_rows, DBResult,
+    },
+};
+
+/// A search result, tagged with the kind of item that matched.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "item", rename_all = "camelCase")]
+pub enum SearchResult {
+    Track(Track),
+    Album(Album),
+    Artist(Artist),
+    Playlist(Playlist),
+    Genre(Genre),
+}
+
+/// Database operations for searching the music library.
+pub trait SearchExt {
+    /// Searches the names of tracks, albums, artists, playlists and genres.
+    ///
+    /// Each word of the `query` matches as a prefix (so "beyo" finds "Beyoncé"), ignoring case
+    /// and diacritics. Only the user's own (or followed) playlists are searched.
+    ///
+    /// # Note
+    /// The results are streamed to the `channel`, grouped by kind (tracks, albums, artists,
+    /// playlists, then genres) and ranked by relevance within each group. At most `limit`
+    /// results are sent per group.
+    async fn search(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        limit: u32,
+        channel: ResponseChannel<SearchResult>,
+    ) -> DBResult<()>;
+}
+
+impl SearchExt for DatabaseClient {
+    async fn search(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        limit: u32,
+        channel: ResponseChannel<SearchResult>,
+    ) -> DBResult<()> {
+        let Some(query) = fts_query(query) else {
+            // Nothing to search for
+            return stream_rows(stream::empty(), channel).await;
+        };
+
+        let tracks = sqlx::query_as::<Sqlite, Track>(
+            "
+            SELECT t.*
+            FROM search_index s
+            JOIN tracks t ON t.id = s.item_id
+            WHERE s.name MATCH $1 AND s.kind = 'track'
+            ORDER BY s.rank
+            LIMIT $2
+            ",
+        )
+        .bind(query.clone())
+        .bind(limit)
+        .fetch(&self.pool)
+        .map(|row| row.map(SearchResult::Track));
+
+        let albums = sqlx::query_as::<Sqlite, Album>(
+            "
+            SELECT a.*
+            FROM search_index s
+            JOIN albums a ON a.id = s.item_id
+            WHERE s.name MATCH $1 AND s.kind = 'album'
+            ORDER BY s.rank
+            LIMIT $2
+            ",
+        )
+        .bind(query.clone())
+        .bind(limit)
+        .fetch(&self.pool)
+        .map(|row| row.map(SearchResult::Album));
+
+        let artists = sqlx::query_as::<Sqlite, Artist>(
+            "
+            SELECT a.*
+            FROM search_index s
+            JOIN artists a ON a.id = s.item_id
+            WHERE s.name MATCH $1 AND s.kind = 'artist'
+            ORDER BY s.rank
+            LIMIT $2
+            ",
+        )
+        .bind(query.clone())
+        .bind(limit)
+        .fetch(&self.pool)
+        .map(|row| row.map(SearchResult::Artist));
+
+        let playlists = sqlx::query_as::<Sqlite, Playlist>(
+            "
+            SELECT p.*
+            FROM search_index s
+            JOIN playlists p ON p.id = s.item_id
+            WHERE s.name MATCH $1 AND s.kind = 'playlist' AND (
+                p.user_id = $3 OR EXISTS (
+                    SELECT 1
+                    FROM followed_playlists fp
+                    WHERE fp.playlist_id = p.id AND fp.user_id = $3
+                )
+            )
+            ORDER BY s.rank
+            LIMIT $2
+            ",
+        )
+        .bind(query.clone())
+        .bind(limit)
+        .bind(user_id.to_string())
+        .fetch(&self.pool)
+        .map(|row| row.map(SearchResult::Playlist));
+
+        let genres = sqlx::query_as::<Sqlite, Genre>(
+            "
+            SELECT g.*
+            FROM search_index s
+            JOIN genres g ON g.name = s.item_id
+            WHERE s.name MATCH $1 AND s.kind = 'genre'
+            ORDER BY s.rank
+            LIMIT $2
+            ",
+        )
+        .bind(query)
+        .bind(limit)
+        .fetch(&self.pool)
+        .map(|row| row.map(SearchResult::Genre));
+
+        // Stream the results to the channel
+        let results = tracks
+            .chain(albums)
+            .chain(artists)
+            .chain(playlists)
+            .chain(genres);
+        stream_rows(results, channel).await
+    }
+}
+
+/// Turns the user's search query into an FTS5 query that matches every word as a prefix.
+///
+/// The words are quoted, so characters with a special meaning in FTS5 queries (e.g. `"`, `*`
+/// or `-`) can't cause syntax errors. Returns `None` if there are no words to search for.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::database::{
+        library::{LibraryExt, NewTrack},
+        playlists::PlaylistExt,
+        test_utils::{response_channel, streamed_values, TempDb},
+        users::UserExt,
+    };
+
+    /// Searches the library, returning the `(kind, name)` of each result.
+    async fn search_names(
+        db: &DatabaseClient,
+        user_id: Uuid,
+        query: &str,
+    ) -> Vec<(String, String)> {
+        let (channel, responses) = response_channel();
+        db.search(user_id, query, 10, channel).await.unwrap();
+        streamed_values(&responses)
+            .iter()
+            .map(|result| {
+                let item = &result["item"];
+                let name = match item {
+                    Value::String(_) => item,
+                    _ => item.get("title").or(item.get("name")).unwrap(),
+                };
+                (
+                    result["kind"].as_str().unwrap().to_owned(),
+                    name.as_str().unwrap().to_owned(),
+                )
+            })
+            .collect()
+    }
+
+    /// Gets the names in the search index of the kind of item.
+    async fn indexed_names(db: &DatabaseClient, kind: &str) -> Vec<String> {
+        sqlx::query_scalar("SELECT name FROM search_index WHERE kind = $1 ORDER BY name")
+            .bind(kind)
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    /// Runs a statement that changes the library.
+    async fn execute(db: &DatabaseClient, sql: &str) {
+        sqlx::query(sql).execute(&db.pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("beyo"), Some(String::from("\"beyo\"*")));
+        assert_eq!(
+            fts_query("  AC/DC \"back*"),
+            Some(String::from("\"AC\"* \"DC\"* \"back\"*"))
+        );
+        assert_eq!(fts_query("Beyoncé"), Some(String::from("\"Beyoncé\"*")));
+        assert_eq!(fts_query(" -*\" "), None);
+    }
+
+    #[test]
+    fn test_search() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+            let track = NewTrack {
+                file_path: String::from("/music/halo.mp3"),
+                title: String::from("Halo"),
+                album: Some(String::from("I Am... Sasha Fierce")),
+                artists: vec![String::from("Beyoncé")],
+                genres: vec![String::from("Pop")],
+                ..Default::default()
+            };
+            db.upsert_track(user_id, track).await.unwrap();
+            db.create_playlist(user_id, "Beyond").await.unwrap();
+            db.create_playlist(other_id, "Beyoncé's").await.unwrap();
+
+            // Prefixes match, ignoring case and diacritics
+            let result = |kind: &str, name: &str| (String::from(kind), String::from(name));
+            assert_eq!(
+                search_names(db, user_id, "beyo").await,
+                vec![result("artist", "Beyoncé"), result("playlist", "Beyond")]
+            );
+            assert_eq!(
+                search_names(db, user_id, "BEYONCE").await,
+                vec![result("artist", "Beyoncé")]
+            );
+            assert_eq!(
+                search_names(db, user_id, "sasha fi").await,
+                vec![result("album", "I Am... Sasha Fierce")]
+            );
+            assert_eq!(
+                search_names(db, user_id, "ha").await,
+                vec![result("track", "Halo")]
+            );
+            assert_eq!(
+                search_names(db, user_id, "pop").await,
+                vec![result("genre", "Pop")]
+            );
+            assert!(search_names(db, user_id, " * ").await.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_search_index_triggers() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let track = NewTrack {
+                file_path: String::from("/music/track.mp3"),
+                title: String::from("Track"),
+                album: Some(String::from("Album")),
+                artists: vec![String::from("Artist")],
+                ..Default::default()
+            };
+            db.upsert_track(user_id, track).await.unwrap();
+            let playlist_id = db.create_playlist(user_id, "Playlist").await.unwrap().id;
+
+            // Inserts
+            for (kind, name) in [
+                ("track", "Track"),
+                ("album", "Album"),
+                ("artist", "Artist"),
+                ("playlist", "Playlist"),
+            ] {
+                assert_eq!(indexed_names(db, kind).await, vec![name]);
+            }
+
+            // Updates
+            execute(db, "UPDATE tracks SET title = 'New Track'").await;
+            execute(db, "UPDATE albums SET title = 'New Album'").await;
+            execute(db, "UPDATE artists SET name = 'New Artist'").await;
+            db.rename_playlist(playlist_id, "New Playlist")
+                .await
+                .unwrap();
+            for (kind, name) in [
+                ("track", "New Track"),
+                ("album", "New Album"),
+                ("artist", "New Artist"),
+                ("playlist", "New Playlist"),
+            ] {
+                assert_eq!(indexed_names(db, kind).await, vec![name]);
+            }
+            assert_eq!(
+                search_names(db, user_id, "new").await.len(),
+                4,
+                "the renamed items should be found by their new names"
+            );
+
+            // Deletes
+            execute(db, "DELETE FROM tracks").await;
+            execute(db, "DELETE FROM albums").await;
+            execute(db, "DELETE FROM artists").await;
+            db.delete_playlist(playlist_id).await.unwrap();
+            for kind in ["track", "album", "artist", "playlist"] {
+                assert!(indexed_names(db, kind).await.is_empty());
+            }
+        });
+    }
+}
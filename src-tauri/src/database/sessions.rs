@@ -0,0 +1,133 @@
+use chrono::{NaiveDateTime, Utc};
+use uuid::Uuid;
+
+use crate::database::{client::DatabaseClient, DBResult};
+
+/// Database operations for the sessions that auth tokens belong to.
+///
+/// Each auth token carries the ID of its session, and is only valid while the session exists,
+/// so deleting a session revokes its token.
+pub trait SessionExt {
+    /// Starts a session for the user that lasts until `expires_at`, returning its ID.
+    ///
+    /// The user's expired sessions are cleaned up along the way.
+    async fn create_session(&self, user_id: Uuid, expires_at: NaiveDateTime) -> DBResult<Uuid>;
+
+    /// Checks if the user's session is active (it hasn't been ended and hasn't expired).
+    async fn is_session_active(&self, session_id: Uuid, user_id: Uuid) -> DBResult<bool>;
+
+    /// Ends the session, revoking its token.
+    async fn delete_session(&self, session_id: Uuid) -> DBResult<()>;
+
+    /// Ends all of the user's sessions, revoking their tokens on every device.
+    ///
+    /// Returns the number of sessions that were ended.
+    async fn delete_user_sessions(&self, user_id: Uuid) -> DBResult<u64>;
+}
+
+impl SessionExt for DatabaseClient {
+    async fn create_session(&self, user_id: Uuid, expires_at: NaiveDateTime) -> DBResult<Uuid> {
+        let now = Utc::now().naive_local().to_string();
+        let session_id = Uuid::new_v4();
+        let mut tx = self.begin_write().await?;
+        sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND expires_at <= $2")
+            .bind(user_id.to_string())
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "
+            INSERT INTO sessions (id, user_id, created_at, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ",
+        )
+        .bind(session_id.to_string())
+        .bind(user_id.to_string())
+        .bind(&now)
+        .bind(expires_at.to_string())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(session_id)
+    }
+
+    async fn is_session_active(&self, session_id: Uuid, user_id: Uuid) -> DBResult<bool> {
+        let is_active: bool = sqlx::query_scalar(
+            "
+            SELECT EXISTS (
+                SELECT 1
+                FROM sessions
+                WHERE id = $1 AND user_id = $2 AND expires_at > $3
+            )
+            ",
+        )
+        .bind(session_id.to_string())
+        .bind(user_id.to_string())
+        .bind(Utc::now().naive_local().to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(is_active)
+    }
+
+    async fn delete_session(&self, session_id: Uuid) -> DBResult<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(session_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, user_id: Uuid) -> DBResult<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::database::{
+        test_utils::{count, TempDb},
+        users::UserExt,
+    };
+
+    #[test]
+    fn test_sessions() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+            let expires_at = Utc::now().naive_local() + Duration::minutes(60);
+
+            let laptop = db.create_session(user_id, expires_at).await.unwrap();
+            let phone = db.create_session(user_id, expires_at).await.unwrap();
+            let other = db.create_session(other_id, expires_at).await.unwrap();
+            assert!(db.is_session_active(laptop, user_id).await.unwrap());
+            assert!(!db.is_session_active(laptop, other_id).await.unwrap());
+
+            // Logging out
+            db.delete_session(laptop).await.unwrap();
+            assert!(!db.is_session_active(laptop, user_id).await.unwrap());
+            assert!(db.is_session_active(phone, user_id).await.unwrap());
+
+            // Logging out on all devices
+            db.create_session(user_id, expires_at).await.unwrap();
+            assert_eq!(db.delete_user_sessions(user_id).await.unwrap(), 2);
+            assert!(!db.is_session_active(phone, user_id).await.unwrap());
+            assert!(db.is_session_active(other, other_id).await.unwrap());
+
+            // Expired sessions aren't active, and are cleaned up by the next login
+            let expired = Utc::now().naive_local() - Duration::minutes(1);
+            let stale = db.create_session(user_id, expired).await.unwrap();
+            assert!(!db.is_session_active(stale, user_id).await.unwrap());
+            db.create_session(user_id, expires_at).await.unwrap();
+            assert_eq!(count(db, "sessions").await, 2);
+        });
+    }
+}
@@ -0,0 +1,202 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::query::{
+    bind, play_count_sql, Comparison, Expr, Filter, NumberFilter, SqlArg,
+};
+
+/// The rules of a smart playlist, whose tracks are computed from the library instead of being
+/// added by hand.
+///
+/// The rules are stored as JSON, e.g. "genre is jazz AND added in the last 30 days, limit 50,
+/// sort by play count":
+///
+/// ```json
+/// {
+///   "matchAny": false,
+///   "rules": [
+///     { "field": "genre", "value": "jazz" },
+///     { "field": "added", "days": 30 }
+///   ],
+///   "limit": 50,
+///   "sort": { "by": "playCount", "descending": true }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartRules {
+    /// Whether a track has to match any of the rules (instead of all of them).
+    #[serde(default)]
+    pub match_any: bool,
+
+    /// The rules the tracks have to match (every track matches if there are none).
+    pub rules: Vec<SmartRule>,
+
+    /// The max number of tracks in the playlist.
+    pub limit: Option<u32>,
+
+    /// How the tracks are sorted (by title if not set).
+    pub sort: Option<SmartSort>,
+}
+
+/// A single rule of a smart playlist.
+///
+/// Text rules match the tracks whose field contains the value (ignoring case).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "field", rename_all = "camelCase")]
+pub enum SmartRule {
+    Title {
+        value: String,
+    },
+    Album {
+        value: String,
+    },
+    Artist {
+        value: String,
+    },
+    Genre {
+        value: String,
+    },
+    Year {
+        op: Comparison,
+        value: i64,
+    },
+
+    /// The track's duration in seconds.
+    Duration {
+        op: Comparison,
+        value: i64,
+    },
+
+    /// How many times the playlist's owner played the track.
+    PlayCount {
+        op: Comparison,
+        value: i64,
+    },
+
+    /// Added to the library in the last number of days.
+    Added {
+        days: i64,
+    },
+}
+
+/// How the tracks of a smart playlist are sorted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmartSort {
+    pub by: SmartSortField,
+
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// The field the tracks of a smart playlist are sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SmartSortField {
+    Title,
+    Year,
+    Duration,
+    Added,
+    LastPlayed,
+    PlayCount,
+    Random,
+}
+
+impl SmartRules {
+    /// Compiles the rules into an SQL condition on the track `t` and the expression the tracks
+    /// are ordered by.
+    ///
+    /// The values are added to `args` (see [Expr::to_sql]), and `user_id` is the user whose
+    /// play counts are used.
+    pub fn to_sql(&self, user_id: Uuid, args: &mut Vec<SqlArg>) -> (String, String) {
+        let conditions: Vec<String> = self
+            .rules
+            .iter()
+            .map(|rule| format!("({})", rule.to_sql(user_id, args)))
+            .collect();
+        let condition = match (conditions.is_empty(), self.match_any) {
+            (true, _) => String::from("1"),
+            (false, true) => conditions.join(" OR "),
+            (false, false) => conditions.join(" AND "),
+        };
+
+        let sort = self.sort.unwrap_or(SmartSort {
+            by: SmartSortField::Title,
+            descending: false,
+        });
+        let column = match sort.by {
+            SmartSortField::Title => String::from("t.title"),
+            SmartSortField::Year => String::from("t.release_year"),
+            SmartSortField::Duration => String::from("t.duration_secs"),
+            SmartSortField::Added => String::from("t.created_at"),
+            SmartSortField::LastPlayed => String::from("t.last_played_at"),
+            SmartSortField::PlayCount => play_count_sql(user_id, args),
+            SmartSortField::Random => String::from("RANDOM()"),
+        };
+        let direction = if sort.descending { "DESC" } else { "ASC" };
+
+        // Ties are broken by title, so the order is stable
+        (condition, format!("{column} {direction}, t.title ASC"))
+    }
+}
+
+impl SmartRule {
+    /// Compiles the rule into an SQL condition on the track `t`.
+    ///
+    /// Most rules are the same as a filter of the query language (see [Expr]).
+    fn to_sql(&self, user_id: Uuid, args: &mut Vec<SqlArg>) -> String {
+        let number = |op: &Comparison, value: &i64| NumberFilter::Compare(*op, *value);
+        let filter = match self {
+            SmartRule::Title { value } => Filter::Title(value.clone()),
+            SmartRule::Album { value } => Filter::Album(value.clone()),
+            SmartRule::Artist { value } => Filter::Artist(value.clone()),
+            SmartRule::Genre { value } => Filter::Genre(value.clone()),
+            SmartRule::Year { op, value } => Filter::Year(number(op, value)),
+            SmartRule::Duration { op, value } => Filter::Duration(number(op, value)),
+            SmartRule::PlayCount { op, value } => Filter::Played(number(op, value)),
+            SmartRule::Added { days } => {
+                let since = Utc::now().naive_local() - Duration::days(*days);
+                let since = bind(args, SqlArg::Text(since.to_string()));
+                return format!("t.created_at >= {since}");
+            }
+        };
+        Expr::Filter(filter).to_sql(user_id, args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smart_rules() {
+        let rules: SmartRules = serde_json::from_str(
+            r#"{
+                "rules": [
+                    { "field": "genre", "value": "jazz" },
+                    { "field": "added", "days": 30 }
+                ],
+                "limit": 50,
+                "sort": { "by": "playCount", "descending": true }
+            }"#,
+        )
+        .unwrap();
+        assert!(!rules.match_any);
+        assert_eq!(
+            rules.rules[0],
+            SmartRule::Genre {
+                value: "jazz".into()
+            }
+        );
+
+        let mut args = Vec::new();
+        let (condition, order) = rules.to_sql(Uuid::new_v4(), &mut args);
+        assert!(condition.contains("tg.genre LIKE $1"));
+        assert!(condition.ends_with("(t.created_at >= $2)"));
+        assert!(order.contains("pc.user_id = $3"));
+        assert!(order.ends_with("DESC, t.title ASC"));
+        assert_eq!(args.len(), 3);
+    }
+}
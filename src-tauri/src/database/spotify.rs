@@ -0,0 +1,798 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        bundle::{local_tracks, LocalTrack},
+        client::DatabaseClient,
+        fuzzy::{normalize, trigram_similarity, trigrams},
+        history::{import_play, HistoryExt},
+        playlists::{insert_playlist, playlist_ids_by_title, save_playlist_order},
+        DBResult,
+    },
+    errors::SpotsError,
+};
+
+/// The min similarity (from `0.0` to `1.0`) of a name in the export to a name in the library
+/// for them to match.
+///
+/// This is stricter than the search's, since a false match ends up in the user's playlists.
+const MIN_MATCH_SIMILARITY: f64 = 0.6;
+
+/// The format of the `endTime` of a stream in the streaming history (in UTC).
+const END_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The files of Spotify's "Download your data" package that can be imported.
+#[derive(Debug, Clone, Default)]
+pub struct SpotifyExport {
+    /// The playlists from the `Playlist*.json` files.
+    pub playlists: Vec<SpotifyPlaylist>,
+
+    /// The streams from the `StreamingHistory*.json` files (podcasts and videos are left out).
+    pub streaming_history: Vec<SpotifyStream>,
+
+    /// The saved tracks, albums and artists from `YourLibrary.json`.
+    pub library: SpotifyLibrary,
+}
+
+/// The contents of a `Playlist*.json` file.
+#[derive(Debug, Clone, Deserialize)]
+struct SpotifyPlaylists {
+    #[serde(default)]
+    playlists: Vec<SpotifyPlaylist>,
+}
+
+/// A playlist in the export.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyPlaylist {
+    pub name: String,
+
+    #[serde(default)]
+    pub items: Vec<SpotifyPlaylistItem>,
+}
+
+/// An item of a playlist in the export.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyPlaylistItem {
+    /// The track (`None` for podcast episodes and local files).
+    pub track: Option<SpotifyTrack>,
+}
+
+/// A track in the export.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyTrack {
+    pub track_name: String,
+
+    pub artist_name: String,
+
+    #[serde(default)]
+    pub album_name: Option<String>,
+}
+
+/// A stream in the streaming history.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyStream {
+    /// When the stream ended (in UTC, to the minute).
+    pub end_time: String,
+
+    pub artist_name: String,
+
+    pub track_name: String,
+
+    pub ms_played: u64,
+}
+
+/// The contents of `YourLibrary.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SpotifyLibrary {
+    #[serde(default)]
+    pub tracks: Vec<SpotifySavedTrack>,
+
+    #[serde(default)]
+    pub albums: Vec<SpotifyAlbum>,
+
+    #[serde(default)]
+    pub artists: Vec<SpotifyArtist>,
+}
+
+/// A saved ("liked") track in `YourLibrary.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifySavedTrack {
+    pub artist: String,
+
+    pub album: String,
+
+    pub track: String,
+}
+
+/// A saved album in `YourLibrary.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SpotifyAlbum {
+    pub artist: String,
+
+    pub album: String,
+}
+
+/// A followed artist in `YourLibrary.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpotifyArtist {
+    pub name: String,
+}
+
+/// The result of importing a Spotify export.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotifyImportReport {
+    /// The number of playlists that were created (ones with the same title as one of the user's
+    /// playlists are skipped).
+    pub playlists: usize,
+
+    /// The number of favorites that were added (ones that were already favorited are skipped).
+    pub favorites: usize,
+
+    /// The number of plays that were added to the history (ones that are already in it are
+    /// skipped).
+    pub plays: usize,
+
+    /// The tracks that aren't in the library (each one once). They are left out of the
+    /// playlists, favorites and history.
+    pub unmatched_tracks: Vec<SpotifyTrack>,
+
+    /// The saved albums that aren't in the library.
+    pub unmatched_albums: Vec<SpotifyAlbum>,
+
+    /// The followed artists that aren't in the library.
+    pub unmatched_artists: Vec<String>,
+}
+
+/// Database operations for importing a user's data from Spotify.
+pub trait SpotifyExt {
+    /// Imports the playlists, saved tracks, albums and artists, and streaming history of the
+    /// export for the user.
+    ///
+    /// The export's items are matched to the library by the similarity of their names (ignoring
+    /// case, diacritics and suffixes like "- Remastered"). Saved tracks, albums and artists
+    /// become favorites, and streams count as plays by the user's [PlayThreshold]. Playlists
+    /// with the same title as one of the user's aren't imported again.
+    ///
+    /// [PlayThreshold]: crate::database::history::PlayThreshold
+    async fn import_spotify(
+        &self,
+        user_id: Uuid,
+        export: &SpotifyExport,
+    ) -> DBResult<SpotifyImportReport>;
+}
+
+impl SpotifyExt for DatabaseClient {
+    async fn import_spotify(
+        &self,
+        user_id: Uuid,
+        export: &SpotifyExport,
+    ) -> DBResult<SpotifyImportReport> {
+        let mut matcher = Matcher::new(self).await?;
+        let mut report = SpotifyImportReport::default();
+        let mut unmatched_tracks: HashSet<SpotifyTrack> = HashSet::new();
+        let mut match_track = |matcher: &mut Matcher, track: SpotifyTrack| {
+            let track_id = matcher.match_track(&track);
+            if track_id.is_none() && unmatched_tracks.insert(track.clone()) {
+                report.unmatched_tracks.push(track);
+            }
+            track_id
+        };
+
+        // Match everything before writing, since matching is slow for a large library
+        let mut playlists = vec![];
+        for playlist in &export.playlists {
+            let mut track_ids: Vec<Uuid> = vec![];
+            for track in playlist.items.iter().filter_map(|item| item.track.clone()) {
+                if let Some(track_id) = match_track(&mut matcher, track) {
+                    if !track_ids.contains(&track_id) {
+                        track_ids.push(track_id);
+                    }
+                }
+            }
+            playlists.push((playlist.name.trim(), track_ids));
+        }
+        let saved_tracks: Vec<Uuid> = export
+            .library
+            .tracks
+            .iter()
+            .filter_map(|saved| {
+                let track = SpotifyTrack {
+                    track_name: saved.track.clone(),
+                    artist_name: saved.artist.clone(),
+                    album_name: Some(saved.album.clone()),
+                };
+                match_track(&mut matcher, track)
+            })
+            .collect();
+        let mut streams = vec![];
+        for stream in &export.streaming_history {
+            let end_time = NaiveDateTime::parse_from_str(&stream.end_time, END_TIME_FORMAT)
+                .map_err(|e| {
+                    SpotsError::InvalidSpotifyExport(format!("{}: {e}", stream.end_time))
+                })?;
+            let track = SpotifyTrack {
+                track_name: stream.track_name.clone(),
+                artist_name: stream.artist_name.clone(),
+                album_name: None,
+            };
+            if let Some(track_id) = match_track(&mut matcher, track) {
+                // Both `endTime` and the history's timestamps are in UTC
+                let started_at = end_time - Duration::milliseconds(stream.ms_played as i64);
+                streams.push((track_id, started_at, stream.ms_played));
+            }
+        }
+        let mut saved_albums = vec![];
+        for album in &export.library.albums {
+            match matcher.match_album(album) {
+                Some(album_id) => saved_albums.push(album_id),
+                None => report.unmatched_albums.push(album.clone()),
+            }
+        }
+        let mut saved_artists = vec![];
+        for artist in &export.library.artists {
+            match matcher.match_artist(&artist.name) {
+                Some(artist_id) => saved_artists.push(artist_id),
+                None => report.unmatched_artists.push(artist.name.clone()),
+            }
+        }
+
+        let threshold = self.get_play_threshold(user_id).await?;
+        let mut tx = self.begin_write().await?;
+        let now = Utc::now().naive_local().to_string();
+
+        // Playlists (ones that were already imported are skipped)
+        let existing = playlist_ids_by_title(&mut tx, user_id).await?;
+        for (title, track_ids) in playlists {
+            if existing.contains_key(title) {
+                continue;
+            }
+            let playlist = insert_playlist(&mut tx, user_id, title).await?;
+            save_playlist_order(&mut tx, playlist.id, &track_ids).await?;
+            report.playlists += 1;
+        }
+
+        // Favorites (when the tracks were saved isn't in the export)
+        for track_id in saved_tracks {
+            let result = sqlx::query(
+                "INSERT OR IGNORE INTO favorited_tracks (user_id, track_id) VALUES ($1, $2)",
+            )
+            .bind(user_id.to_string())
+            .bind(track_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+            report.favorites += result.rows_affected() as usize;
+        }
+        for (ids, table, column) in [
+            (saved_albums, "favorited_albums", "album_id"),
+            (saved_artists, "favorited_artists", "artist_id"),
+        ] {
+            for id in ids {
+                let result = sqlx::query(&format!(
+                    "
+                    INSERT OR IGNORE INTO {table} (user_id, {column}, favorited_at)
+                    VALUES ($1, $2, $3)
+                    "
+                ))
+                .bind(user_id.to_string())
+                .bind(id.to_string())
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?;
+                report.favorites += result.rows_affected() as usize;
+            }
+        }
+
+        // History
+        for (track_id, started_at, ms_played) in streams {
+            let duration_ms = matcher
+                .duration_secs(track_id)
+                .map(|secs| secs.max(0) as u64 * 1000);
+            let is_completed = threshold.is_reached(ms_played, duration_ms);
+            let started_at = started_at.to_string();
+            if import_play(
+                &mut tx,
+                user_id,
+                track_id,
+                &started_at,
+                ms_played,
+                is_completed,
+            )
+            .await?
+            {
+                report.plays += 1;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(report)
+    }
+}
+
+/// Reads the files of the Spotify export that was extracted to the `dir`.
+///
+/// Returns [SpotsError::InvalidSpotifyExport] if none of the files that can be imported are in
+/// the directory.
+pub fn read_spotify_export(dir: &Path) -> DBResult<SpotifyExport> {
+    let mut file_names: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|entry| Some(entry.ok()?.file_name().to_string_lossy().to_string()))
+        .filter(|file_name| file_name.to_lowercase().ends_with(".json"))
+        .collect();
+    file_names.sort();
+
+    let mut export = SpotifyExport::default();
+    let mut found = false;
+    for file_name in file_names {
+        let path = dir.join(&file_name);
+        if file_name.starts_with("Playlist") {
+            let playlists: SpotifyPlaylists = read_json(&path)?;
+            export.playlists.extend(playlists.playlists);
+        } else if file_name.starts_with("StreamingHistory")
+            && !file_name.contains("podcast")
+            && !file_name.contains("video")
+        {
+            let streams: Vec<SpotifyStream> = read_json(&path)?;
+            export.streaming_history.extend(streams);
+        } else if file_name == "YourLibrary.json" {
+            export.library = read_json(&path)?;
+        } else {
+            continue;
+        }
+        found = true;
+    }
+
+    if !found {
+        return Err(SpotsError::InvalidSpotifyExport(format!(
+            "no Spotify data was found in {}",
+            dir.to_string_lossy()
+        )));
+    }
+    Ok(export)
+}
+
+/// Reads a JSON file of the export.
+fn read_json<T: DeserializeOwned>(path: &Path) -> DBResult<T> {
+    let json = std::fs::read(path)?;
+    serde_json::from_slice(&json)
+        .map_err(|e| SpotsError::InvalidSpotifyExport(format!("{}: {e}", path.to_string_lossy())))
+}
+
+/// A name's trigrams, along with the trigrams of its base name (see [base_name]).
+struct Name {
+    full: HashSet<[char; 3]>,
+    base: HashSet<[char; 3]>,
+}
+
+impl Name {
+    fn new(name: &str) -> Self {
+        Self {
+            full: trigrams(&normalize(name)),
+            base: trigrams(&normalize(base_name(name))),
+        }
+    }
+
+    /// How similar the names are, from `0.0` to `1.0`.
+    fn similarity(&self, other: &Name) -> f64 {
+        trigram_similarity(&self.full, &other.full).max(trigram_similarity(&self.base, &other.base))
+    }
+}
+
+/// A track in the library, for matching the export's tracks to it.
+struct MatchTrack {
+    track: LocalTrack,
+    title: Name,
+    album: Option<Name>,
+}
+
+/// Matches the export's tracks, albums and artists to the library's by their names.
+struct Matcher {
+    tracks: Vec<MatchTrack>,
+    albums: Vec<(Uuid, Name, Vec<String>)>,
+    artists: Vec<(Uuid, String, Name)>,
+
+    /// The library's artists that are similar to an artist in the export, with their
+    /// similarity.
+    artist_matches: HashMap<String, HashMap<String, f64>>,
+
+    /// The tracks that were already matched.
+    track_matches: HashMap<SpotifyTrack, Option<Uuid>>,
+}
+
+impl Matcher {
+    async fn new(db: &DatabaseClient) -> DBResult<Self> {
+        let tracks: Vec<MatchTrack> = local_tracks(db)
+            .await?
+            .into_iter()
+            .filter(|track| !track.is_missing)
+            .map(|track| MatchTrack {
+                title: Name::new(&track.title),
+                album: track.album.as_deref().map(Name::new),
+                track,
+            })
+            .collect();
+
+        // An album's artists are the artists of its tracks
+        let album_artists: Vec<(String, String, Option<String>)> = sqlx::query_as(
+            "
+            SELECT DISTINCT al.id, al.title, a.name
+            FROM albums al
+            LEFT JOIN tracks t ON t.album_id = al.id
+            LEFT JOIN track_artists ta ON ta.track_id = t.id
+            LEFT JOIN artists a ON a.id = ta.artist_id
+            ORDER BY al.id
+            ",
+        )
+        .fetch_all(&db.pool)
+        .await?;
+        let mut albums: Vec<(Uuid, Name, Vec<String>)> = vec![];
+        for (id, title, artist) in album_artists {
+            let id = Uuid::parse_str(&id).map_err(|e| sqlx::Error::Decode(e.into()))?;
+            if albums.last().is_none_or(|(last_id, _, _)| *last_id != id) {
+                albums.push((id, Name::new(&title), vec![]));
+            }
+            if let (Some(artist), Some((_, _, artists))) = (artist, albums.last_mut()) {
+                artists.push(artist);
+            }
+        }
+
+        let artists: Vec<(String, String)> = sqlx::query_as("SELECT id, name FROM artists")
+            .fetch_all(&db.pool)
+            .await?;
+        let artists = artists
+            .into_iter()
+            .map(|(id, name)| {
+                let id = Uuid::parse_str(&id).map_err(|e| sqlx::Error::Decode(e.into()))?;
+                Ok((id, normalize(&name), Name::new(&name)))
+            })
+            .collect::<DBResult<_>>()?;
+
+        Ok(Self {
+            tracks,
+            albums,
+            artists,
+            artist_matches: HashMap::new(),
+            track_matches: HashMap::new(),
+        })
+    }
+
+    /// Gets how similar the artist in the export is to each of the library's artists that it
+    /// matches (by their normalized names).
+    fn artist_matches(&mut self, artist: &str) -> &HashMap<String, f64> {
+        let artists = &self.artists;
+        self.artist_matches
+            .entry(normalize(artist))
+            .or_insert_with(|| {
+                let name = Name::new(artist);
+                artists
+                    .iter()
+                    .filter_map(|(_, normalized, local)| {
+                        let score = name.similarity(local);
+                        (score >= MIN_MATCH_SIMILARITY).then(|| (normalized.clone(), score))
+                    })
+                    .collect()
+            })
+    }
+
+    /// How similar the artist in the export is to the most similar of the artists (`None` if
+    /// it doesn't match any of them).
+    fn artist_score(&mut self, artist: &str, artists: &[String]) -> Option<f64> {
+        let matches = self.artist_matches(artist);
+        artists
+            .iter()
+            .filter_map(|name| matches.get(&normalize(name)).copied())
+            .max_by(f64::total_cmp)
+    }
+
+    /// Finds the track in the library that is most similar to the export's track.
+    fn match_track(&mut self, track: &SpotifyTrack) -> Option<Uuid> {
+        if let Some(track_id) = self.track_matches.get(track) {
+            return *track_id;
+        }
+
+        let title = Name::new(&track.track_name);
+        let album = track.album_name.as_deref().map(Name::new);
+        let artist_matches = self.artist_matches(&track.artist_name).clone();
+        let best = self
+            .tracks
+            .iter()
+            .filter_map(|local| {
+                let artist_score = local
+                    .track
+                    .artists
+                    .iter()
+                    .filter_map(|name| artist_matches.get(&normalize(name)).copied())
+                    .max_by(f64::total_cmp)?;
+                let title_score = title.similarity(&local.title);
+                if title_score < MIN_MATCH_SIMILARITY {
+                    return None;
+                }
+                let album_score = match (&album, &local.album) {
+                    (Some(album), Some(local_album)) => album.similarity(local_album),
+                    _ => 0.0,
+                };
+                Some((title_score * 2.0 + artist_score + album_score / 2.0, local))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, local)| local.track.id);
+
+        self.track_matches.insert(track.clone(), best);
+        best
+    }
+
+    /// Finds the album in the library that is most similar to the export's album.
+    fn match_album(&mut self, album: &SpotifyAlbum) -> Option<Uuid> {
+        let title = Name::new(&album.album);
+        let candidates: Vec<(Uuid, f64, Vec<String>)> = self
+            .albums
+            .iter()
+            .filter_map(|(id, local, artists)| {
+                let score = title.similarity(local);
+                (score >= MIN_MATCH_SIMILARITY).then(|| (*id, score, artists.clone()))
+            })
+            .collect();
+        candidates
+            .into_iter()
+            .filter_map(|(id, title_score, artists)| {
+                // Albums without artists can only be matched by their title
+                let artist_score = if artists.is_empty() {
+                    0.0
+                } else {
+                    self.artist_score(&album.artist, &artists)?
+                };
+                Some((title_score * 2.0 + artist_score, id))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, id)| id)
+    }
+
+    /// Finds the artist in the library that is most similar to the export's artist.
+    fn match_artist(&mut self, artist: &str) -> Option<Uuid> {
+        let matches = self.artist_matches(artist).clone();
+        self.artists
+            .iter()
+            .filter_map(|(id, normalized, _)| Some((matches.get(normalized)?, *id)))
+            .max_by(|a, b| a.0.total_cmp(b.0))
+            .map(|(_, id)| id)
+    }
+
+    /// Gets the duration of the library's track.
+    fn duration_secs(&self, track_id: Uuid) -> Option<i64> {
+        self.tracks
+            .iter()
+            .find(|local| local.track.id == track_id)
+            .and_then(|local| local.track.duration_secs)
+    }
+}
+
+/// Gets the name without the suffixes Spotify adds to it (e.g. "Song - Remastered 2011" or
+/// "Song (feat. Artist)").
+fn base_name(name: &str) -> &str {
+    let end = [" - ", " (", " ["]
+        .iter()
+        .filter_map(|separator| name.find(separator))
+        .filter(|end| *end > 0)
+        .min()
+        .unwrap_or(name.len());
+    &name[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        favorites::FavoriteExt,
+        history::HistoryExt,
+        library::{LibraryExt, NewTrack},
+        playlists::PlaylistExt,
+        test_utils::{count, TempDb},
+        users::UserExt,
+    };
+
+    async fn add_track(
+        db: &DatabaseClient,
+        user_id: Uuid,
+        title: &str,
+        artist: &str,
+        album: &str,
+    ) -> Uuid {
+        let track = NewTrack {
+            file_path: format!("/music/{artist}/{album}/{title}.mp3"),
+            title: title.to_string(),
+            album: Some(album.to_string()),
+            artists: vec![artist.to_string()],
+            duration_secs: Some(200),
+            ..Default::default()
+        };
+        db.upsert_track(user_id, track).await.unwrap().id
+    }
+
+    fn track(title: &str, artist: &str) -> SpotifyTrack {
+        SpotifyTrack {
+            track_name: title.to_string(),
+            artist_name: artist.to_string(),
+            album_name: None,
+        }
+    }
+
+    #[test]
+    fn test_base_name() {
+        assert_eq!(base_name("Song - Remastered 2011"), "Song");
+        assert_eq!(base_name("Song (feat. Artist) [Live]"), "Song");
+        assert_eq!(base_name("(Untitled)"), "(Untitled)");
+    }
+
+    #[test]
+    fn test_read_spotify_export() {
+        let dir = std::env::temp_dir().join(format!("spots-spotify-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(read_spotify_export(&dir).is_err());
+
+        std::fs::write(
+            dir.join("Playlist1.json"),
+            r#"{"playlists": [{"name": "Mix", "lastModifiedDate": "2024-01-01", "items": [
+                {"track": {"trackName": "Halo", "artistName": "Beyoncé", "albumName": "I Am...",
+                           "trackUri": "spotify:track:1"}, "episode": null, "localTrack": null},
+                {"track": null, "episode": {"episodeName": "Episode"}, "localTrack": null}
+            ]}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("StreamingHistory_music_0.json"),
+            r#"[{"endTime": "2024-01-01 12:00", "artistName": "Queen",
+                 "trackName": "Bohemian Rhapsody", "msPlayed": 354000}]"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("StreamingHistory_podcast_0.json"), "[{}]").unwrap();
+        std::fs::write(
+            dir.join("YourLibrary.json"),
+            r#"{"tracks": [{"artist": "Queen", "album": "A Night at the Opera",
+                            "track": "Love of My Life", "uri": "spotify:track:2"}],
+                "albums": [], "shows": [], "episodes": [], "bannedTracks": [],
+                "artists": [{"name": "Queen", "uri": "spotify:artist:1"}], "other": []}"#,
+        )
+        .unwrap();
+
+        let export = read_spotify_export(&dir).unwrap();
+        assert_eq!(export.playlists.len(), 1);
+        assert_eq!(export.playlists[0].items.len(), 2);
+        assert_eq!(export.streaming_history.len(), 1);
+        assert_eq!(export.library.tracks.len(), 1);
+        assert_eq!(export.library.artists.len(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_spotify() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let opera = "A Night at the Opera";
+            let rhapsody = add_track(db, user_id, "Bohemian Rhapsody", "Queen", opera).await;
+            let love = add_track(db, user_id, "Love of My Life", "Queen", opera).await;
+            let halo = add_track(db, user_id, "Halo", "Beyoncé", "I Am... Sasha Fierce").await;
+            add_track(db, user_id, "Halo", "Other Artist", "Other Album").await;
+
+            let export = SpotifyExport {
+                playlists: vec![SpotifyPlaylist {
+                    name: String::from("Road Trip"),
+                    items: vec![
+                        SpotifyPlaylistItem {
+                            track: Some(track("Bohemian Rhapsody - Remastered 2011", "Queen")),
+                        },
+                        SpotifyPlaylistItem {
+                            track: Some(track("Halo", "Beyonce")),
+                        },
+                        SpotifyPlaylistItem {
+                            track: Some(track("Unknown Song", "Nobody")),
+                        },
+                        SpotifyPlaylistItem { track: None },
+                    ],
+                }],
+                streaming_history: vec![
+                    SpotifyStream {
+                        end_time: String::from("2024-01-01 12:00"),
+                        artist_name: String::from("Queen"),
+                        track_name: String::from("Love Of My Life"),
+                        ms_played: 200_000,
+                    },
+                    SpotifyStream {
+                        end_time: String::from("2024-01-01 12:05"),
+                        artist_name: String::from("Queen"),
+                        track_name: String::from("Bohemian Rhapsody"),
+                        ms_played: 5_000,
+                    },
+                    SpotifyStream {
+                        end_time: String::from("2024-01-01 12:10"),
+                        artist_name: String::from("Nobody"),
+                        track_name: String::from("Unknown Song"),
+                        ms_played: 200_000,
+                    },
+                ],
+                library: SpotifyLibrary {
+                    tracks: vec![SpotifySavedTrack {
+                        artist: String::from("Queen"),
+                        album: String::from(opera),
+                        track: String::from("Love of My Life - 2011 Remaster"),
+                    }],
+                    albums: vec![
+                        SpotifyAlbum {
+                            artist: String::from("Queen"),
+                            album: String::from("A Night At The Opera (Deluxe Edition)"),
+                        },
+                        SpotifyAlbum {
+                            artist: String::from("Nobody"),
+                            album: String::from(opera),
+                        },
+                    ],
+                    artists: vec![
+                        SpotifyArtist {
+                            name: String::from("queen"),
+                        },
+                        SpotifyArtist {
+                            name: String::from("Nobody"),
+                        },
+                    ],
+                },
+            };
+
+            let report = db.import_spotify(user_id, &export).await.unwrap();
+            assert_eq!(report.playlists, 1);
+            assert_eq!(report.favorites, 3);
+            assert_eq!(report.plays, 2);
+            assert_eq!(
+                report.unmatched_tracks,
+                vec![track("Unknown Song", "Nobody")]
+            );
+            assert_eq!(
+                report.unmatched_albums,
+                vec![export.library.albums[1].clone()]
+            );
+            assert_eq!(report.unmatched_artists, vec![String::from("Nobody")]);
+
+            let playlist_id: String =
+                sqlx::query_scalar("SELECT id FROM playlists WHERE title = 'Road Trip'")
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap();
+            assert_eq!(
+                db.get_playlist_track_ids(playlist_id.parse().unwrap())
+                    .await
+                    .unwrap(),
+                vec![rhapsody, halo]
+            );
+            assert_eq!(db.get_favorited_albums(user_id).await.unwrap().len(), 1);
+            assert_eq!(db.get_favorited_artists(user_id).await.unwrap().len(), 1);
+            assert_eq!(db.get_play_count(user_id, love).await.unwrap(), 1);
+            assert_eq!(db.get_play_count(user_id, rhapsody).await.unwrap(), 0);
+            let started_at: String =
+                sqlx::query_scalar("SELECT started_at FROM play_history WHERE track_id = $1")
+                    .bind(love.to_string())
+                    .fetch_one(&db.pool)
+                    .await
+                    .unwrap();
+            assert_eq!(started_at, "2024-01-01 11:56:40");
+
+            // Importing again doesn't duplicate the playlists, favorites or plays
+            let report = db.import_spotify(user_id, &export).await.unwrap();
+            assert_eq!(
+                (report.playlists, report.favorites, report.plays),
+                (0, 0, 0)
+            );
+            assert_eq!(count(db, "playlists").await, 1);
+            assert_eq!(count(db, "play_history").await, 2);
+        });
+    }
+}
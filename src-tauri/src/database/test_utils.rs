@@ -0,0 +1,94 @@
+//! Helpers for tests that need a real DB.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use uuid::Uuid;
+
+use crate::{api::utils::ResponseChannel, database::client::DatabaseClient};
+
+/// A migrated DB in a temp file (in-memory DBs can't be shared between connections), which
+/// is deleted when it is dropped.
+pub struct TempDb {
+    pub db: DatabaseClient,
+    pub path: PathBuf,
+}
+
+impl TempDb {
+    pub async fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("spots-test-{}.sqlite", Uuid::new_v4()));
+        let db = DatabaseClient::open(&path).await.unwrap();
+        Self { db, path }
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.path.to_string_lossy()));
+        }
+    }
+}
+
+/// Adds a track to the DB.
+pub async fn insert_track(db: &DatabaseClient, title: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    let now = Utc::now().naive_local().to_string();
+    sqlx::query(
+        "
+        INSERT INTO tracks (id, title, file_path, thumbnail_path, created_at, updated_at)
+        VALUES ($1, $2, $3, '', $4, $4)
+        ",
+    )
+    .bind(id.to_string())
+    .bind(title)
+    .bind(format!("/music/{id}.mp3"))
+    .bind(&now)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+    id
+}
+
+/// Counts the rows in the table.
+pub async fn count(db: &DatabaseClient, table: &str) -> i64 {
+    sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table}"))
+        .fetch_one(&db.pool)
+        .await
+        .unwrap()
+}
+
+/// The responses sent to a channel, as JSON.
+pub type Responses = Arc<Mutex<Vec<Value>>>;
+
+/// Makes a channel that keeps the responses sent to it.
+pub fn response_channel<T: Serialize>() -> (ResponseChannel<T>, Responses) {
+    let responses = Responses::default();
+    let sent = responses.clone();
+    let channel = Channel::new(move |body| {
+        if let InvokeResponseBody::Json(json) = body {
+            sent.lock()
+                .unwrap()
+                .push(serde_json::from_str(&json).unwrap());
+        }
+        Ok(())
+    });
+    (channel, responses)
+}
+
+/// The values of the rows that were streamed to a channel.
+pub fn streamed_values(responses: &Responses) -> Vec<Value> {
+    responses
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|response| response["status"] == "Pending")
+        .map(|response| response["value"].clone())
+        .collect()
+}
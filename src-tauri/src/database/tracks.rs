@@ -1,3 +1,5 @@
+use std::{io::ErrorKind, path::Path};
+
 use sqlx::Sqlite;
 use uuid::Uuid;
 
@@ -5,9 +7,12 @@ use crate::{
     api::utils::ResponseChannel,
     database::{
         client::DatabaseClient,
+        library::LibraryExt,
         models::music_library::{Artist, Genre, Track},
-        stream_rows, DBResult,
+        query::{parse_query, SqlArg},
+        sql_arguments, stream_page, stream_rows, DBResult, Page, SortKey, SortOrder,
     },
+    errors::SpotsError,
 };
 
 /// Database operations for [Track].
@@ -15,15 +20,19 @@ pub trait TrackExt {
     /// Gets the specified track from the DB.
     async fn get_track(&self, track_id: Uuid) -> DBResult<Option<Track>>;
 
-    /// Gets the user's favorited tracks.
+    /// Gets a page of the user's favorited tracks (most recently favorited first by default).
+    ///
+    /// Sorting by [SortKey::Added] sorts by when the tracks were favorited. Returns the cursor of
+    /// the next page.
     ///
     /// # Note
     /// The tracks are all streamed to the `channel`.
     async fn get_favorited_tracks(
         &self,
         user_id: Uuid,
+        page: &Page,
         channel: ResponseChannel<Track>,
-    ) -> DBResult<()>;
+    ) -> DBResult<Option<String>>;
 
     /// Gets all of the artists for the specified track.
     async fn get_track_artists(&self, track_id: Uuid) -> DBResult<Vec<Artist>>;
@@ -31,12 +40,42 @@ pub trait TrackExt {
     /// Gets the track's genres.
     async fn get_track_genres(&self, track_id: Uuid) -> DBResult<Vec<Genre>>;
 
-    /// Gets all of the tracks in the DB.
-    async fn get_all_tracks(&self, channel: ResponseChannel<Track>) -> DBResult<()>;
+    /// Gets a page of the tracks in the DB (sorted by title by default).
+    ///
+    /// Returns the cursor of the next page.
+    ///
+    /// # Note
+    /// The tracks are all streamed to the `channel`.
+    async fn get_all_tracks(
+        &self,
+        page: &Page,
+        channel: ResponseChannel<Track>,
+    ) -> DBResult<Option<String>>;
+
+    /// Gets the tracks that match the query (e.g. `artist:"Daft Punk" year:>2000`), sorted by
+    /// title. See [crate::database::query] for the syntax.
+    ///
+    /// The `played:` filter uses the play counts of the user.
+    ///
+    /// # Note
+    /// The tracks are all streamed to the `channel`.
+    async fn filter_tracks(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        channel: ResponseChannel<Track>,
+    ) -> DBResult<()>;
 
     /// Gets the audio data of the track as bytes.
+    ///
+    /// The track is marked as missing if its file no longer exists.
     async fn get_audio_data(&self, track_id: Uuid) -> DBResult<Vec<u8>>;
 
+    /// Gets a track that can be played (or streamed).
+    ///
+    /// The track is marked as missing if its file no longer exists.
+    async fn get_playable_track(&self, track_id: Uuid) -> DBResult<Track>;
+
     /// Gets the last played track.
     async fn get_last_played_track(&self) -> DBResult<Option<Track>>;
 }
@@ -53,23 +92,32 @@ impl TrackExt for DatabaseClient {
     async fn get_favorited_tracks(
         &self,
         user_id: Uuid,
+        page: &Page,
         channel: ResponseChannel<Track>,
-    ) -> DBResult<()> {
-        let favorited_tracks = sqlx::query_as::<Sqlite, Track>(
+    ) -> DBResult<Option<String>> {
+        let order = page.order(SortOrder {
+            key: SortKey::Added,
+            descending: true,
+        });
+        let sort_sql = match order.key {
+            SortKey::Added => "COALESCE(r.favorited_at, '')",
+            key => track_sort_sql(key),
+        };
+        stream_page(
+            &self.pool,
             "
-            SELECT t.*
+            SELECT t.*, ft.favorited_at
             FROM tracks t
-            LEFT JOIN favorited_tracks ft ON t.id = ft.track_id
+            JOIN favorited_tracks ft ON t.id = ft.track_id
             WHERE ft.user_id = $1
             ",
+            vec![SqlArg::Text(user_id.to_string())],
+            sort_sql,
+            order,
+            page,
+            channel,
         )
-        .bind(user_id.to_string())
-        .fetch(&self.pool);
-
-        // Stream track to the channel
-        stream_rows(favorited_tracks, channel).await?;
-
-        Ok(())
+        .await
     }
 
     async fn get_track_artists(&self, track_id: Uuid) -> DBResult<Vec<Artist>> {
@@ -102,8 +150,43 @@ impl TrackExt for DatabaseClient {
         Ok(track_genres)
     }
 
-    async fn get_all_tracks(&self, channel: ResponseChannel<Track>) -> DBResult<()> {
-        let tracks = sqlx::query_as::<Sqlite, Track>("SELECT * FROM tracks").fetch(&self.pool);
+    async fn get_all_tracks(
+        &self,
+        page: &Page,
+        channel: ResponseChannel<Track>,
+    ) -> DBResult<Option<String>> {
+        let order = page.order(SortOrder::BY_TITLE);
+        stream_page(
+            &self.pool,
+            "SELECT * FROM tracks",
+            Vec::new(),
+            track_sort_sql(order.key),
+            order,
+            page,
+            channel,
+        )
+        .await
+    }
+
+    async fn filter_tracks(
+        &self,
+        user_id: Uuid,
+        query: &str,
+        channel: ResponseChannel<Track>,
+    ) -> DBResult<()> {
+        let mut args = Vec::new();
+        let condition = parse_query(query)?.to_sql(user_id, &mut args);
+        let sql = format!(
+            "
+            SELECT t.*
+            FROM tracks t
+            WHERE {condition}
+            ORDER BY t.title
+            "
+        );
+
+        let tracks =
+            sqlx::query_as_with::<Sqlite, Track, _>(&sql, sql_arguments(args)?).fetch(&self.pool);
 
         // Stream track to the channel
         stream_rows(tracks, channel).await?;
@@ -117,7 +200,26 @@ impl TrackExt for DatabaseClient {
             .await?
             .ok_or_else(|| sqlx::Error::RowNotFound)?
             .file_path;
-        Ok(std::fs::read(filepath)?)
+        match std::fs::read(&filepath) {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                self.mark_tracks_missing(&filepath).await?;
+                Err(SpotsError::TrackFileMissing(filepath))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_playable_track(&self, track_id: Uuid) -> DBResult<Track> {
+        let track = self
+            .get_track(track_id)
+            .await?
+            .ok_or_else(|| sqlx::Error::RowNotFound)?;
+        if !Path::new(&track.file_path).exists() {
+            self.mark_tracks_missing(&track.file_path).await?;
+            return Err(SpotsError::TrackFileMissing(track.file_path));
+        }
+        Ok(track)
     }
 
     async fn get_last_played_track(&self) -> DBResult<Option<Track>> {
@@ -134,3 +236,99 @@ impl TrackExt for DatabaseClient {
         Ok(last_played_track)
     }
 }
+
+/// The sort key of a track `r` (see [stream_page]).
+///
+/// Tracks sorted by album are in track number order within each album.
+pub(crate) fn track_sort_sql(key: SortKey) -> &'static str {
+    match key {
+        SortKey::Title => "r.title COLLATE NOCASE",
+        SortKey::Album => "r.album_sort_key",
+        SortKey::Artist => "r.artist_sort_key",
+        SortKey::Year => "COALESCE(r.release_year, 0)",
+        SortKey::Added => "r.created_at",
+        SortKey::LastPlayed => "COALESCE(r.last_played_at, '')",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::database::{
+        history::{HistoryExt, NewPlay},
+        library::NewTrack,
+        test_utils::{response_channel, streamed_values, TempDb},
+        users::UserExt,
+    };
+
+    /// Gets the titles of the tracks that match the query.
+    async fn filtered_titles(db: &DatabaseClient, user_id: Uuid, query: &str) -> Vec<String> {
+        let (channel, responses) = response_channel();
+        db.filter_tracks(user_id, query, channel).await.unwrap();
+        streamed_values(&responses)
+            .iter()
+            .map(|track| track["title"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_filter_tracks() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            let other_id = db.create_user("other", "hash").await.unwrap().id;
+            let mut track_ids = vec![];
+            for (title, artist, year) in [
+                ("Da Funk", "Daft Punk", 1995),
+                ("One More Time", "Daft Punk", 2000),
+                ("Get Lucky", "Daft Punk", 2013),
+                ("Lose Yourself to Dance", "Daft Punk", 2013),
+                ("Around the World", "Daft Punk", 2001),
+                ("Genesis", "Justice", 2007),
+            ] {
+                let track = NewTrack {
+                    file_path: format!("/music/{title}.mp3"),
+                    title: String::from(title),
+                    artists: vec![String::from(artist)],
+                    release_year: Some(year),
+                    duration_secs: Some(240),
+                    ..Default::default()
+                };
+                track_ids.push(db.upsert_track(user_id, track).await.unwrap().id);
+            }
+            let play = |track_id| NewPlay {
+                track_id,
+                playlist_id: None,
+                started_at: Utc::now().naive_local(),
+                ms_played: 240_000,
+            };
+            db.record_play(user_id, play(track_ids[2])).await.unwrap();
+            db.record_play(other_id, play(track_ids[3])).await.unwrap();
+
+            // Play counts are the user's own
+            let query = r#"artist:"daft punk" year:>2000 played:never"#;
+            assert_eq!(
+                filtered_titles(db, user_id, query).await,
+                vec!["Around the World", "Lose Yourself to Dance"]
+            );
+            assert_eq!(
+                filtered_titles(db, other_id, query).await,
+                vec!["Around the World", "Get Lucky"]
+            );
+
+            assert_eq!(
+                filtered_titles(db, user_id, "-artist:daft OR year:1990..1999").await,
+                vec!["Da Funk", "Genesis"]
+            );
+            assert_eq!(filtered_titles(db, user_id, "").await.len(), 6);
+            assert!(matches!(
+                db.filter_tracks(user_id, "year:>", response_channel().0)
+                    .await,
+                Err(SpotsError::QueryParseError { .. })
+            ));
+        });
+    }
+}
@@ -1,7 +1,8 @@
 use serde::Serialize;
 use thiserror::Error;
+use uuid::Uuid;
 
-use crate::api::utils::token::Token;
+use crate::{api::utils::token::Token, database::SortKey};
 
 #[derive(Error, Debug, Serialize)]
 pub enum SpotsError {
@@ -38,6 +39,9 @@ pub enum SpotsError {
     #[error("The auth token has expired and is invalid")]
     AuthTokenExpired,
 
+    #[error("The auth token has been revoked")]
+    AuthTokenRevoked,
+
     #[error("Validation failed: {0}")]
     ValidationError(#[from] validator::ValidationErrors),
 
@@ -60,6 +64,89 @@ pub enum SpotsError {
         #[serde(serialize_with = "io_error_serializer")]
         std::io::Error,
     ),
+
+    #[error("The music library directory is invalid: {0}")]
+    InvalidLibraryDirectory(String),
+
+    #[error("Unable to read the audio metadata: {{ file: {}, error: {} }}", .file, .error)]
+    MetadataError { file: String, error: String },
+
+    #[error("Unable to resolve the app data directory: {0}")]
+    AppDataDirError(String),
+
+    #[error("Unable to create the thumbnail: {{ path: {}, error: {} }}", .path, .error)]
+    ThumbnailError { path: String, error: String },
+
+    #[error("Library watcher error: {0}")]
+    WatcherError(String),
+
+    #[error("The track's file could not be found: {0}")]
+    TrackFileMissing(String),
+
+    #[error("Invalid stream request: {0}")]
+    InvalidStreamRequest(String),
+
+    #[error("The requested range is not satisfiable: {{ range: {}, file_len: {} }}", .range, .file_len)]
+    RangeNotSatisfiable { range: String, file_len: u64 },
+
+    #[error("Unable to decode the audio: {{ file: {}, error: {} }}", .file, .error)]
+    DecodeError { file: String, error: String },
+
+    #[error("Audio output error: {0}")]
+    AudioOutputError(String),
+
+    #[error("Player error: {0}")]
+    PlayerError(String),
+
+    #[error("The entry isn't in the play queue: {0}")]
+    QueueEntryNotFound(Uuid),
+
+    #[error("The playlist doesn't belong to the user: {0}")]
+    PlaylistAccessDenied(Uuid),
+
+    #[error("Unable to pin more than {0} playlists")]
+    PinLimitReached(usize),
+
+    /// `start` and `end` are offsets in UTF-16 code units.
+    #[error("Unable to parse the query: {{ message: {}, start: {}, end: {} }}", .message, .start, .end)]
+    QueryParseError {
+        message: String,
+        start: usize,
+        end: usize,
+    },
+
+    #[error("The tracks of a smart playlist can't be edited: {0}")]
+    SmartPlaylistNotEditable(Uuid),
+
+    #[error("The smart playlist doesn't have an owner: {0}")]
+    SmartPlaylistWithoutOwner(Uuid),
+
+    #[error("Invalid page cursor: {0}")]
+    InvalidPageCursor(String),
+
+    #[error("The list can't be sorted by {0:?}")]
+    UnsupportedSortKey(SortKey),
+
+    #[error("The backup can't be restored: {0}")]
+    InvalidBackup(String),
+
+    #[error("The backup could not be found: {0}")]
+    BackupNotFound(String),
+
+    #[error("The library bundle is invalid: {0}")]
+    InvalidBundle(String),
+
+    #[error("The library bundle is from a newer version of Spots (version {0})")]
+    UnsupportedBundleVersion(u32),
+
+    #[error("The playlist file's format isn't supported: {0}")]
+    UnsupportedPlaylistFormat(String),
+
+    #[error("Unable to parse the playlist file: {0}")]
+    PlaylistFileParseError(String),
+
+    #[error("The Spotify data is invalid: {0}")]
+    InvalidSpotifyExport(String),
 }
 
 fn sqlx_error_serializer<S: serde::Serializer>(
@@ -1,21 +1,111 @@
-use std::sync::Arc;
+use std::{
+    cell::Cell,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use dotenvy::dotenv;
-use tauri::{async_runtime::Mutex, Manager};
+use tauri::{async_runtime::Mutex, AppHandle, Emitter, Manager};
+use tracing::{error, warn};
 use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
 
-use crate::{api::utils::ApiConfig, database::client::DatabaseClient};
+use crate::{
+    api::utils::ApiConfig,
+    database::{
+        backup::{backups_dir, BackupExt},
+        client::DatabaseClient,
+    },
+    library::{artwork::thumbnails_dir, watcher::LibraryWatcher},
+    playback::{
+        sink::{AudioSink, DeviceSink, NullSink},
+        Player, PlayerEvent, PLAYBACK_ENDED_EVENT, PLAYBACK_POSITION_EVENT, PLAYBACK_STATUS_EVENT,
+    },
+};
 
 mod api;
+#[doc(hidden)]
+pub mod bench_support;
 mod database;
 mod errors;
+mod library;
 mod logger;
+mod playback;
 
 /// The app state.
 #[derive(Clone)]
 struct AppState {
-    db: Arc<Mutex<DatabaseClient>>,
+    /// The database, which commands use concurrently (see [DatabaseClient]).
+    db: DatabaseClient,
     api_config: Arc<Mutex<ApiConfig>>,
+    library_watcher: Arc<Mutex<LibraryWatcher>>,
+    player: Arc<Mutex<Player>>,
+
+    /// The user whose play queue is playing.
+    queue_user: Arc<Mutex<Option<Uuid>>>,
+
+    /// The user whose tracks the player is playing, who the plays are recorded for.
+    playback_user: Arc<Mutex<Option<Uuid>>>,
+}
+
+/// How often the position in the queue that is playing is saved while a track plays, so
+/// playback can be resumed close to where it was if the app doesn't close cleanly.
+const QUEUE_POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Starts the player, playing audio on the default output device.
+///
+/// The player's events are emitted to the frontend.
+fn start_player(app: AppHandle) -> Player {
+    let make_sink = || -> Box<dyn AudioSink> {
+        match DeviceSink::new() {
+            Ok(sink) => Box::new(sink),
+            Err(e) => {
+                warn!(
+                    error = e.to_string(),
+                    "No audio output, playback will be silent"
+                );
+                Box::new(NullSink)
+            }
+        }
+    };
+    let last_position_save = Cell::new(Instant::now());
+    let on_event = move |event: PlayerEvent| {
+        let result = match event {
+            PlayerEvent::StatusChanged(status) => app.emit(PLAYBACK_STATUS_EVENT, status),
+            PlayerEvent::Position(position) => {
+                if last_position_save.get().elapsed() >= QUEUE_POSITION_SAVE_INTERVAL {
+                    last_position_save.set(Instant::now());
+                    let queue_app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        let state = queue_app.state::<AppState>();
+                        if let Err(e) = api::queue::save_queue_position(&state).await {
+                            warn!(error = e.to_string(), "Unable to save the queue position");
+                        }
+                    });
+                }
+                app.emit(PLAYBACK_POSITION_EVENT, position)
+            }
+            PlayerEvent::TrackEnded(play) => {
+                // Record the play, then play the next track in the queue
+                let track_id = play.track_id;
+                let queue_app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let state = queue_app.state::<AppState>();
+                    if let Err(e) = api::history::record_track_play(&state, play).await {
+                        error!(error = e.to_string(), "Unable to record the play");
+                    }
+                    if let Err(e) = api::queue::advance_queue(&state).await {
+                        error!(error = e.to_string(), "Unable to play the next track");
+                    }
+                });
+                app.emit(PLAYBACK_ENDED_EVENT, track_id)
+            }
+        };
+        if let Err(e) = result {
+            error!(error = e.to_string(), "Unable to emit playback event");
+        }
+    };
+    Player::spawn(make_sink, on_event).expect("Failed to start player")
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -43,22 +133,93 @@ pub fn run() {
             logger::error,
             api::auth::register_user,
             api::auth::login_user,
+            api::auth::logout_user,
+            api::auth::logout_all_devices,
             api::music::get_playlist,
             api::music::get_playlist_tracks,
             api::music::get_pinned_playlists,
             api::music::get_all_playlists,
             api::music::get_track,
             api::music::get_favorited_tracks,
+            api::favorites::toggle_favorite_track,
+            api::favorites::toggle_favorite_album,
+            api::favorites::toggle_favorite_artist,
+            api::favorites::toggle_followed_playlist,
+            api::favorites::get_favorited_albums,
+            api::favorites::get_favorited_artists,
+            api::favorites::get_followed_playlists,
             api::music::get_track_artists,
             api::music::get_track_genres,
             api::music::get_all_tracks,
+            api::music::filter_tracks,
             api::music::get_last_played_track,
             api::music::get_audio_data,
             api::music::get_album,
             api::music::get_album_tracks,
             api::music::get_album_artists,
             api::music::get_all_albums,
+            api::music::cancel_stream,
+            api::search::search,
+            api::search::fuzzy_search,
+            api::library::import_library,
+            api::library::export_library_bundle,
+            api::library::import_library_bundle,
+            api::library::import_spotify_data,
+            api::playlists::create_playlist,
+            api::playlists::create_smart_playlist,
+            api::playlists::get_smart_playlist_rules,
+            api::playlists::set_smart_playlist_rules,
+            api::playlists::rename_playlist,
+            api::playlists::set_playlist_thumbnail,
+            api::playlists::delete_playlist,
+            api::playlists::add_playlist_tracks,
+            api::playlists::remove_playlist_tracks,
+            api::playlists::move_playlist_track,
+            api::playlists::pin_playlist,
+            api::playlists::unpin_playlist,
+            api::playlists::move_pinned_playlist,
+            api::playlists::import_playlist_file,
+            api::playlists::export_playlist_file,
+            api::playback::play_track,
+            api::playback::resume_playback,
+            api::playback::pause_playback,
+            api::playback::stop_playback,
+            api::playback::seek_playback,
+            api::playback::set_volume,
+            api::playback::set_crossfade,
+            api::playback::get_playback_status,
+            api::queue::get_play_queue,
+            api::queue::enqueue_tracks,
+            api::queue::enqueue_album,
+            api::queue::enqueue_playlist,
+            api::queue::move_queue_entry,
+            api::queue::remove_queue_entry,
+            api::queue::clear_queue,
+            api::queue::set_repeat_mode,
+            api::queue::set_shuffle,
+            api::queue::play_queue_entry,
+            api::queue::play_next_track,
+            api::queue::play_previous_track,
+            api::queue::resume_play_queue,
+            api::backup::create_backup,
+            api::backup::get_database_backups,
+            api::backup::restore_backup,
+            api::backup::check_database_integrity,
+            api::history::report_play,
+            api::history::get_play_history,
+            api::history::get_play_count,
+            api::history::get_play_threshold,
+            api::history::set_play_threshold,
         ])
+        .register_asynchronous_uri_scheme_protocol(
+            api::stream::STREAM_URI_SCHEME,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    responder.respond(api::stream::handle_stream_request(&app, request).await);
+                });
+            },
+        )
         .setup(|app| {
             tauri::async_runtime::block_on(async move {
                 // Setup API
@@ -69,9 +230,36 @@ pub fn run() {
                     .await
                     .expect("Failed to setup database");
 
+                // Back up the database (only the last few automatic backups are kept)
+                let backup = match backups_dir(app.handle()) {
+                    Ok(dir) => db.backup(&dir, true).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = backup {
+                    warn!(error = e.to_string(), "Unable to back up the database");
+                }
+
+                // Setup library watcher
+                let thumbnails_dir =
+                    thumbnails_dir(app.handle()).expect("Failed to resolve thumbnails directory");
+                let library_watcher =
+                    LibraryWatcher::start(app.handle().clone(), db.clone(), thumbnails_dir)
+                        .await
+                        .expect("Failed to start library watcher");
+                let library_watcher = Arc::new(Mutex::new(library_watcher));
+
+                // Setup player
+                let player = Arc::new(Mutex::new(start_player(app.handle().clone())));
+
                 // Setup app state
-                let db = Arc::new(Mutex::new(db));
-                let app_state = AppState { db, api_config };
+                let app_state = AppState {
+                    db,
+                    api_config,
+                    library_watcher,
+                    player,
+                    queue_user: Arc::new(Mutex::new(None)),
+                    playback_user: Arc::new(Mutex::new(None)),
+                };
                 app.manage(app_state);
 
                 // Setup API
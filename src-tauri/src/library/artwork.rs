@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageFormat;
+use ring::digest::{digest, SHA256};
+use tauri::{AppHandle, Manager};
+
+use crate::errors::SpotsError;
+
+/// The max width/height of a generated thumbnail.
+pub const THUMBNAIL_SIZE: u32 = 300;
+
+/// Names (without extension) of image files commonly used as album covers.
+const COVER_FILE_NAMES: [&str; 5] = ["cover", "folder", "front", "album", "albumart"];
+
+/// Extensions of the cover image files that are recognized.
+const COVER_FILE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// Gets the directory the thumbnails are stored in (next to the database in the app data dir).
+pub fn thumbnails_dir(app: &AppHandle) -> Result<PathBuf, SpotsError> {
+    let mut path = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| SpotsError::AppDataDirError(e.to_string()))?;
+    path.push("thumbnails");
+    Ok(path)
+}
+
+/// Gets the track's cover art: a cover image in the track's directory, or else the art
+/// `embedded` in the track.
+///
+/// The cover image wins because it covers the whole album, while embedded art can differ from
+/// track to track (or be a low resolution copy).
+pub fn cover_art(track_path: &Path, embedded: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    find_sibling_cover(track_path)
+        .and_then(|cover| std::fs::read(cover).ok())
+        .or(embedded)
+}
+
+/// Finds a cover image (e.g. `cover.jpg` or `folder.png`) in the same directory as the track.
+pub fn find_sibling_cover(track_path: &Path) -> Option<PathBuf> {
+    let dir = track_path.parent()?;
+    let mut covers: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && is_cover_file(path))
+        .collect();
+
+    // Prefer covers in the order of `COVER_FILE_NAMES`
+    covers.sort_by_key(|path| {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        COVER_FILE_NAMES.iter().position(|name| *name == stem)
+    });
+    covers.into_iter().next()
+}
+
+/// Writes a thumbnail of the image to the `thumbnails_dir`, returning the thumbnail's path.
+///
+/// Thumbnails are named after the SHA-256 hash of the original image, so identical artwork (e.g.
+/// the same cover embedded in every track of an album) is only stored once.
+pub fn write_thumbnail(thumbnails_dir: &Path, image_data: &[u8]) -> Result<PathBuf, SpotsError> {
+    let hash = digest(&SHA256, image_data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    let thumbnail_path = thumbnails_dir.join(format!("{}.jpg", hash));
+    if thumbnail_path.exists() {
+        return Ok(thumbnail_path);
+    }
+
+    std::fs::create_dir_all(thumbnails_dir)?;
+    let thumbnail_error = |error: String| SpotsError::ThumbnailError {
+        path: thumbnail_path.to_string_lossy().to_string(),
+        error,
+    };
+    image::load_from_memory(image_data)
+        .map_err(|e| thumbnail_error(e.to_string()))?
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .to_rgb8()
+        .save_with_format(&thumbnail_path, ImageFormat::Jpeg)
+        .map_err(|e| thumbnail_error(e.to_string()))?;
+
+    Ok(thumbnail_path)
+}
+
+/// Checks if the file is named like a cover image.
+fn is_cover_file(path: &Path) -> bool {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    COVER_FILE_NAMES.contains(&stem.as_str()) && COVER_FILE_EXTENSIONS.contains(&ext.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{GenericImageView, RgbImage};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spots-artwork-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Encodes a solid image as a PNG.
+    fn png(width: u32, height: u32, shade: u8) -> Vec<u8> {
+        let mut data = vec![];
+        RgbImage::from_pixel(width, height, image::Rgb([shade, shade, shade]))
+            .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_write_thumbnail() {
+        let dir = temp_dir();
+        let thumbnails_dir = dir.join("thumbnails");
+
+        // The same artwork is only stored once
+        let cover = png(600, 300, 0);
+        let first = write_thumbnail(&thumbnails_dir, &cover).unwrap();
+        let second = write_thumbnail(&thumbnails_dir, &cover).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(std::fs::read_dir(&thumbnails_dir).unwrap().count(), 1);
+        assert_eq!(
+            image::open(&first).unwrap().dimensions(),
+            (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2)
+        );
+
+        let other = write_thumbnail(&thumbnails_dir, &png(600, 300, 255)).unwrap();
+        assert_ne!(first, other);
+        assert_eq!(std::fs::read_dir(&thumbnails_dir).unwrap().count(), 2);
+
+        assert!(write_thumbnail(&thumbnails_dir, b"not an image").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_find_sibling_cover() {
+        let dir = temp_dir();
+        let track = dir.join("01 Song.mp3");
+        for file in ["01 Song.mp3", "notes.txt", "cover.gif"] {
+            std::fs::write(dir.join(file), b"").unwrap();
+        }
+        assert_eq!(find_sibling_cover(&track), None);
+
+        std::fs::write(dir.join("Front.PNG"), b"").unwrap();
+        assert_eq!(find_sibling_cover(&track), Some(dir.join("Front.PNG")));
+        std::fs::write(dir.join("folder.jpg"), b"").unwrap();
+        assert_eq!(find_sibling_cover(&track), Some(dir.join("folder.jpg")));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cover_art_prefers_sibling_cover() {
+        let dir = temp_dir();
+        let track = dir.join("01 Song.mp3");
+        let embedded = png(10, 10, 0);
+        assert_eq!(
+            cover_art(&track, Some(embedded.clone())),
+            Some(embedded.clone())
+        );
+
+        let sibling = png(10, 10, 255);
+        std::fs::write(dir.join("cover.png"), &sibling).unwrap();
+        assert_eq!(cover_art(&track, Some(embedded)), Some(sibling.clone()));
+        assert_eq!(cover_art(&track, None), Some(sibling));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use symphonia::core::{
+    formats::FormatOptions,
+    io::{BufReader, MediaSourceStream},
+    meta::{
+        MetadataBuilder, MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey,
+        Value,
+    },
+    probe::Hint,
+};
+use symphonia_metadata::id3v1;
+
+use crate::{database::library::NewTrack, errors::SpotsError};
+
+/// Size of an ID3v1 tag (always at the very end of the file).
+const ID3V1_TAG_SIZE: u64 = 128;
+
+/// Metadata read from the tags embedded in an audio file.
+///
+/// Supports ID3v1/ID3v2 (MP3), Vorbis comments (OGG/Opus/FLAC) and MP4/M4A atoms.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackMetadata {
+    /// Title of the track.
+    pub title: Option<String>,
+
+    /// Title of the album the track belongs to.
+    pub album: Option<String>,
+
+    /// The album's artist.
+    pub album_artist: Option<String>,
+
+    /// Names of the track's artists.
+    pub artists: Vec<String>,
+
+    /// Names of the track's genres.
+    pub genres: Vec<String>,
+
+    /// The track number of the track (in its album).
+    pub track_number: Option<i64>,
+
+    /// The year the track was released.
+    pub release_year: Option<i64>,
+
+    /// The duration of the track in seconds.
+    pub duration_secs: Option<i64>,
+
+    /// The embedded cover art (APIC frame, FLAC PICTURE block or `covr` atom), still encoded.
+    pub cover_art: Option<Vec<u8>>,
+}
+
+impl TrackMetadata {
+    /// Fills in the track with the metadata, overriding any values guessed from the file's
+    /// location.
+    pub fn apply_to(self, mut track: NewTrack) -> NewTrack {
+        if let Some(title) = self.title {
+            track.title = title;
+        }
+        if self.album.is_some() {
+            track.album = self.album;
+        }
+        if !self.artists.is_empty() {
+            track.artists = self.artists;
+        } else if let Some(album_artist) = &self.album_artist {
+            track.artists = vec![album_artist.clone()];
+        }
+        if self.album_artist.is_some() {
+            track.album_artist = self.album_artist;
+        }
+        if !self.genres.is_empty() {
+            track.genres = self.genres;
+        }
+        track.track_number = self.track_number.or(track.track_number);
+        track.release_year = self.release_year.or(track.release_year);
+        track.duration_secs = self.duration_secs.or(track.duration_secs);
+        track
+    }
+
+    /// Fills in any missing values using the tags in the metadata revision.
+    fn merge_revision(&mut self, revision: &MetadataRevision) {
+        let mut artists = vec![];
+        let mut genres = vec![];
+        for tag in revision.tags() {
+            let Some(key) = tag.std_key else {
+                continue;
+            };
+            let value = match &tag.value {
+                Value::String(s) => s.trim().to_string(),
+                Value::UnsignedInt(n) => n.to_string(),
+                Value::SignedInt(n) => n.to_string(),
+                _ => continue,
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            match key {
+                StandardTagKey::TrackTitle => {
+                    self.title.get_or_insert(value);
+                }
+                StandardTagKey::Album => {
+                    self.album.get_or_insert(value);
+                }
+                StandardTagKey::AlbumArtist => {
+                    self.album_artist.get_or_insert(value);
+                }
+                StandardTagKey::Artist => artists.extend(split_values(&value)),
+                StandardTagKey::Genre => genres.extend(
+                    split_values(&value)
+                        .into_iter()
+                        .map(|g| normalize_genre(&g)),
+                ),
+                StandardTagKey::TrackNumber if self.track_number.is_none() => {
+                    self.track_number = parse_track_number(&value);
+                }
+                StandardTagKey::Date
+                | StandardTagKey::ReleaseDate
+                | StandardTagKey::OriginalDate
+                    if self.release_year.is_none() =>
+                {
+                    self.release_year = parse_year(&value);
+                }
+                _ => {}
+            }
+        }
+
+        if self.artists.is_empty() {
+            self.artists = dedup(artists);
+        }
+        if self.genres.is_empty() {
+            self.genres = dedup(genres);
+        }
+
+        // Prefer the front cover, but use any other picture if there isn't one
+        if self.cover_art.is_none() {
+            let visuals = revision.visuals();
+            self.cover_art = visuals
+                .iter()
+                .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+                .or_else(|| visuals.first())
+                .map(|visual| visual.data.to_vec());
+        }
+    }
+}
+
+/// Reads the metadata embedded in the audio file.
+///
+/// Container tags (Vorbis comments, MP4 atoms, etc.) take precedence over ID3v2 tags, which in
+/// turn take precedence over an ID3v1 tag at the end of the file.
+pub fn read_metadata(path: &Path) -> Result<TrackMetadata, SpotsError> {
+    let metadata_error = |error: String| SpotsError::MetadataError {
+        file: path.to_string_lossy().to_string(),
+        error,
+    };
+
+    let file = File::open(path)?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| metadata_error(e.to_string()))?;
+
+    let mut metadata = TrackMetadata::default();
+
+    // Container tags
+    if let Some(revision) = probed.format.metadata().skip_to_latest() {
+        metadata.merge_revision(revision);
+    }
+
+    // Tags found before the container (ID3v2)
+    if let Some(mut probed_metadata) = probed.metadata.get() {
+        if let Some(revision) = probed_metadata.skip_to_latest() {
+            metadata.merge_revision(revision);
+        }
+    }
+
+    // ID3v1
+    if let Some(revision) = read_id3v1(path)? {
+        metadata.merge_revision(&revision);
+    }
+
+    // Duration
+    if let Some(track) = probed.format.default_track() {
+        let params = &track.codec_params;
+        metadata.duration_secs = match (params.time_base, params.n_frames) {
+            (Some(time_base), Some(n_frames)) => Some(time_base.calc_time(n_frames).seconds as i64),
+            (None, Some(n_frames)) => params
+                .sample_rate
+                .map(|rate| (n_frames / u64::from(rate)) as i64),
+            _ => None,
+        };
+    }
+
+    Ok(metadata)
+}
+
+/// Reads the ID3v1 tag at the end of the file, if there is one.
+fn read_id3v1(path: &Path) -> Result<Option<MetadataRevision>, SpotsError> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() < ID3V1_TAG_SIZE {
+        return Ok(None);
+    }
+
+    let mut buf = [0u8; ID3V1_TAG_SIZE as usize];
+    file.seek(SeekFrom::End(-(ID3V1_TAG_SIZE as i64)))?;
+    file.read_exact(&mut buf)?;
+
+    let mut builder = MetadataBuilder::new();
+    match id3v1::read_id3v1(&mut BufReader::new(&buf), &mut builder) {
+        Ok(()) => Ok(Some(builder.metadata())),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Splits a tag value that holds multiple values (e.g. `"Artist A; Artist B"`).
+fn split_values(value: &str) -> Vec<String> {
+    value
+        .split(['\0', ';'])
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+/// Converts numeric ID3 genre references (e.g. `"(17)"` or `"17"`) to the genre's name.
+fn normalize_genre(genre: &str) -> String {
+    let index = genre
+        .strip_prefix('(')
+        .and_then(|g| g.strip_suffix(')'))
+        .unwrap_or(genre);
+    index
+        .parse::<u8>()
+        .ok()
+        .and_then(id3v1::util::genre_name)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| genre.to_string())
+}
+
+/// Parses a track number, which may be in the `"<number>/<total>"` form.
+fn parse_track_number(value: &str) -> Option<i64> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Parses the year out of a date (e.g. `"2001"` or `"2001-05-14"`).
+fn parse_year(value: &str) -> Option<i64> {
+    let year = value.trim().get(0..4)?;
+    year.parse().ok().filter(|year| *year > 0)
+}
+
+/// Removes duplicate values while keeping their order.
+fn dedup(values: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(values.len());
+    for value in values {
+        if !unique.iter().any(|v| v.eq_ignore_ascii_case(&value)) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tag_values() {
+        assert_eq!(parse_track_number("3/12"), Some(3));
+        assert_eq!(parse_track_number("07"), Some(7));
+        assert_eq!(parse_year("2001-05-14"), Some(2001));
+        assert_eq!(parse_year("abc"), None);
+        assert_eq!(normalize_genre("(17)"), "Rock");
+        assert_eq!(normalize_genre("Jazz"), "Jazz");
+        assert_eq!(
+            split_values("Daft Punk; Pharrell Williams"),
+            vec!["Daft Punk", "Pharrell Williams"]
+        );
+    }
+
+    #[test]
+    fn test_apply_to_track() {
+        let metadata = TrackMetadata {
+            title: Some(String::from("Get Lucky")),
+            album_artist: Some(String::from("Daft Punk")),
+            track_number: Some(8),
+            ..Default::default()
+        };
+        let track = metadata.apply_to(NewTrack {
+            title: String::from("08 get_lucky"),
+            album: Some(String::from("Random Access Memories")),
+            ..Default::default()
+        });
+        assert_eq!(track.title, "Get Lucky");
+        assert_eq!(track.album.as_deref(), Some("Random Access Memories"));
+        assert_eq!(track.artists, vec!["Daft Punk"]);
+        assert_eq!(track.album_artist.as_deref(), Some("Daft Punk"));
+        assert_eq!(track.track_number, Some(8));
+    }
+}
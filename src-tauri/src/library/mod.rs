@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::utils::{ApiResponse, ApiResponseStatus, ResponseChannel},
+    database::{
+        client::DatabaseClient,
+        library::{LibraryExt, NewTrack},
+        send_response, DBResult,
+    },
+};
+
+pub mod artwork;
+pub mod metadata;
+pub mod playlist_files;
+pub mod scanner;
+pub mod watcher;
+
+/// The progress of a library import.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportProgress {
+    /// The total number of audio files found.
+    pub total: usize,
+
+    /// The number of files processed so far.
+    pub processed: usize,
+
+    /// The number of tracks that were imported.
+    pub imported: usize,
+
+    /// The number of files that could not be imported.
+    pub failed: usize,
+
+    /// The file that was just processed.
+    pub current_file: Option<String>,
+}
+
+/// Imports all of the audio files in the `root` directory into the music library.
+///
+/// Thumbnails of the tracks' cover art are written to the `thumbnails_dir`.
+///
+/// # Note
+/// The import progress is streamed to the `channel` after each file.
+pub async fn import_directory(
+    db: &DatabaseClient,
+    user_id: Uuid,
+    root: PathBuf,
+    thumbnails_dir: PathBuf,
+    channel: ResponseChannel<ImportProgress>,
+) -> DBResult<ImportProgress> {
+    info!(
+        root = root.to_string_lossy().to_string(),
+        "Importing music library"
+    );
+
+    // Find the audio files
+    let scan_root = root.clone();
+    let files = tauri::async_runtime::spawn_blocking(move || scanner::scan_directory(&scan_root))
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))??;
+
+    // Signals the start of the import
+    let mut progress = ImportProgress {
+        total: files.len(),
+        ..Default::default()
+    };
+    send_response(
+        &channel,
+        ApiResponse {
+            status: ApiResponseStatus::Started,
+            value: Some(progress.clone()),
+        },
+    )?;
+
+    // Import each file
+    for path in files {
+        let (track_root, track_path, track_thumbnails_dir) =
+            (root.clone(), path.clone(), thumbnails_dir.clone());
+        let track = tauri::async_runtime::spawn_blocking(move || {
+            read_track(&track_root, &track_path, &track_thumbnails_dir)
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+        match db.upsert_track(user_id, track).await {
+            Ok(_) => progress.imported += 1,
+            Err(e) => {
+                warn!(
+                    file = path.to_string_lossy().to_string(),
+                    error = e.to_string(),
+                    "Unable to import track"
+                );
+                progress.failed += 1;
+            }
+        }
+        progress.processed += 1;
+        progress.current_file = Some(path.to_string_lossy().to_string());
+        send_response(&channel, ApiResponse::pending(Some(progress.clone())))?;
+    }
+
+    // Signals the end of the import
+    send_response(
+        &channel,
+        ApiResponse {
+            status: ApiResponseStatus::Completed,
+            value: Some(progress.clone()),
+        },
+    )?;
+    info!(
+        imported = progress.imported,
+        failed = progress.failed,
+        "Music library imported"
+    );
+
+    Ok(progress)
+}
+
+/// Reads the track at `path`, using its embedded tags when they can be read and falling back to
+/// its location in the library otherwise.
+///
+/// The track's thumbnail is generated from a cover image in the same directory (e.g.
+/// `cover.jpg`), or from its embedded cover art.
+pub fn read_track(root: &Path, path: &Path, thumbnails_dir: &Path) -> NewTrack {
+    let mut track = scanner::track_from_path(root, path);
+    let mut cover_art = None;
+    match metadata::read_metadata(path) {
+        Ok(mut metadata) => {
+            cover_art = metadata.cover_art.take();
+            track = metadata.apply_to(track);
+        }
+        Err(e) => warn!(
+            file = path.to_string_lossy().to_string(),
+            error = e.to_string(),
+            "Unable to read track metadata"
+        ),
+    }
+
+    // Thumbnail
+    if let Some(cover_art) = artwork::cover_art(path, cover_art) {
+        match artwork::write_thumbnail(thumbnails_dir, &cover_art) {
+            Ok(thumbnail_path) => {
+                track.thumbnail_path = thumbnail_path.to_string_lossy().to_string()
+            }
+            Err(e) => warn!(
+                file = path.to_string_lossy().to_string(),
+                error = e.to_string(),
+                "Unable to create track thumbnail"
+            ),
+        }
+    }
+
+    track
+}
@@ -0,0 +1,578 @@
+use std::{
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use quick_xml::{
+    escape::{escape, resolve_predefined_entity},
+    events::Event,
+    Reader,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::SpotsError;
+
+/// The characters that are percent-encoded in the paths of `file://` URLs.
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// The playlist file formats that can be imported and exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaylistFormat {
+    /// Extended M3U (`.m3u` or `.m3u8`), which is always written as UTF-8. `.m3u` files that
+    /// aren't valid UTF-8 are read as Latin-1.
+    M3u,
+    Pls,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    /// Gets the format of the playlist file from its extension.
+    pub fn from_path(path: &Path) -> Result<Self, SpotsError> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Ok(Self::M3u),
+            "pls" => Ok(Self::Pls),
+            "xspf" => Ok(Self::Xspf),
+            _ => Err(SpotsError::UnsupportedPlaylistFormat(
+                path.to_string_lossy().to_string(),
+            )),
+        }
+    }
+}
+
+/// The contents of a playlist file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistFile {
+    /// The playlist's title, if the file has one.
+    pub title: Option<String>,
+
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// A track in a playlist file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistEntry {
+    /// The track's path, which is relative to the playlist file unless it is absolute.
+    pub path: Option<PathBuf>,
+
+    pub title: Option<String>,
+
+    pub artist: Option<String>,
+
+    pub duration_secs: Option<i64>,
+}
+
+/// Decodes the contents of the playlist file at `path`.
+///
+/// Playlist files are UTF-8, except for `.m3u` files which were traditionally written in the
+/// system's legacy encoding, so they are read as Latin-1 when they aren't valid UTF-8.
+pub fn decode_playlist(path: &Path, contents: Vec<u8>) -> String {
+    let is_m3u = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("m3u"));
+    match String::from_utf8(contents) {
+        Ok(contents) => contents,
+        Err(e) if is_m3u => e.as_bytes().iter().map(|&byte| char::from(byte)).collect(),
+        Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    }
+}
+
+/// Parses the contents of a playlist file.
+pub fn parse_playlist(format: PlaylistFormat, contents: &str) -> Result<PlaylistFile, SpotsError> {
+    match format {
+        PlaylistFormat::M3u => Ok(parse_m3u(contents)),
+        PlaylistFormat::Pls => Ok(parse_pls(contents)),
+        PlaylistFormat::Xspf => parse_xspf(contents),
+    }
+}
+
+/// Writes the playlist in the format.
+pub fn write_playlist(format: PlaylistFormat, playlist: &PlaylistFile) -> String {
+    match format {
+        PlaylistFormat::M3u => write_m3u(playlist),
+        PlaylistFormat::Pls => write_pls(playlist),
+        PlaylistFormat::Xspf => write_xspf(playlist),
+    }
+}
+
+/// Gets the path of `path` relative to the `dir` (e.g. `../Music/song.mp3`), or `path` itself if
+/// they are only in the same root (e.g. `/` or a drive).
+pub fn relative_path(dir: &Path, path: &Path) -> PathBuf {
+    let (dir, path) = (normalize_path(dir), normalize_path(path));
+    let common = dir
+        .components()
+        .zip(path.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let shares_dir = dir
+        .components()
+        .take(common)
+        .any(|component| matches!(component, Component::Normal(_)));
+    if !shares_dir {
+        return path;
+    }
+
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(path.components().skip(common));
+    relative
+}
+
+/// Removes the `.` and `..` components of the path without touching the file system.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Gets the path of an entry's location, which is a path or a `file://` URL.
+///
+/// Returns `None` for other URLs (e.g. streams), which can't be in the library.
+fn location_path(location: &str, is_url: bool) -> Option<PathBuf> {
+    let location = location.trim();
+    if location.is_empty() {
+        return None;
+    }
+    let path = if let Some(path) = location.strip_prefix("file://") {
+        let path = path.strip_prefix("localhost").unwrap_or(path);
+        let path = percent_decode_str(path).decode_utf8_lossy();
+        // `file:///C:/Music` is `C:/Music` on Windows
+        if cfg!(windows) && path.as_bytes().get(2) == Some(&b':') {
+            path[1..].to_string()
+        } else {
+            path.to_string()
+        }
+    } else if location.contains("://") {
+        return None;
+    } else if is_url {
+        percent_decode_str(location).decode_utf8_lossy().to_string()
+    } else {
+        location.to_string()
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Gets the URL of the path for XSPF (a `file://` URL if the path is absolute).
+fn path_url(path: &Path) -> String {
+    let url_path = path.to_string_lossy().replace('\\', "/");
+    let encoded = utf8_percent_encode(&url_path, PATH_ENCODE_SET).to_string();
+    if !path.is_absolute() {
+        encoded
+    } else if url_path.starts_with('/') {
+        format!("file://{encoded}")
+    } else {
+        // `C:/Music` is `file:///C:/Music`
+        format!("file:///{encoded}")
+    }
+}
+
+/// Splits the `Artist - Title` names that M3U and PLS use.
+fn split_name(name: &str) -> (Option<String>, Option<String>) {
+    let name = name.trim();
+    if name.is_empty() {
+        return (None, None);
+    }
+    match name.split_once(" - ") {
+        Some((artist, title)) => (
+            Some(artist.trim().to_string()),
+            Some(title.trim().to_string()),
+        ),
+        None => (None, Some(name.to_string())),
+    }
+}
+
+/// Joins the entry's artist and title into an `Artist - Title` name.
+fn entry_name(entry: &PlaylistEntry) -> Option<String> {
+    match (&entry.artist, &entry.title) {
+        (Some(artist), Some(title)) => Some(format!("{artist} - {title}")),
+        (None, Some(title)) => Some(title.clone()),
+        _ => None,
+    }
+}
+
+/// Parses a duration in seconds, which is `-1` when it is unknown.
+fn parse_duration(duration: &str) -> Option<i64> {
+    duration.trim().parse().ok().filter(|secs: &i64| *secs >= 0)
+}
+
+/// Parses an (extended) M3U playlist.
+fn parse_m3u(contents: &str) -> PlaylistFile {
+    let mut playlist = PlaylistFile::default();
+    let mut entry = PlaylistEntry::default();
+    for line in contents.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            // `#EXTINF:<duration> <attributes>,<artist> - <title>`
+            let (duration, name) = info.split_once(',').unwrap_or((info, ""));
+            let duration = duration.split_whitespace().next().unwrap_or_default();
+            (entry.artist, entry.title) = split_name(name);
+            entry.duration_secs = parse_duration(duration);
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            playlist.title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entry.path = location_path(line, false);
+            playlist.entries.push(std::mem::take(&mut entry));
+        }
+    }
+    playlist
+}
+
+/// Writes an extended M3U playlist.
+fn write_m3u(playlist: &PlaylistFile) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    if let Some(title) = &playlist.title {
+        let _ = writeln!(m3u, "#PLAYLIST:{title}");
+    }
+    for entry in &playlist.entries {
+        let Some(path) = &entry.path else {
+            continue;
+        };
+        if let Some(name) = entry_name(entry) {
+            let _ = writeln!(m3u, "#EXTINF:{},{name}", entry.duration_secs.unwrap_or(-1));
+        }
+        let _ = writeln!(m3u, "{}", path.to_string_lossy());
+    }
+    m3u
+}
+
+/// Parses a PLS playlist.
+fn parse_pls(contents: &str) -> PlaylistFile {
+    let mut entries: Vec<(usize, PlaylistEntry)> = vec![];
+    for line in contents.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let field = key.trim_end_matches(|c: char| c.is_ascii_digit());
+        let Ok(number) = key[field.len()..].parse::<usize>() else {
+            continue;
+        };
+        let index = match entries.iter().position(|(n, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                entries.push((number, PlaylistEntry::default()));
+                entries.len() - 1
+            }
+        };
+        let entry = &mut entries[index].1;
+        match field {
+            "file" => entry.path = location_path(value, false),
+            "title" => (entry.artist, entry.title) = split_name(value),
+            "length" => entry.duration_secs = parse_duration(value),
+            _ => {}
+        }
+    }
+
+    entries.sort_by_key(|(number, _)| *number);
+    PlaylistFile {
+        title: None,
+        entries: entries
+            .into_iter()
+            .map(|(_, entry)| entry)
+            .filter(|entry| entry.path.is_some())
+            .collect(),
+    }
+}
+
+/// Writes a PLS playlist.
+fn write_pls(playlist: &PlaylistFile) -> String {
+    let mut pls = String::from("[playlist]\n");
+    let entries = playlist.entries.iter().filter(|entry| entry.path.is_some());
+    let mut count = 0;
+    for (i, entry) in entries.enumerate() {
+        let number = i + 1;
+        if let Some(path) = &entry.path {
+            let _ = writeln!(pls, "File{number}={}", path.to_string_lossy());
+        }
+        if let Some(name) = entry_name(entry) {
+            let _ = writeln!(pls, "Title{number}={name}");
+        }
+        let _ = writeln!(pls, "Length{number}={}", entry.duration_secs.unwrap_or(-1));
+        count = number;
+    }
+    let _ = write!(pls, "NumberOfEntries={count}\nVersion=2\n");
+    pls
+}
+
+/// Parses an XSPF playlist.
+fn parse_xspf(contents: &str) -> Result<PlaylistFile, SpotsError> {
+    let parse_error = |e: &dyn std::fmt::Display| SpotsError::PlaylistFileParseError(e.to_string());
+
+    let mut reader = Reader::from_str(contents);
+    let mut playlist = PlaylistFile::default();
+    let mut elements: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut entry = PlaylistEntry::default();
+    loop {
+        match reader.read_event().map_err(|e| parse_error(&e))? {
+            Event::Start(element) => {
+                let name = element.local_name();
+                elements.push(String::from_utf8_lossy(name.as_ref()).to_string());
+                text.clear();
+            }
+            Event::Text(content) => text.push_str(&content.decode().map_err(|e| parse_error(&e))?),
+            Event::CData(content) => text.push_str(&content.decode().map_err(|e| parse_error(&e))?),
+            Event::GeneralRef(reference) => {
+                if let Some(c) = reference.resolve_char_ref().map_err(|e| parse_error(&e))? {
+                    text.push(c);
+                } else {
+                    let name = reference.decode().map_err(|e| parse_error(&e))?;
+                    text.push_str(resolve_predefined_entity(&name).unwrap_or_default());
+                }
+            }
+            Event::End(_) => {
+                let name = elements.pop().unwrap_or_default();
+                let value = std::mem::take(&mut text).trim().to_string();
+                match (elements.last().map(String::as_str), name.as_str()) {
+                    (Some("playlist"), "title") => playlist.title = Some(value),
+                    (Some("track"), "location") if entry.path.is_none() => {
+                        entry.path = location_path(&value, true)
+                    }
+                    (Some("track"), "title") => entry.title = Some(value),
+                    (Some("track"), "creator") => entry.artist = Some(value),
+                    (Some("track"), "duration") => {
+                        entry.duration_secs = value.parse::<i64>().ok().map(|ms| ms / 1000)
+                    }
+                    (Some("trackList"), "track") => {
+                        playlist.entries.push(std::mem::take(&mut entry))
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(playlist)
+}
+
+/// Writes an XSPF playlist.
+fn write_xspf(playlist: &PlaylistFile) -> String {
+    let mut xspf = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if let Some(title) = &playlist.title {
+        let _ = writeln!(xspf, "  <title>{}</title>", escape(title));
+    }
+    xspf.push_str("  <trackList>\n");
+    for entry in &playlist.entries {
+        xspf.push_str("    <track>\n");
+        if let Some(path) = &entry.path {
+            let _ = writeln!(
+                xspf,
+                "      <location>{}</location>",
+                escape(path_url(path))
+            );
+        }
+        if let Some(title) = &entry.title {
+            let _ = writeln!(xspf, "      <title>{}</title>", escape(title));
+        }
+        if let Some(artist) = &entry.artist {
+            let _ = writeln!(xspf, "      <creator>{}</creator>", escape(artist));
+        }
+        if let Some(duration_secs) = entry.duration_secs {
+            let _ = writeln!(xspf, "      <duration>{}</duration>", duration_secs * 1000);
+        }
+        xspf.push_str("    </track>\n");
+    }
+    xspf.push_str("  </trackList>\n</playlist>\n");
+    xspf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, artist: &str, title: &str, duration_secs: i64) -> PlaylistEntry {
+        PlaylistEntry {
+            path: Some(PathBuf::from(path)),
+            title: Some(title.to_string()),
+            artist: Some(artist.to_string()),
+            duration_secs: Some(duration_secs),
+        }
+    }
+
+    #[test]
+    fn test_parse_m3u() {
+        let m3u = "\u{feff}#EXTM3U\n\
+                   #PLAYLIST:Road Trip\n\
+                   #EXTINF:354 tvg-id=\"1\",Queen - Bohemian Rhapsody\n\
+                   /music/Queen/Bohemian Rhapsody.mp3\n\
+                   \n\
+                   # A comment\n\
+                   ../Other/untitled.flac\n\
+                   #EXTINF:-1,Radio\n\
+                   http://radio.example.com/stream\n";
+        let playlist = parse_playlist(PlaylistFormat::M3u, m3u).unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Road Trip"));
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry(
+                    "/music/Queen/Bohemian Rhapsody.mp3",
+                    "Queen",
+                    "Bohemian Rhapsody",
+                    354
+                ),
+                PlaylistEntry {
+                    path: Some(PathBuf::from("../Other/untitled.flac")),
+                    ..Default::default()
+                },
+                PlaylistEntry {
+                    title: Some(String::from("Radio")),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_playlist() {
+        let latin1 = b"#EXTINF:100,Beyonc\xe9 - Halo\n".to_vec();
+        assert_eq!(
+            decode_playlist(Path::new("a.M3U"), latin1.clone()),
+            "#EXTINF:100,Beyonc\u{e9} - Halo\n"
+        );
+        assert_eq!(
+            decode_playlist(Path::new("a.m3u8"), latin1),
+            "#EXTINF:100,Beyonc\u{fffd} - Halo\n"
+        );
+
+        let utf8 = "#EXTINF:100,Beyonc\u{e9} - Halo\n".as_bytes().to_vec();
+        for path in ["a.m3u", "a.m3u8", "a.pls"] {
+            assert_eq!(
+                decode_playlist(Path::new(path), utf8.clone()),
+                "#EXTINF:100,Beyonc\u{e9} - Halo\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_pls() {
+        let pls = "[playlist]\n\
+                   File2=b.mp3\n\
+                   File1=a.mp3\n\
+                   Title1=Artist - A\n\
+                   Length1=61\n\
+                   NumberOfEntries=2\n\
+                   Version=2\n";
+        let playlist = parse_playlist(PlaylistFormat::Pls, pls).unwrap();
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry("a.mp3", "Artist", "A", 61),
+                PlaylistEntry {
+                    path: Some(PathBuf::from("b.mp3")),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_xspf() {
+        let xspf = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Rock &amp; Roll</title>
+              <trackList>
+                <track>
+                  <location>file:///music/AC%2FDC/Back%20in%20Black.mp3</location>
+                  <title>Back in Black</title>
+                  <creator>AC/DC</creator>
+                  <album>Back in Black</album>
+                  <duration>255000</duration>
+                </track>
+                <track>
+                  <location>relative/caf%C3%A9.mp3</location>
+                </track>
+              </trackList>
+            </playlist>"#;
+        let playlist = parse_playlist(PlaylistFormat::Xspf, xspf).unwrap();
+        assert_eq!(playlist.title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(
+            playlist.entries,
+            vec![
+                entry(
+                    "/music/AC/DC/Back in Black.mp3",
+                    "AC/DC",
+                    "Back in Black",
+                    255
+                ),
+                PlaylistEntry {
+                    path: Some(PathBuf::from("relative/café.mp3")),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        assert!(parse_playlist(PlaylistFormat::Xspf, "<playlist><title></playlist>").is_err());
+    }
+
+    #[test]
+    fn test_write_and_parse() {
+        let playlist = PlaylistFile {
+            title: Some(String::from("Mix <1>")),
+            entries: vec![
+                entry("/music/Song #1.mp3", "Artist", "Song #1", 200),
+                entry("../relative/café.flac", "Other", "Café", 100),
+            ],
+        };
+        for format in [
+            PlaylistFormat::M3u,
+            PlaylistFormat::Pls,
+            PlaylistFormat::Xspf,
+        ] {
+            let written = write_playlist(format, &playlist);
+            let parsed = parse_playlist(format, &written).unwrap();
+            assert_eq!(parsed.entries, playlist.entries, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(
+                Path::new("/music/playlists"),
+                Path::new("/music/Artist/song.mp3")
+            ),
+            PathBuf::from("../Artist/song.mp3")
+        );
+        assert_eq!(
+            relative_path(Path::new("/music"), Path::new("/music/./Artist/song.mp3")),
+            PathBuf::from("Artist/song.mp3")
+        );
+        assert_eq!(
+            normalize_path(Path::new("/music/playlists/../Artist/song.mp3")),
+            PathBuf::from("/music/Artist/song.mp3")
+        );
+    }
+}
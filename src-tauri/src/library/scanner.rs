@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use tracing::warn;
+use walkdir::WalkDir;
+
+use crate::{database::library::NewTrack, errors::SpotsError};
+
+/// File extensions of the audio files that can be imported.
+pub const AUDIO_EXTENSIONS: [&str; 11] = [
+    "mp3", "flac", "ogg", "oga", "opus", "m4a", "mp4", "aac", "wav", "aif", "aiff",
+];
+
+/// Checks if the file at the given path is a supported audio file.
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|supported| supported.eq_ignore_ascii_case(ext))
+        })
+        .unwrap_or(false)
+}
+
+/// Recursively finds all of the audio files in the given directory.
+///
+/// Hidden files and directories are skipped, and unreadable entries are logged and ignored.
+pub fn scan_directory(root: &Path) -> Result<Vec<PathBuf>, SpotsError> {
+    if !root.is_dir() {
+        return Err(SpotsError::InvalidLibraryDirectory(
+            root.to_string_lossy().to_string(),
+        ));
+    }
+
+    let mut audio_files = vec![];
+    let entries = WalkDir::new(root)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()));
+    for entry in entries {
+        match entry {
+            Ok(entry) => {
+                if entry.file_type().is_file() && is_audio_file(entry.path()) {
+                    audio_files.push(entry.into_path());
+                }
+            }
+            Err(e) => warn!(error = e.to_string(), "Unable to read library entry"),
+        }
+    }
+
+    Ok(audio_files)
+}
+
+/// Builds a [NewTrack] from the file's location in the library.
+///
+/// The title is the file name, and the album and artist are guessed from an
+/// `<artist>/<album>/<track>` folder layout relative to `root`. The file's size and
+/// modification time are read from its metadata.
+pub fn track_from_path(root: &Path, path: &Path) -> NewTrack {
+    let title = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let folders: Vec<String> = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let album = folders.last().cloned();
+    let artists = if folders.len() >= 2 {
+        vec![folders[folders.len() - 2].clone()]
+    } else {
+        vec![]
+    };
+
+    let metadata = path.metadata().ok();
+    let file_size = metadata.as_ref().map(|metadata| metadata.len() as i64);
+    let file_modified_at = metadata
+        .and_then(|metadata| metadata.modified().ok())
+        .map(|modified| DateTime::<Utc>::from(modified).naive_utc().to_string());
+
+    NewTrack {
+        file_path: path.to_string_lossy().to_string(),
+        title,
+        album,
+        artists,
+        file_size,
+        file_modified_at,
+        ..Default::default()
+    }
+}
+
+/// Checks if the file or directory is hidden (starts with a `.`).
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with('.'))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_scan_directory() {
+        let root = std::env::temp_dir().join(format!("spots-scanner-{}", Uuid::new_v4()));
+        for file in [
+            "Artist/Album/01 Song.mp3",
+            "Artist/Album/02 Song.FLAC",
+            "Artist/Album/cover.jpg",
+            "Artist/.hidden/song.mp3",
+            ".song.mp3",
+        ] {
+            let path = root.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"").unwrap();
+        }
+
+        assert_eq!(
+            scan_directory(&root).unwrap(),
+            vec![
+                root.join("Artist/Album/01 Song.mp3"),
+                root.join("Artist/Album/02 Song.FLAC"),
+            ]
+        );
+        assert!(scan_directory(&root.join("missing")).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_track_from_path() {
+        let root = Path::new("/music");
+        let track = track_from_path(root, Path::new("/music/Queen/Jazz/Mustapha.mp3"));
+        assert_eq!(track.title, "Mustapha");
+        assert_eq!(track.album.as_deref(), Some("Jazz"));
+        assert_eq!(track.artists, vec!["Queen"]);
+
+        let track = track_from_path(root, Path::new("/music/Loose.mp3"));
+        assert_eq!(track.title, "Loose");
+        assert_eq!(track.album, None);
+        assert!(track.artists.is_empty());
+    }
+}
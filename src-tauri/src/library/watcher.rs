@@ -0,0 +1,315 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{
+        event::{ModifyKind, RenameMode},
+        EventKind, RecommendedWatcher, RecursiveMode,
+    },
+    DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache,
+};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    database::{
+        client::DatabaseClient, library::LibraryExt, models::music_library::LibraryFolder, DBResult,
+    },
+    errors::SpotsError,
+    library::{read_track, scanner},
+};
+
+/// The event emitted to the frontend after the library was synced with changes on disk.
+pub const LIBRARY_CHANGED_EVENT: &str = "library-changed";
+
+/// How long file events have to settle before they are synced to the DB.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The payload of the [LIBRARY_CHANGED_EVENT].
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryChanged {
+    /// Tracks that were added or whose files were modified.
+    pub updated_tracks: Vec<Uuid>,
+
+    /// Tracks whose files were moved or renamed.
+    pub moved_tracks: Vec<Uuid>,
+
+    /// Tracks whose files were deleted.
+    pub missing_tracks: Vec<Uuid>,
+}
+
+impl LibraryChanged {
+    fn is_empty(&self) -> bool {
+        self.updated_tracks.is_empty()
+            && self.moved_tracks.is_empty()
+            && self.missing_tracks.is_empty()
+    }
+}
+
+/// Watches the library folders, keeping the `tracks` in the DB in sync with the files on disk.
+pub struct LibraryWatcher {
+    debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+impl LibraryWatcher {
+    /// Starts watching all of the library folders in the DB.
+    ///
+    /// Thumbnails of new tracks are written to the `thumbnails_dir`.
+    pub async fn start(
+        app: AppHandle,
+        db: DatabaseClient,
+        thumbnails_dir: PathBuf,
+    ) -> Result<Self, SpotsError> {
+        // Debounced events are sent to the sync task
+        let (tx, mut rx) = mpsc::unbounded_channel::<Vec<DebouncedEvent>>();
+        let debouncer = new_debouncer(
+            DEBOUNCE_TIMEOUT,
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    let _ = tx.send(events);
+                }
+                Err(errors) => errors
+                    .iter()
+                    .for_each(|e| warn!(error = e.to_string(), "Library watcher error")),
+            },
+        )
+        .map_err(|e| SpotsError::WatcherError(e.to_string()))?;
+
+        let mut watcher = Self { debouncer };
+        let folders = db.get_library_folders().await?;
+        for folder in &folders {
+            if let Err(e) = watcher.watch(Path::new(&folder.path)) {
+                warn!(
+                    folder = folder.path,
+                    error = e.to_string(),
+                    "Unable to watch library folder"
+                );
+            }
+        }
+
+        // Sync the events as they come in
+        tauri::async_runtime::spawn(async move {
+            while let Some(events) = rx.recv().await {
+                match sync_events(&db, &thumbnails_dir, events).await {
+                    Ok(changes) if !changes.is_empty() => {
+                        info!(
+                            updated = changes.updated_tracks.len(),
+                            moved = changes.moved_tracks.len(),
+                            missing = changes.missing_tracks.len(),
+                            "Library synced"
+                        );
+                        if let Err(e) = app.emit(LIBRARY_CHANGED_EVENT, changes) {
+                            error!(error = e.to_string(), "Unable to emit library change");
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!(error = e.to_string(), "Unable to sync library changes"),
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+
+    /// Starts watching the folder (recursively).
+    pub fn watch(&mut self, folder: &Path) -> Result<(), SpotsError> {
+        self.debouncer
+            .watch(folder, RecursiveMode::Recursive)
+            .map_err(|e| SpotsError::WatcherError(e.to_string()))
+    }
+}
+
+/// Applies the file events to the tracks in the DB.
+///
+/// An event that can't be applied is logged and skipped, so it doesn't hold up the rest.
+async fn sync_events(
+    db: &DatabaseClient,
+    thumbnails_dir: &Path,
+    events: Vec<DebouncedEvent>,
+) -> DBResult<LibraryChanged> {
+    let folders = db.get_library_folders().await?;
+    let mut changes = LibraryChanged::default();
+
+    for event in events {
+        if let Err(e) = sync_event(db, &folders, thumbnails_dir, &event, &mut changes).await {
+            warn!(
+                paths = ?event.paths,
+                error = e.to_string(),
+                "Unable to sync library change"
+            );
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Applies a single file event to the tracks in the DB.
+async fn sync_event(
+    db: &DatabaseClient,
+    folders: &[LibraryFolder],
+    thumbnails_dir: &Path,
+    event: &DebouncedEvent,
+    changes: &mut LibraryChanged,
+) -> DBResult<()> {
+    match (event.kind, event.paths.as_slice()) {
+        (EventKind::Modify(ModifyKind::Name(RenameMode::Both)), [from, to]) => {
+            if !is_in_library(folders, to) {
+                // Moved out of the library
+                let missing = db.mark_tracks_missing(&from.to_string_lossy()).await?;
+                changes.missing_tracks.extend(missing);
+                return Ok(());
+            }
+
+            let moved = db
+                .move_tracks(&from.to_string_lossy(), &to.to_string_lossy())
+                .await?;
+            if moved.is_empty() {
+                // Renamed from a file that wasn't in the library (e.g. a temp file)
+                upsert_path(db, folders, thumbnails_dir, to, changes).await?;
+            }
+            changes.moved_tracks.extend(moved);
+        }
+        (EventKind::Modify(ModifyKind::Name(RenameMode::From)), paths)
+        | (EventKind::Remove(_), paths) => {
+            for path in paths {
+                let missing = db.mark_tracks_missing(&path.to_string_lossy()).await?;
+                changes.missing_tracks.extend(missing);
+            }
+        }
+        (EventKind::Create(_), paths) | (EventKind::Modify(ModifyKind::Name(_)), paths) => {
+            for path in paths {
+                if path.exists() {
+                    upsert_path(db, folders, thumbnails_dir, path, changes).await?;
+                } else {
+                    let missing = db.mark_tracks_missing(&path.to_string_lossy()).await?;
+                    changes.missing_tracks.extend(missing);
+                }
+            }
+        }
+        (EventKind::Modify(_), paths) => {
+            for path in paths.iter().filter(|path| path.is_file()) {
+                upsert_path(db, folders, thumbnails_dir, path, changes).await?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Checks if the path is in one of the library folders.
+fn is_in_library(folders: &[LibraryFolder], path: &Path) -> bool {
+    folders
+        .iter()
+        .any(|folder| path.starts_with(Path::new(&folder.path)))
+}
+
+/// Imports the audio file at `path`, or all of the audio files in it if it is a directory.
+async fn upsert_path(
+    db: &DatabaseClient,
+    folders: &[LibraryFolder],
+    thumbnails_dir: &Path,
+    path: &Path,
+    changes: &mut LibraryChanged,
+) -> DBResult<()> {
+    let Some(folder) = folders
+        .iter()
+        .find(|folder| path.starts_with(Path::new(&folder.path)))
+    else {
+        return Ok(());
+    };
+    let Some(user_id) = folder.user_id else {
+        return Ok(());
+    };
+
+    let files = if path.is_dir() {
+        scanner::scan_directory(path)?
+    } else if path.is_file() && scanner::is_audio_file(path) {
+        vec![path.to_path_buf()]
+    } else {
+        vec![]
+    };
+
+    let root = PathBuf::from(&folder.path);
+    for file in files {
+        let (track_root, track_thumbnails_dir) = (root.clone(), thumbnails_dir.to_path_buf());
+        let track = tauri::async_runtime::spawn_blocking(move || {
+            read_track(&track_root, &file, &track_thumbnails_dir)
+        })
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let track = db.upsert_track(user_id, track).await?;
+        changes.updated_tracks.push(track.id);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use notify_debouncer_full::notify::Event;
+
+    use super::*;
+    use crate::database::{library::NewTrack, test_utils::TempDb, users::UserExt};
+
+    fn rename(from: &str, to: &str) -> DebouncedEvent {
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(PathBuf::from(from))
+            .add_path(PathBuf::from(to));
+        DebouncedEvent::new(event, Instant::now())
+    }
+
+    #[test]
+    fn test_sync_renames() {
+        tauri::async_runtime::block_on(async {
+            let temp = TempDb::new().await;
+            let db = &temp.db;
+            let user_id = db.create_user("user", "hash").await.unwrap().id;
+            db.add_library_folder(user_id, "/music").await.unwrap();
+            let mut tracks = vec![];
+            for file_path in ["/music/1.mp3", "/music/2.mp3"] {
+                let track = NewTrack {
+                    file_path: file_path.to_string(),
+                    title: file_path.to_string(),
+                    ..Default::default()
+                };
+                tracks.push(db.upsert_track(user_id, track).await.unwrap().id);
+            }
+
+            // Renamed within the library, and moved out of it
+            let events = vec![
+                rename("/music/1.mp3", "/music/one.mp3"),
+                rename("/music/2.mp3", "/tmp/2.mp3"),
+            ];
+            let changes = sync_events(db, Path::new("/thumbnails"), events)
+                .await
+                .unwrap();
+            assert_eq!(changes.moved_tracks, vec![tracks[0]]);
+            assert_eq!(changes.missing_tracks, vec![tracks[1]]);
+            assert!(changes.updated_tracks.is_empty());
+
+            let file_paths: Vec<(String, bool)> =
+                sqlx::query_as("SELECT file_path, is_missing FROM tracks ORDER BY file_path")
+                    .fetch_all(&db.pool)
+                    .await
+                    .unwrap();
+            assert_eq!(
+                file_paths,
+                vec![
+                    (String::from("/music/2.mp3"), true),
+                    (String::from("/music/one.mp3"), false)
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,69 @@
+use std::f32::consts::FRAC_PI_2;
+
+/// A crossfade between the end of one track and the start of the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crossfade {
+    /// The length of the crossfade in frames.
+    pub total_frames: u64,
+
+    /// The number of frames that were mixed so far.
+    pub frames_mixed: u64,
+}
+
+impl Crossfade {
+    pub fn new(total_frames: u64) -> Self {
+        Self {
+            total_frames: total_frames.max(1),
+            frames_mixed: 0,
+        }
+    }
+
+    /// Mixes the start of the next track (`incoming`) into the end of the current track
+    /// (`outgoing`) in place, using equal-power gains so the loudness stays constant.
+    ///
+    /// Only `incoming.len()` samples are mixed if `incoming` is shorter than `outgoing`.
+    pub fn mix(&mut self, outgoing: &mut [f32], incoming: &[f32], channels: usize) {
+        let channels = channels.max(1);
+        for (frame, (out, inc)) in outgoing
+            .chunks_mut(channels)
+            .zip(incoming.chunks(channels))
+            .enumerate()
+        {
+            let t = (self.frames_mixed + frame as u64) as f32 / self.total_frames as f32;
+            let (fade_out, fade_in) = equal_power_gains(t);
+            for (o, i) in out.iter_mut().zip(inc) {
+                *o = *o * fade_out + *i * fade_in;
+            }
+        }
+        let mixed = outgoing.len().min(incoming.len()) / channels;
+        self.frames_mixed += mixed as u64;
+    }
+}
+
+/// The gains of the outgoing and incoming tracks at `t` (from `0.0` to `1.0`) into the
+/// crossfade.
+pub fn equal_power_gains(t: f32) -> (f32, f32) {
+    let angle = t.clamp(0.0, 1.0) * FRAC_PI_2;
+    (angle.cos(), angle.sin())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_equal_power_crossfade() {
+        // The power of the two tracks always adds up to 1
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            let (fade_out, fade_in) = equal_power_gains(t);
+            assert!((fade_out.powi(2) + fade_in.powi(2) - 1.0).abs() < 1e-6);
+        }
+
+        let mut crossfade = Crossfade::new(4);
+        let mut outgoing = vec![1.0; 8];
+        crossfade.mix(&mut outgoing, &[1.0; 8], 2);
+        assert_eq!(crossfade.frames_mixed, 4);
+        assert_eq!(outgoing[0], 1.0);
+        assert!((outgoing[4] - 2.0_f32.sqrt()).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{fs::File, path::Path, time::Duration};
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder as CodecDecoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+use crate::errors::SpotsError;
+
+/// The format of decoded audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioSpec {
+    /// The number of frames per second.
+    pub sample_rate: u32,
+
+    /// The number of (interleaved) channels in each frame.
+    pub channels: u16,
+}
+
+/// A source of decoded audio that the player reads packet by packet.
+pub trait AudioSource: Send {
+    /// The format of the decoded samples.
+    fn spec(&self) -> AudioSpec;
+
+    /// The total duration of the audio, if it is known.
+    fn duration(&self) -> Option<Duration>;
+
+    /// Decodes the next packet, returning its interleaved samples.
+    ///
+    /// Returns `None` once the end of the audio is reached.
+    fn next_samples(&mut self) -> Result<Option<&[f32]>, SpotsError>;
+
+    /// Seeks to the `position`, returning the position that was actually seeked to.
+    fn seek(&mut self, position: Duration) -> Result<Duration, SpotsError>;
+}
+
+/// Decodes an audio file into interleaved `f32` samples.
+pub struct Decoder {
+    file: String,
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn CodecDecoder>,
+    track_id: u32,
+    spec: AudioSpec,
+    duration: Option<Duration>,
+    sample_buf: Option<SampleBuffer<f32>>,
+
+    /// The number of decoded frames to drop to get to the position that was seeked to.
+    skip_frames: u64,
+}
+
+impl Decoder {
+    /// Opens the audio file for decoding.
+    pub fn open(path: &Path) -> Result<Self, SpotsError> {
+        let file = path.to_string_lossy().to_string();
+        let decode_error = |error: String| SpotsError::DecodeError {
+            file: file.clone(),
+            error,
+        };
+
+        let source = File::open(path)?;
+        let stream = MediaSourceStream::new(Box::new(source), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                stream,
+                &FormatOptions {
+                    enable_gapless: true,
+                    ..Default::default()
+                },
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| decode_error(e.to_string()))?;
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| decode_error(String::from("No audio track found")))?;
+        let params = &track.codec_params;
+        let spec = AudioSpec {
+            sample_rate: params
+                .sample_rate
+                .ok_or_else(|| decode_error(String::from("Unknown sample rate")))?,
+            channels: params.channels.map(|c| c.count() as u16).unwrap_or(2),
+        };
+        let duration = match (params.time_base, params.n_frames) {
+            (Some(time_base), Some(n_frames)) => {
+                Some(time_to_duration(time_base.calc_time(n_frames)))
+            }
+            (None, Some(n_frames)) => Some(Duration::from_secs_f64(
+                n_frames as f64 / f64::from(spec.sample_rate),
+            )),
+            _ => None,
+        };
+        let decoder = symphonia::default::get_codecs()
+            .make(params, &DecoderOptions::default())
+            .map_err(|e| decode_error(e.to_string()))?;
+
+        Ok(Self {
+            track_id: track.id,
+            file,
+            format,
+            decoder,
+            spec,
+            duration,
+            sample_buf: None,
+            skip_frames: 0,
+        })
+    }
+
+    /// Decodes the next packet into the sample buffer, returning the index of the first sample
+    /// to play.
+    fn decode_next_packet(&mut self) -> Result<Option<usize>, SpotsError> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(None)
+                }
+                Err(SymphoniaError::ResetRequired) => {
+                    self.decoder.reset();
+                    continue;
+                }
+                Err(e) => return Err(self.decode_error(e)),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // Skip corrupted packets
+                Err(SymphoniaError::DecodeError(_)) => continue,
+                Err(e) => return Err(self.decode_error(e)),
+            };
+            if decoded.frames() == 0 {
+                continue;
+            }
+
+            // Only reallocate the sample buffer if the packet doesn't fit
+            let needed = decoded.capacity() * decoded.spec().channels.count();
+            if !matches!(&self.sample_buf, Some(buf) if buf.capacity() >= needed) {
+                self.sample_buf = None;
+            }
+            let sample_buf = self.sample_buf.get_or_insert_with(|| {
+                SampleBuffer::new(decoded.capacity() as u64, *decoded.spec())
+            });
+            sample_buf.copy_interleaved_ref(decoded);
+
+            // Drop the frames before the position that was seeked to
+            let samples = sample_buf.samples();
+            let channels = self.spec.channels.max(1) as usize;
+            let skip = (self.skip_frames as usize).min(samples.len() / channels);
+            self.skip_frames -= skip as u64;
+            if skip * channels == samples.len() {
+                continue;
+            }
+            return Ok(Some(skip * channels));
+        }
+    }
+
+    fn decode_error(&self, error: SymphoniaError) -> SpotsError {
+        SpotsError::DecodeError {
+            file: self.file.clone(),
+            error: error.to_string(),
+        }
+    }
+}
+
+impl AudioSource for Decoder {
+    fn spec(&self) -> AudioSpec {
+        self.spec
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    fn next_samples(&mut self) -> Result<Option<&[f32]>, SpotsError> {
+        let Some(start) = self.decode_next_packet()? else {
+            return Ok(None);
+        };
+        Ok(self
+            .sample_buf
+            .as_ref()
+            .map(|sample_buf| &sample_buf.samples()[start..]))
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<Duration, SpotsError> {
+        let seeked = self
+            .format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::from(position.as_secs_f64()),
+                    track_id: Some(self.track_id),
+                },
+            )
+            .map_err(|e| self.decode_error(e))?;
+        self.decoder.reset();
+
+        // The reader seeks to the packet containing the position, so the frames before it have to
+        // be decoded and dropped
+        let time_base = self
+            .format
+            .tracks()
+            .iter()
+            .find(|track| track.id == self.track_id)
+            .and_then(|track| track.codec_params.time_base);
+        let Some(time_base) = time_base else {
+            return Ok(position);
+        };
+        let actual = time_to_duration(time_base.calc_time(seeked.actual_ts));
+        let required = time_to_duration(time_base.calc_time(seeked.required_ts));
+        self.skip_frames = (required.saturating_sub(actual).as_secs_f64()
+            * f64::from(self.spec.sample_rate))
+        .round() as u64;
+        Ok(required)
+    }
+}
+
+/// Converts a symphonia [Time] to a [Duration].
+fn time_to_duration(time: Time) -> Duration {
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
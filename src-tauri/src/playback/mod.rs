@@ -0,0 +1,983 @@
+use std::{
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    database::models::music_library::Track,
+    errors::SpotsError,
+    playback::{
+        crossfade::Crossfade,
+        decoder::{AudioSource, AudioSpec, Decoder},
+        sink::AudioSink,
+    },
+};
+
+pub mod crossfade;
+pub mod decoder;
+pub mod queue;
+pub mod sink;
+
+/// The event emitted to the frontend when the player's state changes.
+pub const PLAYBACK_STATUS_EVENT: &str = "playback-status";
+
+/// The event emitted to the frontend periodically while a track is playing.
+pub const PLAYBACK_POSITION_EVENT: &str = "playback-position";
+
+/// The event emitted to the frontend when a track finishes playing.
+pub const PLAYBACK_ENDED_EVENT: &str = "playback-ended";
+
+/// How often [PlayerEvent::Position] events are sent while playing.
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The state of the player.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaybackState {
+    #[default]
+    Stopped,
+    Playing,
+    Paused,
+}
+
+/// The current status of the player.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerStatus {
+    /// Whether the player is playing, paused or stopped.
+    pub state: PlaybackState,
+
+    /// The track that is loaded into the player.
+    pub track_id: Option<Uuid>,
+
+    /// The position in the track in milliseconds.
+    pub position_ms: u64,
+
+    /// The duration of the track in milliseconds, if it is known.
+    pub duration_ms: Option<u64>,
+
+    /// The volume, from `0.0` (muted) to `1.0`.
+    pub volume: f32,
+
+    /// How long tracks crossfade into each other in milliseconds (`0` for gapless playback).
+    pub crossfade_ms: u64,
+}
+
+impl Default for PlayerStatus {
+    fn default() -> Self {
+        Self {
+            state: PlaybackState::Stopped,
+            track_id: None,
+            position_ms: 0,
+            duration_ms: None,
+            volume: 1.0,
+            crossfade_ms: 0,
+        }
+    }
+}
+
+/// The payload of the [PLAYBACK_POSITION_EVENT].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackPosition {
+    pub track_id: Uuid,
+    pub position_ms: u64,
+}
+
+/// How a track was played, reported once it ends.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPlay {
+    pub track_id: Uuid,
+
+    /// When the track started playing.
+    pub started_at: NaiveDateTime,
+
+    /// How long the track was played for in milliseconds (not counting the parts that were
+    /// skipped by seeking).
+    pub ms_played: u64,
+}
+
+/// Events sent by the player.
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+    /// The player's state, track or volume changed.
+    StatusChanged(PlayerStatus),
+
+    /// The position in the track that is playing.
+    Position(PlaybackPosition),
+
+    /// The track finished playing.
+    ///
+    /// If the next track was set with [Player::set_next], it is already playing.
+    TrackEnded(TrackPlay),
+}
+
+/// Commands sent to the player's thread.
+enum PlayerCommand {
+    Load {
+        track: Box<LoadedTrack>,
+        autoplay: bool,
+    },
+    SetNext(Option<Box<LoadedTrack>>),
+    Play,
+    Pause,
+    Stop,
+    Seek(Duration),
+    SetVolume(f32),
+    SetCrossfade(Duration),
+    Shutdown,
+}
+
+/// A track that was loaded into the player.
+struct LoadedTrack {
+    track_id: Uuid,
+    album_id: Option<Uuid>,
+    decoder: Box<dyn AudioSource>,
+
+    /// The position in the track in frames.
+    frames_played: u64,
+
+    /// When the track started playing.
+    started_at: Option<NaiveDateTime>,
+
+    /// The number of frames of the track that were sent to the sink.
+    frames_heard: u64,
+}
+
+impl LoadedTrack {
+    fn open(track: &Track) -> Result<Box<Self>, SpotsError> {
+        Ok(Box::new(Self {
+            track_id: track.id,
+            album_id: track.album_id,
+            decoder: Box::new(Decoder::open(Path::new(&track.file_path))?),
+            frames_played: 0,
+            started_at: None,
+            frames_heard: 0,
+        }))
+    }
+
+    /// Moves the position on by the `frames` that were sent to the sink.
+    fn advance(&mut self, frames: u64) {
+        self.started_at
+            .get_or_insert_with(|| Utc::now().naive_local());
+        self.frames_played += frames;
+        self.frames_heard += frames;
+    }
+
+    /// Rewinds the track to its start, as if it was never played.
+    fn rewind(&mut self) -> Result<(), SpotsError> {
+        self.decoder.seek(Duration::ZERO)?;
+        self.frames_played = 0;
+        self.started_at = None;
+        self.frames_heard = 0;
+        Ok(())
+    }
+
+    /// How the track was played so far.
+    fn play(&self) -> TrackPlay {
+        let sample_rate = u64::from(self.decoder.spec().sample_rate.max(1));
+        TrackPlay {
+            track_id: self.track_id,
+            started_at: self.started_at.unwrap_or_else(|| Utc::now().naive_local()),
+            ms_played: self.frames_heard * 1000 / sample_rate,
+        }
+    }
+
+    fn position_ms(&self) -> u64 {
+        self.frames_played * 1000 / u64::from(self.decoder.spec().sample_rate.max(1))
+    }
+
+    fn duration_ms(&self) -> Option<u64> {
+        self.decoder.duration().map(|d| d.as_millis() as u64)
+    }
+}
+
+/// Plays tracks on a dedicated thread, sending the decoded audio to an [AudioSink].
+///
+/// The next track can be set ahead of time, so it starts without a gap (or crossfades in) when
+/// the current track ends.
+pub struct Player {
+    commands: Sender<PlayerCommand>,
+    status: Arc<Mutex<PlayerStatus>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Player {
+    /// Starts the player's thread.
+    ///
+    /// The sink is created on the player's thread by `make_sink`, and `on_event` is called
+    /// (from the player's thread) for every [PlayerEvent].
+    pub fn spawn<S, E>(make_sink: S, on_event: E) -> Result<Self, SpotsError>
+    where
+        S: FnOnce() -> Box<dyn AudioSink> + Send + 'static,
+        E: Fn(PlayerEvent) + Send + 'static,
+    {
+        let (commands, rx) = mpsc::channel();
+        let status = Arc::new(Mutex::new(PlayerStatus::default()));
+
+        let engine_status = status.clone();
+        let thread = std::thread::Builder::new()
+            .name(String::from("spots-player"))
+            .spawn(move || {
+                Engine::new(make_sink(), Box::new(on_event), engine_status).run(rx);
+            })?;
+
+        Ok(Self {
+            commands,
+            status,
+            thread: Some(thread),
+        })
+    }
+
+    /// Loads the track into the player, replacing the current (and next) track.
+    ///
+    /// The track starts playing right away if `autoplay` is set.
+    pub fn load(&self, track: &Track, autoplay: bool) -> Result<(), SpotsError> {
+        let track = LoadedTrack::open(track)?;
+        self.send(PlayerCommand::Load { track, autoplay })
+    }
+
+    /// Sets the track to play once the current track ends (or clears it if `None`).
+    pub fn set_next(&self, track: Option<&Track>) -> Result<(), SpotsError> {
+        let track = track.map(LoadedTrack::open).transpose()?;
+        self.send(PlayerCommand::SetNext(track))
+    }
+
+    /// Plays (or resumes) the loaded track.
+    pub fn play(&self) -> Result<(), SpotsError> {
+        self.send(PlayerCommand::Play)
+    }
+
+    /// Pauses the track, keeping its position.
+    pub fn pause(&self) -> Result<(), SpotsError> {
+        self.send(PlayerCommand::Pause)
+    }
+
+    /// Stops the track and unloads it.
+    pub fn stop(&self) -> Result<(), SpotsError> {
+        self.send(PlayerCommand::Stop)
+    }
+
+    /// Seeks to the `position` in the loaded track.
+    pub fn seek(&self, position: Duration) -> Result<(), SpotsError> {
+        self.send(PlayerCommand::Seek(position))
+    }
+
+    /// Sets the volume (clamped between `0.0` and `1.0`).
+    ///
+    /// Returns [SpotsError::PlayerError] if the volume isn't a finite number.
+    pub fn set_volume(&self, volume: f32) -> Result<(), SpotsError> {
+        if !volume.is_finite() {
+            return Err(SpotsError::PlayerError(format!("Invalid volume: {volume}")));
+        }
+        self.send(PlayerCommand::SetVolume(volume))
+    }
+
+    /// Sets how long tracks crossfade into each other ([Duration::ZERO] for gapless playback).
+    ///
+    /// # Note
+    /// Consecutive tracks from the same album are never crossfaded.
+    pub fn set_crossfade(&self, duration: Duration) -> Result<(), SpotsError> {
+        self.send(PlayerCommand::SetCrossfade(duration))
+    }
+
+    /// Gets the current status of the player.
+    pub fn status(&self) -> PlayerStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn send(&self, command: PlayerCommand) -> Result<(), SpotsError> {
+        self.commands
+            .send(command)
+            .map_err(|_| SpotsError::PlayerError(String::from("The player has shut down")))
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        let _ = self.commands.send(PlayerCommand::Shutdown);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The state machine that runs on the player's thread.
+struct Engine {
+    sink: Box<dyn AudioSink>,
+    on_event: Box<dyn Fn(PlayerEvent)>,
+    status: Arc<Mutex<PlayerStatus>>,
+
+    /// The track that is playing.
+    current: Option<Box<LoadedTrack>>,
+
+    /// The track to play after the current one.
+    next: Option<Box<LoadedTrack>>,
+
+    /// The crossfade into the next track, once it started.
+    crossfade: Option<Crossfade>,
+    crossfade_duration: Duration,
+
+    /// Samples of the next track that were decoded but not mixed yet.
+    pending: Vec<f32>,
+
+    /// The format the sink was last opened with.
+    spec: Option<AudioSpec>,
+    last_position_event: Instant,
+}
+
+impl Engine {
+    fn new(
+        sink: Box<dyn AudioSink>,
+        on_event: Box<dyn Fn(PlayerEvent)>,
+        status: Arc<Mutex<PlayerStatus>>,
+    ) -> Self {
+        Self {
+            sink,
+            on_event,
+            status,
+            current: None,
+            next: None,
+            crossfade: None,
+            crossfade_duration: Duration::ZERO,
+            pending: Vec::new(),
+            spec: None,
+            last_position_event: Instant::now(),
+        }
+    }
+
+    fn run(mut self, commands: Receiver<PlayerCommand>) {
+        loop {
+            // Only block on commands when there's nothing to play
+            let command = if self.state() == PlaybackState::Playing {
+                match commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(mpsc::TryRecvError::Empty) => None,
+                    Err(mpsc::TryRecvError::Disconnected) => break,
+                }
+            } else {
+                match commands.recv_timeout(POSITION_INTERVAL) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            };
+
+            match command {
+                Some(PlayerCommand::Shutdown) => break,
+                Some(command) => {
+                    if let Err(e) = self.handle_command(command) {
+                        error!(error = e.to_string(), "Player command failed");
+                    }
+                }
+                None => {
+                    if let Err(e) = self.play_next_packet() {
+                        error!(error = e.to_string(), "Unable to play the track");
+                        self.unload();
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = self.sink.close() {
+            warn!(error = e.to_string(), "Unable to close the audio sink");
+        }
+    }
+
+    fn handle_command(&mut self, command: PlayerCommand) -> Result<(), SpotsError> {
+        match command {
+            PlayerCommand::Load { track, autoplay } => {
+                info!(track_id = track.track_id.to_string(), "Loading track");
+                self.sink.clear();
+                self.next = None;
+                self.cancel_crossfade()?;
+                let (track_id, duration_ms) = (track.track_id, track.duration_ms());
+                self.current = Some(track);
+                self.update_status(|status| {
+                    status.track_id = Some(track_id);
+                    status.position_ms = 0;
+                    status.duration_ms = duration_ms;
+                    status.state = if autoplay {
+                        PlaybackState::Playing
+                    } else {
+                        PlaybackState::Paused
+                    };
+                });
+                if autoplay {
+                    self.sink.resume()?;
+                }
+            }
+            PlayerCommand::SetNext(track) => {
+                self.cancel_crossfade()?;
+                self.next = track;
+            }
+            PlayerCommand::Play => {
+                if self.current.is_some() && self.state() != PlaybackState::Playing {
+                    self.sink.resume()?;
+                    self.update_status(|status| status.state = PlaybackState::Playing);
+                }
+            }
+            PlayerCommand::Pause => {
+                if self.state() == PlaybackState::Playing {
+                    self.sink.pause()?;
+                    self.update_status(|status| status.state = PlaybackState::Paused);
+                }
+            }
+            PlayerCommand::Stop => self.unload(),
+            PlayerCommand::Seek(position) => {
+                self.cancel_crossfade()?;
+                let Some(current) = &mut self.current else {
+                    return Err(SpotsError::PlayerError(String::from("No track is loaded")));
+                };
+                let seeked = current.decoder.seek(position)?;
+                current.frames_played =
+                    (seeked.as_secs_f64() * f64::from(current.decoder.spec().sample_rate)) as u64;
+                self.sink.clear();
+                self.update_status(|status| status.position_ms = seeked.as_millis() as u64);
+            }
+            PlayerCommand::SetVolume(volume) => {
+                self.update_status(|status| status.volume = volume.clamp(0.0, 1.0));
+            }
+            PlayerCommand::SetCrossfade(duration) => {
+                self.crossfade_duration = duration;
+                self.update_status(|status| status.crossfade_ms = duration.as_millis() as u64);
+            }
+            PlayerCommand::Shutdown => {}
+        }
+        Ok(())
+    }
+
+    /// Decodes the next packet of the track and sends it to the sink.
+    ///
+    /// Near the end of the track, the start of the next track is mixed in if crossfading.
+    fn play_next_packet(&mut self) -> Result<(), SpotsError> {
+        // Play what's left of the next track's samples once the crossfade is over (the leftovers
+        // are mixed into the current track's next packet while it's still going)
+        if self.crossfade.is_none() && !self.pending.is_empty() {
+            let samples = std::mem::take(&mut self.pending);
+            if let Some(current) = &mut self.current {
+                let channels = current.decoder.spec().channels.max(1) as usize;
+                current.advance((samples.len() / channels) as u64);
+            }
+            return self.write(samples);
+        }
+
+        if self.crossfade.is_none() {
+            self.crossfade = self.start_crossfade();
+        }
+        let Some(current) = &mut self.current else {
+            self.update_status(|status| status.state = PlaybackState::Stopped);
+            return Ok(());
+        };
+        let Some(samples) = current.decoder.next_samples()? else {
+            return self.finish_track();
+        };
+        let mut samples = samples.to_vec();
+        let channels = current.decoder.spec().channels as usize;
+        current.advance((samples.len() / channels.max(1)) as u64);
+
+        // Mix in the start of the next track
+        if let (Some(crossfade), Some(next)) = (&mut self.crossfade, &mut self.next) {
+            while self.pending.len() < samples.len() {
+                match next.decoder.next_samples()? {
+                    Some(incoming) => self.pending.extend_from_slice(incoming),
+                    None => break,
+                }
+            }
+            let mixed = samples.len().min(self.pending.len());
+            crossfade.mix(&mut samples, &self.pending[..mixed], channels);
+            self.pending.drain(..mixed);
+            next.advance((mixed / channels.max(1)) as u64);
+        }
+
+        self.write(samples)
+    }
+
+    /// Starts crossfading into the next track if the current track is close enough to its end.
+    ///
+    /// Tracks aren't crossfaded if they are from the same album (so live albums and DJ mixes
+    /// play as intended), or if their formats don't match.
+    fn start_crossfade(&self) -> Option<Crossfade> {
+        let (current, next) = (self.current.as_ref()?, self.next.as_ref()?);
+        if self.crossfade_duration.is_zero()
+            || (current.album_id.is_some() && current.album_id == next.album_id)
+            || current.decoder.spec() != next.decoder.spec()
+        {
+            return None;
+        }
+
+        let remaining_ms = current.duration_ms()?.saturating_sub(current.position_ms());
+        let crossfade_ms = self.crossfade_duration.as_millis() as u64;
+        if remaining_ms > crossfade_ms {
+            return None;
+        }
+        let sample_rate = u64::from(current.decoder.spec().sample_rate);
+        Some(Crossfade::new(remaining_ms * sample_rate / 1000))
+    }
+
+    /// Stops crossfading, rewinding the next track to its start.
+    fn cancel_crossfade(&mut self) -> Result<(), SpotsError> {
+        self.pending.clear();
+        if self.crossfade.take().is_some() {
+            if let Some(next) = &mut self.next {
+                next.rewind()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves on to the next track once the current track ends, or stops if there isn't one.
+    fn finish_track(&mut self) -> Result<(), SpotsError> {
+        let ended = self.current.take().map(|track| track.play());
+        self.crossfade = None;
+
+        match self.next.take() {
+            Some(next) => {
+                let (track_id, position_ms, duration_ms) =
+                    (next.track_id, next.position_ms(), next.duration_ms());
+                self.current = Some(next);
+                self.update_status(|status| {
+                    status.track_id = Some(track_id);
+                    status.position_ms = position_ms;
+                    status.duration_ms = duration_ms;
+                });
+            }
+            None => {
+                self.sink.drain()?;
+                self.unload();
+            }
+        }
+
+        if let Some(play) = ended {
+            (self.on_event)(PlayerEvent::TrackEnded(play));
+        }
+        Ok(())
+    }
+
+    /// Sends the samples of the current track to the sink.
+    fn write(&mut self, mut samples: Vec<f32>) -> Result<(), SpotsError> {
+        let Some(current) = &self.current else {
+            return Ok(());
+        };
+        let spec = current.decoder.spec();
+        let (track_id, position_ms) = (current.track_id, current.position_ms());
+
+        if self.spec != Some(spec) {
+            self.sink.open(spec)?;
+            self.spec = Some(spec);
+        }
+        let volume = self.status.lock().unwrap_or_else(|e| e.into_inner()).volume;
+        if volume < 1.0 {
+            samples.iter_mut().for_each(|sample| *sample *= volume);
+        }
+        self.sink.write(&samples)?;
+
+        // Update the position
+        self.update_status(|status| status.position_ms = position_ms);
+        if self.last_position_event.elapsed() >= POSITION_INTERVAL {
+            self.last_position_event = Instant::now();
+            (self.on_event)(PlayerEvent::Position(PlaybackPosition {
+                track_id,
+                position_ms,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Stops playback and unloads the current and next tracks.
+    fn unload(&mut self) {
+        self.sink.clear();
+        self.current = None;
+        self.next = None;
+        self.crossfade = None;
+        self.pending.clear();
+        let status = self.status();
+        if status.state != PlaybackState::Stopped || status.track_id.is_some() {
+            *self.status.lock().unwrap_or_else(|e| e.into_inner()) = PlayerStatus {
+                volume: status.volume,
+                crossfade_ms: status.crossfade_ms,
+                ..Default::default()
+            };
+            (self.on_event)(PlayerEvent::StatusChanged(self.status()));
+        }
+    }
+
+    fn state(&self) -> PlaybackState {
+        self.status().state
+    }
+
+    fn status(&self) -> PlayerStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Updates the status, sending a [PlayerEvent::StatusChanged] if anything but the position
+    /// changed.
+    fn update_status(&self, update: impl FnOnce(&mut PlayerStatus)) {
+        let (changed, status) = {
+            let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+            let before = status.clone();
+            update(&mut status);
+            let changed = PlayerStatus {
+                position_ms: before.position_ms,
+                ..status.clone()
+            } != before;
+            (changed, status.clone())
+        };
+        if changed {
+            (self.on_event)(PlayerEvent::StatusChanged(status));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use chrono::Utc;
+    use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+    use super::*;
+    use crate::playback::sink::{NullSink, WavSink};
+
+    /// The sample rate of the test tracks, which are mono.
+    const SAMPLE_RATE: u32 = 8000;
+
+    /// Writes a mono WAV file with `frames` samples of `value`, returning its track.
+    fn write_track(dir: &Path, name: &str, frames: usize, value: f32) -> Track {
+        let file_path = dir.join(format!("{name}.wav"));
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(&file_path, spec).unwrap();
+        for _ in 0..frames {
+            writer.write_sample(value).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let now = Utc::now().naive_local();
+        Track {
+            id: Uuid::new_v4(),
+            user_id: None,
+            title: name.to_string(),
+            album_id: None,
+            track_number: None,
+            release_year: None,
+            duration_secs: None,
+            file_path: file_path.to_string_lossy().to_string(),
+            thumbnail_path: String::new(),
+            created_at: now,
+            updated_at: now,
+            last_played_at: None,
+            is_missing: false,
+        }
+    }
+
+    /// A mono source of `frames` samples of `value`, decoded `packet_frames` at a time.
+    struct TestSource {
+        frames: usize,
+        packet_frames: usize,
+        value: f32,
+        position: usize,
+        packet: Vec<f32>,
+    }
+
+    impl TestSource {
+        fn track(frames: usize, packet_frames: usize, value: f32) -> Box<LoadedTrack> {
+            let source = Self {
+                frames,
+                packet_frames,
+                value,
+                position: 0,
+                packet: vec![],
+            };
+            Box::new(LoadedTrack {
+                track_id: Uuid::new_v4(),
+                album_id: None,
+                decoder: Box::new(source),
+                frames_played: 0,
+                started_at: None,
+                frames_heard: 0,
+            })
+        }
+    }
+
+    impl AudioSource for TestSource {
+        fn spec(&self) -> AudioSpec {
+            AudioSpec {
+                sample_rate: SAMPLE_RATE,
+                channels: 1,
+            }
+        }
+
+        fn duration(&self) -> Option<Duration> {
+            Some(Duration::from_secs_f64(
+                self.frames as f64 / f64::from(SAMPLE_RATE),
+            ))
+        }
+
+        fn next_samples(&mut self) -> Result<Option<&[f32]>, SpotsError> {
+            let len = self.packet_frames.min(self.frames - self.position);
+            if len == 0 {
+                return Ok(None);
+            }
+            self.position += len;
+            self.packet = vec![self.value; len];
+            Ok(Some(&self.packet))
+        }
+
+        fn seek(&mut self, position: Duration) -> Result<Duration, SpotsError> {
+            self.position =
+                ((position.as_secs_f64() * f64::from(SAMPLE_RATE)) as usize).min(self.frames);
+            Ok(position)
+        }
+    }
+
+    /// Creates an engine that sends its events to the returned receiver.
+    fn engine(sink: Box<dyn AudioSink>) -> (Engine, Receiver<PlayerEvent>) {
+        let (tx, rx) = mpsc::channel();
+        let on_event = Box::new(move |event| {
+            let _ = tx.send(event);
+        });
+        let status = Arc::new(Mutex::new(PlayerStatus::default()));
+        (Engine::new(sink, on_event, status), rx)
+    }
+
+    fn load(track: &Track, autoplay: bool) -> PlayerCommand {
+        PlayerCommand::Load {
+            track: LoadedTrack::open(track).unwrap(),
+            autoplay,
+        }
+    }
+
+    /// Plays packets until the engine stops playing.
+    fn play_to_end(engine: &mut Engine) {
+        while engine.state() == PlaybackState::Playing {
+            engine.play_next_packet().unwrap();
+        }
+    }
+
+    fn ended_plays(events: &Receiver<PlayerEvent>) -> Vec<TrackPlay> {
+        events
+            .try_iter()
+            .filter_map(|event| match event {
+                PlayerEvent::TrackEnded(play) => Some(play),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn ended_tracks(events: &Receiver<PlayerEvent>) -> Vec<Uuid> {
+        ended_plays(events)
+            .into_iter()
+            .map(|play| play.track_id)
+            .collect()
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("spots-playback-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_engine_transport() {
+        let dir = temp_dir();
+        let output = dir.join("output.wav");
+        let track = write_track(&dir, "track", 2 * SAMPLE_RATE as usize, 0.5);
+        let (mut engine, events) = engine(Box::new(WavSink::new(&output)));
+
+        // Loading without autoplay
+        engine.handle_command(load(&track, false)).unwrap();
+        let status = engine.status();
+        assert_eq!(status.state, PlaybackState::Paused);
+        assert_eq!(status.track_id, Some(track.id));
+        assert_eq!(status.duration_ms, Some(2000));
+
+        // Playing a packet moves the position
+        engine.handle_command(PlayerCommand::Play).unwrap();
+        assert_eq!(engine.state(), PlaybackState::Playing);
+        engine.play_next_packet().unwrap();
+        let position_ms = engine.status().position_ms;
+        assert!(position_ms > 0 && position_ms < 2000, "{position_ms}");
+
+        engine.handle_command(PlayerCommand::Pause).unwrap();
+        assert_eq!(engine.state(), PlaybackState::Paused);
+        assert_eq!(engine.status().position_ms, position_ms);
+
+        // Seeking, then playing the rest at half volume
+        engine
+            .handle_command(PlayerCommand::Seek(Duration::from_millis(1500)))
+            .unwrap();
+        assert_eq!(engine.status().position_ms, 1500);
+        engine
+            .handle_command(PlayerCommand::SetVolume(0.5))
+            .unwrap();
+        engine.handle_command(PlayerCommand::Play).unwrap();
+        play_to_end(&mut engine);
+
+        let status = engine.status();
+        assert_eq!(status.state, PlaybackState::Stopped);
+        assert_eq!(status.track_id, None);
+        assert_eq!(status.volume, 0.5);
+
+        // The skipped part of the track isn't counted as played
+        let plays = ended_plays(&events);
+        assert_eq!(plays.len(), 1);
+        assert_eq!(plays[0].track_id, track.id);
+        assert_eq!(plays[0].ms_played, position_ms + 500);
+        drop(engine);
+
+        // The first packet is at full volume, and the last half second at half volume
+        let samples: Vec<f32> = WavReader::open(&output)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        let first_packet = (position_ms * u64::from(SAMPLE_RATE) / 1000) as usize;
+        assert_eq!(samples.len(), first_packet + SAMPLE_RATE as usize / 2);
+        assert!(samples[..first_packet].iter().all(|sample| *sample == 0.5));
+        assert!(samples[first_packet..].iter().all(|sample| *sample == 0.25));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_engine_stop_and_next_track() {
+        let dir = temp_dir();
+        let first = write_track(&dir, "first", SAMPLE_RATE as usize / 2, 0.5);
+        let second = write_track(&dir, "second", SAMPLE_RATE as usize / 2, 0.5);
+        let (mut engine, events) = engine(Box::new(NullSink));
+
+        // The next track plays once the first one ends
+        engine.handle_command(load(&first, true)).unwrap();
+        engine
+            .handle_command(PlayerCommand::SetNext(Some(
+                LoadedTrack::open(&second).unwrap(),
+            )))
+            .unwrap();
+        while engine.status().track_id == Some(first.id) {
+            engine.play_next_packet().unwrap();
+        }
+        assert_eq!(engine.status().track_id, Some(second.id));
+        assert_eq!(engine.state(), PlaybackState::Playing);
+        assert_eq!(ended_tracks(&events), vec![first.id]);
+
+        // Stopping unloads the track without ending it
+        engine.handle_command(PlayerCommand::Stop).unwrap();
+        assert_eq!(engine.status().track_id, None);
+        assert_eq!(engine.state(), PlaybackState::Stopped);
+        assert!(ended_tracks(&events).is_empty());
+        assert!(engine
+            .handle_command(PlayerCommand::Seek(Duration::ZERO))
+            .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_player() {
+        let dir = temp_dir();
+        let track = write_track(&dir, "track", SAMPLE_RATE as usize / 2, 0.5);
+        let (tx, events) = mpsc::channel();
+        let player = Player::spawn(
+            || Box::new(NullSink),
+            move |event| {
+                let _ = tx.send(event);
+            },
+        )
+        .unwrap();
+
+        player.load(&track, true).unwrap();
+        let ended = events
+            .iter()
+            .find_map(|event| match event {
+                PlayerEvent::TrackEnded(play) => Some(play.track_id),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(ended, track.id);
+        assert_eq!(player.status().state, PlaybackState::Stopped);
+
+        for volume in [f32::NAN, f32::INFINITY] {
+            assert!(player.set_volume(volume).is_err());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crossfade_with_unequal_packets() {
+        let dir = temp_dir();
+        let output = dir.join("output.wav");
+        let half_second = SAMPLE_RATE as usize / 2;
+        let sinks: [Box<dyn AudioSink>; 2] = [Box::new(NullSink), Box::new(WavSink::new(&output))];
+        for sink in sinks {
+            // Silence crossfading into a tone, with the tone's packets longer than the silence's
+            let (mut engine, events) = engine(sink);
+            let (outgoing, incoming) = (
+                TestSource::track(half_second, 300, 0.0),
+                TestSource::track(half_second, 700, 1.0),
+            );
+            let (outgoing_id, incoming_id) = (outgoing.track_id, incoming.track_id);
+            engine
+                .handle_command(PlayerCommand::SetCrossfade(Duration::from_millis(250)))
+                .unwrap();
+            engine
+                .handle_command(PlayerCommand::Load {
+                    track: outgoing,
+                    autoplay: true,
+                })
+                .unwrap();
+            engine
+                .handle_command(PlayerCommand::SetNext(Some(incoming)))
+                .unwrap();
+
+            // The incoming track's position counts all of its samples
+            let mut position_ms = 0;
+            while engine.state() == PlaybackState::Playing {
+                engine.play_next_packet().unwrap();
+                let status = engine.status();
+                if status.track_id == Some(incoming_id) {
+                    position_ms = status.position_ms;
+                }
+            }
+            assert_eq!(position_ms, 500);
+            assert_eq!(ended_tracks(&events), vec![outgoing_id, incoming_id]);
+        }
+
+        // The tone fades in without jumping to full volume during the crossfade, and the
+        // overlap is only played once
+        let samples: Vec<f32> = WavReader::open(&output)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect();
+        assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]));
+        let faded_in = samples.iter().filter(|sample| **sample == 1.0).count();
+        assert!(faded_in < half_second, "{faded_in}");
+        let overlap = 2 * half_second - samples.len();
+        assert!(overlap > 1800 && overlap <= 2000, "{overlap}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::database::models::music_library::QueueEntry;
+
+/// How the queue repeats once it reaches the end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RepeatMode {
+    /// Stop after the last track.
+    #[default]
+    Off,
+
+    /// Repeat the current track.
+    One,
+
+    /// Go back to the first track after the last one.
+    All,
+}
+
+impl RepeatMode {
+    /// The name stored in the DB.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::One => "one",
+            RepeatMode::All => "all",
+        }
+    }
+
+    /// Parses the name stored in the DB, defaulting to [RepeatMode::Off].
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "one" => RepeatMode::One,
+            "all" => RepeatMode::All,
+            _ => RepeatMode::Off,
+        }
+    }
+}
+
+/// A user's play queue.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueue {
+    /// The queued tracks, in the order they will be played.
+    pub entries: Vec<QueueEntry>,
+
+    /// The index (in `entries`) of the track that is playing.
+    pub current: Option<usize>,
+
+    /// How the queue repeats.
+    pub repeat: RepeatMode,
+
+    /// Whether the queue is shuffled.
+    pub shuffled: bool,
+
+    /// How far into the current track playback got (in milliseconds).
+    pub position_ms: u64,
+}
+
+impl PlayQueue {
+    /// The ID of the track that is playing.
+    pub fn current_track(&self) -> Option<Uuid> {
+        self.current
+            .and_then(|idx| self.entries.get(idx))
+            .map(|entry| entry.track_id)
+    }
+
+    /// The ID of the track that will play after the current one, following the repeat mode.
+    pub fn peek_next(&self) -> Option<Uuid> {
+        self.next_index()
+            .and_then(|idx| self.entries.get(idx))
+            .map(|entry| entry.track_id)
+    }
+
+    /// Adds the tracks to the end of the queue.
+    pub fn enqueue(&mut self, track_ids: &[Uuid]) {
+        let end = self.entries.len();
+        for (i, track_id) in track_ids.iter().enumerate() {
+            self.entries.push(QueueEntry::new(*track_id, end + i));
+        }
+    }
+
+    /// Adds the tracks right after the current track.
+    ///
+    /// If the queue is shuffled, the tracks are also placed after the current track in the
+    /// original order, so they stay next when the shuffle is undone.
+    pub fn play_next(&mut self, track_ids: &[Uuid]) {
+        let insert_at = self.current.map_or(0, |idx| idx + 1);
+        let original_at = self
+            .current
+            .and_then(|idx| self.entries.get(idx))
+            .map_or(0, |entry| entry.original_position + 1);
+
+        for entry in &mut self.entries {
+            if entry.original_position >= original_at {
+                entry.original_position += track_ids.len();
+            }
+        }
+        let new_entries = track_ids
+            .iter()
+            .enumerate()
+            .map(|(i, track_id)| QueueEntry::new(*track_id, original_at + i));
+        self.entries.splice(insert_at..insert_at, new_entries);
+    }
+
+    /// Moves the entry to the `to` index in the queue.
+    ///
+    /// Returns `false` if the entry isn't in the queue.
+    pub fn move_entry(&mut self, entry_id: Uuid, to: usize) -> bool {
+        let Some(from) = self.index_of(entry_id) else {
+            return false;
+        };
+        let current = self.current.map(|idx| self.entries[idx].id);
+        let entry = self.entries.remove(from);
+        let to = to.min(self.entries.len());
+        self.entries.insert(to, entry);
+        self.current = current.and_then(|id| self.index_of(id));
+
+        // Reordering an unshuffled queue changes its original order too
+        if !self.shuffled {
+            self.renumber();
+        }
+        true
+    }
+
+    /// Removes the entry from the queue.
+    ///
+    /// Returns `false` if the entry isn't in the queue.
+    pub fn remove(&mut self, entry_id: Uuid) -> bool {
+        let Some(idx) = self.index_of(entry_id) else {
+            return false;
+        };
+        self.entries.remove(idx);
+        self.current = match self.current {
+            Some(current) if current == idx => {
+                // The next track becomes the current one
+                self.position_ms = 0;
+                (idx < self.entries.len()).then_some(idx)
+            }
+            Some(current) if current > idx => Some(current - 1),
+            current => current,
+        };
+
+        // Close the gap in the original order
+        let mut by_original: Vec<usize> = (0..self.entries.len()).collect();
+        by_original.sort_by_key(|i| self.entries[*i].original_position);
+        for (position, i) in by_original.into_iter().enumerate() {
+            self.entries[i].original_position = position;
+        }
+        true
+    }
+
+    /// Removes all of the tracks from the queue.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.current = None;
+        self.shuffled = false;
+        self.position_ms = 0;
+    }
+
+    /// Makes the entry the current track.
+    ///
+    /// Returns the entry's track ID, or `None` if the entry isn't in the queue.
+    pub fn jump_to(&mut self, entry_id: Uuid) -> Option<Uuid> {
+        let idx = self.index_of(entry_id)?;
+        self.current = Some(idx);
+        self.position_ms = 0;
+        self.current_track()
+    }
+
+    /// Moves to the next track, following the repeat mode.
+    ///
+    /// Returns the ID of the next track, or `None` if the end of the queue was reached.
+    pub fn advance(&mut self) -> Option<Uuid> {
+        self.current = self.next_index();
+        self.position_ms = 0;
+        self.current_track()
+    }
+
+    /// Moves to the previous track (wrapping around if repeating the whole queue).
+    pub fn previous(&mut self) -> Option<Uuid> {
+        let len = self.entries.len();
+        self.current = match self.current {
+            _ if len == 0 => None,
+            Some(0) if self.repeat == RepeatMode::All => Some(len - 1),
+            Some(idx) => Some(idx.saturating_sub(1)),
+            None => Some(len - 1),
+        };
+        self.position_ms = 0;
+        self.current_track()
+    }
+
+    /// Shuffles or unshuffles the queue.
+    ///
+    /// The current track stays current, and the rest of the queue is shuffled after it.
+    /// Unshuffling puts the tracks back in their original order.
+    pub fn set_shuffled(&mut self, shuffled: bool) {
+        if shuffled == self.shuffled {
+            return;
+        }
+        let current = self.current.map(|idx| self.entries[idx].id);
+
+        if shuffled {
+            let mut rest = std::mem::take(&mut self.entries);
+            let first = self.current.map(|idx| rest.remove(idx));
+            shuffle(&mut rest);
+            self.entries = first.into_iter().chain(rest).collect();
+        } else {
+            self.entries.sort_by_key(|entry| entry.original_position);
+        }
+
+        self.current = current.and_then(|id| self.index_of(id));
+        self.shuffled = shuffled;
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        let len = self.entries.len();
+        match (self.current, self.repeat) {
+            _ if len == 0 => None,
+            (None, _) => Some(0),
+            (Some(idx), RepeatMode::One) => Some(idx),
+            (Some(idx), RepeatMode::All) => Some((idx + 1) % len),
+            (Some(idx), RepeatMode::Off) => (idx + 1 < len).then_some(idx + 1),
+        }
+    }
+
+    fn index_of(&self, entry_id: Uuid) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == entry_id)
+    }
+
+    /// Makes the original order match the current order.
+    fn renumber(&mut self) {
+        for (position, entry) in self.entries.iter_mut().enumerate() {
+            entry.original_position = position;
+        }
+    }
+}
+
+/// Shuffles the entries in place (Fisher-Yates).
+fn shuffle(entries: &mut [QueueEntry]) {
+    let rng = SystemRandom::new();
+    for i in (1..entries.len()).rev() {
+        let mut bytes = [0u8; 8];
+        let j = match rng.fill(&mut bytes) {
+            Ok(()) => (u64::from_le_bytes(bytes) % (i as u64 + 1)) as usize,
+            Err(_) => i,
+        };
+        entries.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(len: usize) -> (PlayQueue, Vec<Uuid>) {
+        let track_ids: Vec<Uuid> = (0..len).map(|_| Uuid::new_v4()).collect();
+        let mut queue = PlayQueue::default();
+        queue.enqueue(&track_ids);
+        (queue, track_ids)
+    }
+
+    fn track_ids(queue: &PlayQueue) -> Vec<Uuid> {
+        queue.entries.iter().map(|entry| entry.track_id).collect()
+    }
+
+    #[test]
+    fn test_shuffle_can_be_undone() {
+        let (mut queue, original) = queue(50);
+        queue.jump_to(queue.entries[10].id);
+
+        queue.set_shuffled(true);
+        assert_eq!(queue.current, Some(0));
+        assert_eq!(queue.current_track(), Some(original[10]));
+
+        // Tracks queued while shuffled keep their place when unshuffled
+        let next = Uuid::new_v4();
+        queue.play_next(&[next]);
+        assert_eq!(queue.peek_next(), Some(next));
+
+        queue.set_shuffled(false);
+        let mut expected = original.clone();
+        expected.insert(11, next);
+        assert_eq!(track_ids(&queue), expected);
+        assert_eq!(queue.current_track(), Some(original[10]));
+    }
+
+    #[test]
+    fn test_repeat_modes() {
+        let (mut queue, tracks) = queue(2);
+        assert_eq!(queue.advance(), Some(tracks[0]));
+        assert_eq!(queue.advance(), Some(tracks[1]));
+        assert_eq!(queue.advance(), None);
+
+        queue.repeat = RepeatMode::All;
+        queue.jump_to(queue.entries[1].id);
+        assert_eq!(queue.advance(), Some(tracks[0]));
+
+        queue.repeat = RepeatMode::One;
+        assert_eq!(queue.advance(), Some(tracks[0]));
+    }
+
+    #[test]
+    fn test_move_and_remove() {
+        let (mut queue, tracks) = queue(4);
+        queue.jump_to(queue.entries[1].id);
+
+        queue.move_entry(queue.entries[3].id, 0);
+        assert_eq!(
+            track_ids(&queue),
+            vec![tracks[3], tracks[0], tracks[1], tracks[2]]
+        );
+        assert_eq!(queue.current_track(), Some(tracks[1]));
+
+        queue.remove(queue.entries[2].id);
+        assert_eq!(queue.current_track(), Some(tracks[2]));
+        assert!(!queue.remove(Uuid::new_v4()));
+    }
+}
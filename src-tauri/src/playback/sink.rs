@@ -0,0 +1,484 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+#[cfg(test)]
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, Stream, StreamConfig,
+};
+#[cfg(test)]
+use hound::{SampleFormat as WavSampleFormat, WavSpec, WavWriter};
+use tracing::error;
+
+use crate::{errors::SpotsError, playback::decoder::AudioSpec};
+
+/// Where the decoded audio is sent to.
+///
+/// # Note
+/// Sinks are created on (and only used from) the player's thread, so they don't have to be `Send`.
+pub trait AudioSink {
+    /// Prepares the sink for samples in the given format.
+    ///
+    /// This is called before the first write, and again whenever the format changes.
+    fn open(&mut self, spec: AudioSpec) -> Result<(), SpotsError>;
+
+    /// Writes the interleaved samples, blocking until the sink has room for them.
+    fn write(&mut self, samples: &[f32]) -> Result<(), SpotsError>;
+
+    /// Pauses the output of any buffered samples.
+    fn pause(&mut self) -> Result<(), SpotsError> {
+        Ok(())
+    }
+
+    /// Resumes the output after a [AudioSink::pause].
+    fn resume(&mut self) -> Result<(), SpotsError> {
+        Ok(())
+    }
+
+    /// Drops any samples that were written but not output yet (e.g. after seeking).
+    fn clear(&mut self) {}
+
+    /// Waits for the buffered samples to be output (e.g. at the end of a track).
+    fn drain(&mut self) -> Result<(), SpotsError> {
+        Ok(())
+    }
+
+    /// Finishes writing any buffered samples and releases the output.
+    fn close(&mut self) -> Result<(), SpotsError> {
+        self.drain()
+    }
+}
+
+/// A sink that discards all of its samples.
+#[derive(Debug, Default)]
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+    fn open(&mut self, _spec: AudioSpec) -> Result<(), SpotsError> {
+        Ok(())
+    }
+
+    fn write(&mut self, _samples: &[f32]) -> Result<(), SpotsError> {
+        Ok(())
+    }
+}
+
+/// A sink that writes the samples to a WAV file (as 32-bit floats), so playback can be tested
+/// headless.
+///
+/// # Note
+/// The file is rewritten if the format of the samples changes.
+#[cfg(test)]
+pub struct WavSink {
+    path: PathBuf,
+    spec: Option<AudioSpec>,
+    writer: Option<WavWriter<BufWriter<File>>>,
+}
+
+#[cfg(test)]
+impl WavSink {
+    /// Creates a sink that writes to the WAV file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            spec: None,
+            writer: None,
+        }
+    }
+
+    fn wav_error(&self, error: hound::Error) -> SpotsError {
+        SpotsError::AudioOutputError(format!("{}: {}", self.path.to_string_lossy(), error))
+    }
+}
+
+#[cfg(test)]
+impl AudioSink for WavSink {
+    fn open(&mut self, spec: AudioSpec) -> Result<(), SpotsError> {
+        if self.spec == Some(spec) && self.writer.is_some() {
+            return Ok(());
+        }
+        self.close()?;
+
+        let writer = WavWriter::create(
+            &self.path,
+            WavSpec {
+                channels: spec.channels,
+                sample_rate: spec.sample_rate,
+                bits_per_sample: 32,
+                sample_format: WavSampleFormat::Float,
+            },
+        )
+        .map_err(|e| self.wav_error(e))?;
+        self.writer = Some(writer);
+        self.spec = Some(spec);
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), SpotsError> {
+        let Some(writer) = &mut self.writer else {
+            return Err(SpotsError::AudioOutputError(String::from(
+                "The WAV sink was written to before it was opened",
+            )));
+        };
+        let result = samples
+            .iter()
+            .try_for_each(|sample| writer.write_sample(*sample));
+        result.map_err(|e| self.wav_error(e))
+    }
+
+    fn drain(&mut self) -> Result<(), SpotsError> {
+        // Flushing also updates the header, so the file is valid up to this point
+        let result = match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
+        };
+        result.map_err(|e| self.wav_error(e))
+    }
+
+    fn close(&mut self) -> Result<(), SpotsError> {
+        match self.writer.take() {
+            Some(writer) => writer.finalize().map_err(|e| self.wav_error(e)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for WavSink {
+    fn drop(&mut self) {
+        if let Err(e) = self.close() {
+            error!(error = e.to_string(), "Unable to finish the WAV file");
+        }
+    }
+}
+
+/// How much audio the [DeviceSink] buffers ahead of the output device.
+const DEVICE_BUFFER_DURATION: Duration = Duration::from_millis(200);
+
+/// How long a write to the [DeviceSink] waits for the device to make room before giving up.
+const DEVICE_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Samples waiting to be output by the device.
+#[derive(Default)]
+struct SampleQueue {
+    samples: Mutex<VecDeque<f32>>,
+    drained: Condvar,
+}
+
+/// A sink that plays the samples on the default output device.
+///
+/// Samples are converted to the device's channel count and sample rate if the device doesn't
+/// support the format of the audio.
+pub struct DeviceSink {
+    device: cpal::Device,
+    stream: Option<Stream>,
+    queue: Arc<SampleQueue>,
+    converter: Option<Converter>,
+    capacity: usize,
+}
+
+impl DeviceSink {
+    /// Creates a sink for the host's default output device.
+    pub fn new() -> Result<Self, SpotsError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| SpotsError::AudioOutputError(String::from("No output device found")))?;
+        Ok(Self {
+            device,
+            stream: None,
+            queue: Arc::default(),
+            converter: None,
+            capacity: 0,
+        })
+    }
+
+    /// Finds the output config to use for the audio.
+    fn output_config(&self, spec: AudioSpec) -> Result<(StreamConfig, SampleFormat), SpotsError> {
+        let output_error = |e: String| SpotsError::AudioOutputError(e);
+
+        // Use the audio's format as is if the device supports it
+        let supported = self
+            .device
+            .supported_output_configs()
+            .map_err(|e| output_error(e.to_string()))?
+            .find(|config| {
+                config.channels() == spec.channels
+                    && config.min_sample_rate().0 <= spec.sample_rate
+                    && config.max_sample_rate().0 >= spec.sample_rate
+            });
+        if let Some(config) = supported {
+            let config = config.with_sample_rate(cpal::SampleRate(spec.sample_rate));
+            return Ok((config.config(), config.sample_format()));
+        }
+
+        let config = self
+            .device
+            .default_output_config()
+            .map_err(|e| output_error(e.to_string()))?;
+        Ok((config.config(), config.sample_format()))
+    }
+
+    /// Builds an output stream that plays the samples in the queue.
+    fn build_stream<T: SizedSample + FromSample<f32>>(
+        &self,
+        config: &StreamConfig,
+    ) -> Result<Stream, SpotsError> {
+        let queue = self.queue.clone();
+        self.device
+            .build_output_stream(
+                config,
+                move |data: &mut [T], _| {
+                    let mut samples = queue.samples.lock().unwrap_or_else(|e| e.into_inner());
+                    for out in data.iter_mut() {
+                        // Output silence if playback can't keep up
+                        *out = T::from_sample(samples.pop_front().unwrap_or(0.0));
+                    }
+                    queue.drained.notify_all();
+                },
+                |e| error!(error = e.to_string(), "Audio output error"),
+                None,
+            )
+            .map_err(|e| SpotsError::AudioOutputError(e.to_string()))
+    }
+}
+
+impl AudioSink for DeviceSink {
+    fn open(&mut self, spec: AudioSpec) -> Result<(), SpotsError> {
+        if self
+            .converter
+            .as_ref()
+            .is_some_and(|converter| converter.input == spec)
+        {
+            return Ok(());
+        }
+
+        let (config, sample_format) = self.output_config(spec)?;
+        let stream = match sample_format {
+            SampleFormat::F32 => self.build_stream::<f32>(&config)?,
+            SampleFormat::I16 => self.build_stream::<i16>(&config)?,
+            SampleFormat::U16 => self.build_stream::<u16>(&config)?,
+            SampleFormat::I32 => self.build_stream::<i32>(&config)?,
+            format => {
+                return Err(SpotsError::AudioOutputError(format!(
+                    "Unsupported sample format: {}",
+                    format
+                )))
+            }
+        };
+        stream
+            .play()
+            .map_err(|e| SpotsError::AudioOutputError(e.to_string()))?;
+
+        let output = AudioSpec {
+            sample_rate: config.sample_rate.0,
+            channels: config.channels,
+        };
+        self.clear();
+        self.stream = Some(stream);
+        self.converter = Some(Converter::new(spec, output));
+        self.capacity = (DEVICE_BUFFER_DURATION.as_secs_f64()
+            * f64::from(output.sample_rate)
+            * f64::from(output.channels)) as usize;
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), SpotsError> {
+        let Some(converter) = &mut self.converter else {
+            return Err(SpotsError::AudioOutputError(String::from(
+                "The output device was written to before it was opened",
+            )));
+        };
+        let mut converted = converter.convert(samples).into_iter().peekable();
+
+        // Wait for the device to make room for the samples
+        let mut queue = self.queue.samples.lock().unwrap_or_else(|e| e.into_inner());
+        while converted.peek().is_some() {
+            while queue.len() >= self.capacity {
+                let (guard, timeout) = self
+                    .queue
+                    .drained
+                    .wait_timeout(queue, DEVICE_WRITE_TIMEOUT)
+                    .unwrap_or_else(|e| e.into_inner());
+                queue = guard;
+                if timeout.timed_out() && queue.len() >= self.capacity {
+                    return Err(SpotsError::AudioOutputError(String::from(
+                        "The output device stopped playing the samples",
+                    )));
+                }
+            }
+            let room = self.capacity - queue.len();
+            queue.extend(converted.by_ref().take(room));
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<(), SpotsError> {
+        match &self.stream {
+            Some(stream) => stream
+                .pause()
+                .map_err(|e| SpotsError::AudioOutputError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn resume(&mut self) -> Result<(), SpotsError> {
+        match &self.stream {
+            Some(stream) => stream
+                .play()
+                .map_err(|e| SpotsError::AudioOutputError(e.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.queue
+            .samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        if let Some(converter) = &mut self.converter {
+            converter.reset();
+        }
+    }
+
+    fn drain(&mut self) -> Result<(), SpotsError> {
+        // Let the device play the rest of the buffered samples
+        let mut queue = self.queue.samples.lock().unwrap_or_else(|e| e.into_inner());
+        while !queue.is_empty() && self.stream.is_some() {
+            let (guard, timeout) = self
+                .queue
+                .drained
+                .wait_timeout(queue, DEVICE_BUFFER_DURATION)
+                .unwrap_or_else(|e| e.into_inner());
+            queue = guard;
+            if timeout.timed_out() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), SpotsError> {
+        self.drain()?;
+        self.stream = None;
+        self.converter = None;
+        Ok(())
+    }
+}
+
+/// Converts interleaved samples between channel counts and sample rates.
+///
+/// Resampling uses linear interpolation, which is only used as a fallback when the output device
+/// doesn't support the audio's sample rate.
+struct Converter {
+    input: AudioSpec,
+    output: AudioSpec,
+
+    /// Position of the next output frame, relative to the `previous` input frame.
+    position: f64,
+
+    /// The last input frame of the previous call to [Converter::convert].
+    previous: Option<Vec<f32>>,
+}
+
+impl Converter {
+    fn new(input: AudioSpec, output: AudioSpec) -> Self {
+        Self {
+            input,
+            output,
+            position: 0.0,
+            previous: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.0;
+        self.previous = None;
+    }
+
+    fn convert(&mut self, samples: &[f32]) -> Vec<f32> {
+        let remapped = remap_channels(samples, self.input.channels, self.output.channels);
+        if self.input.sample_rate == self.output.sample_rate {
+            return remapped;
+        }
+
+        let channels = self.output.channels as usize;
+        let step = f64::from(self.input.sample_rate) / f64::from(self.output.sample_rate);
+        let mut frames: Vec<&[f32]> = Vec::with_capacity(remapped.len() / channels + 1);
+        if let Some(previous) = &self.previous {
+            frames.push(previous);
+        }
+        frames.extend(remapped.chunks_exact(channels));
+
+        let mut resampled = Vec::new();
+        while self.position + 1.0 < frames.len() as f64 {
+            let idx = self.position as usize;
+            let frac = (self.position - idx as f64) as f32;
+            let (a, b) = (frames[idx], frames[idx + 1]);
+            resampled.extend((0..channels).map(|c| a[c] + (b[c] - a[c]) * frac));
+            self.position += step;
+        }
+
+        // Keep the last frame to interpolate from on the next call
+        if let Some(last) = frames.last() {
+            self.position -= (frames.len() - 1) as f64;
+            self.previous = Some(last.to_vec());
+        }
+        resampled
+    }
+}
+
+/// Converts interleaved samples from `from` channels to `to` channels.
+///
+/// Mono is duplicated to every output channel, and extra channels are mixed down to mono
+/// before being duplicated.
+fn remap_channels(samples: &[f32], from: u16, to: u16) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples.to_vec();
+    }
+
+    let (from, to) = (from as usize, to as usize);
+    let mut remapped = Vec::with_capacity(samples.len() / from * to);
+    for frame in samples.chunks_exact(from) {
+        if from >= 2 && to == 2 {
+            // Keep the front left/right channels
+            remapped.extend_from_slice(&frame[..2]);
+        } else {
+            let mono = frame.iter().sum::<f32>() / from as f32;
+            remapped.extend(std::iter::repeat_n(mono, to));
+        }
+    }
+    remapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_samples() {
+        assert_eq!(
+            remap_channels(&[0.5, -0.5], 1, 2),
+            vec![0.5, 0.5, -0.5, -0.5]
+        );
+        assert_eq!(remap_channels(&[0.25, 0.75], 2, 1), vec![0.5]);
+
+        // Doubling the sample rate interpolates a frame between every input frame
+        let mut converter = Converter::new(
+            AudioSpec {
+                sample_rate: 1,
+                channels: 1,
+            },
+            AudioSpec {
+                sample_rate: 2,
+                channels: 1,
+            },
+        );
+        assert_eq!(converter.convert(&[0.0, 1.0]), vec![0.0, 0.5]);
+        assert_eq!(converter.convert(&[0.0]), vec![1.0, 0.5]);
+    }
+}
@@ -0,0 +1,89 @@
+import { invoke } from '@tauri-apps/api/core';
+import { errAsync, okAsync, ResultAsync } from 'neverthrow';
+import { createSignal } from 'solid-js';
+import { ApiError, ApiResponse } from './utils';
+import { SpotsError } from '@/utils/errors';
+
+/** Represents a playlist (user made collection of tracks). */
+export type Playlist = {
+  id: string;
+  user_id?: string;
+  title: string;
+  thumbnail_path: string;
+  created_at: string;
+  updated_at: string;
+  last_played_at?: string;
+};
+
+/**
+ * Changes every time the user's pinned playlists are changed, so the components that show
+ * them can refetch them.
+ */
+const [pinnedPlaylistsVersion, setPinnedPlaylistsVersion] = createSignal(0);
+export { pinnedPlaylistsVersion };
+
+/** Gets the authenticated user's pinned playlists, in the order the user chose. */
+export function getPinnedPlaylists(authToken: string) {
+  return callPlaylistApi('get_pinned_playlists', { authToken });
+}
+
+/** Pins the playlist after the user's other pinned playlists. */
+export function pinPlaylist(authToken: string, playlistId: string) {
+  return callPlaylistApi('pin_playlist', { authToken, playlistId }).andTee(
+    notifyPinnedPlaylistsChanged
+  );
+}
+
+/** Unpins the playlist. */
+export function unpinPlaylist(authToken: string, playlistId: string) {
+  return callPlaylistApi('unpin_playlist', { authToken, playlistId }).andTee(
+    notifyPinnedPlaylistsChanged
+  );
+}
+
+/** Moves the pinned playlist to the `index` in the user's pinned playlists. */
+export function movePinnedPlaylist(
+  authToken: string,
+  playlistId: string,
+  index: number
+) {
+  return callPlaylistApi('move_pinned_playlist', {
+    authToken,
+    playlistId,
+    index,
+  }).andTee(notifyPinnedPlaylistsChanged);
+}
+
+/** Lets the components showing the pinned playlists know that they changed. */
+function notifyPinnedPlaylistsChanged() {
+  setPinnedPlaylistsVersion((version) => version + 1);
+}
+
+/** Calls a rust command that responds with the user's pinned playlists. */
+function callPlaylistApi(
+  command: string,
+  args: Record<string, unknown>
+): ResultAsync<Playlist[], SpotsError | ApiError> {
+  // Calls the rust command
+  const callBackend = ResultAsync.fromPromise(
+    invoke<ApiResponse<Playlist[]>>(command, args),
+    (err) => err as ApiError
+  );
+
+  // Gets the playlists from the response
+  const extractResponse = (
+    res: ApiResponse<Playlist[]>
+  ): ResultAsync<Playlist[], SpotsError | ApiError> => {
+    if (res.status !== 'Success') {
+      return errAsync({
+        kind: 'ApiRequestFailed',
+        message: 'The API responded with a `Failure`',
+        info: res.value,
+        _tag: '_SpotsError',
+      });
+    }
+    return okAsync(res.value);
+  };
+
+  return callBackend.andThen(extractResponse);
+}
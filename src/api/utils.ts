@@ -6,7 +6,8 @@ export type ApiResponseStatus =
   | 'Failure'
   | 'Started'
   | 'Pending'
-  | 'Completed';
+  | 'Completed'
+  | 'Cancelled';
 
 /** An API response. */
 export type ApiResponse<T> = {
@@ -1,7 +1,17 @@
-import { getAuthUserIdResource, useStore } from '@/utils/tauriStore';
+import {
+  getPinnedPlaylists,
+  pinnedPlaylistsVersion,
+  Playlist,
+} from '@/api/playlists';
+import { Logger } from '@/utils/logger';
+import {
+  getAuthTokenResource,
+  getAuthUserIdResource,
+  useStore,
+} from '@/utils/tauriStore';
 import { Shimmer } from '@shimmer-from-structure/solid';
 import { useLocation, useNavigate } from '@solidjs/router';
-import { JSX } from 'solid-js';
+import { createResource, For, JSX } from 'solid-js';
 
 /** The navbar component. */
 export function Navbar() {
@@ -9,8 +19,30 @@ export function Navbar() {
   const navigate = useNavigate();
   const location = useLocation();
   const [authUserId] = getAuthUserIdResource(storeCtx);
+  const [authToken] = getAuthTokenResource(storeCtx);
   const styles = navbarStyles();
 
+  /**
+   * The user's pinned playlists, in the order the user chose.
+   *
+   * They are fetched again whenever a playlist is pinned, unpinned or moved.
+   */
+  const [pinnedPlaylists] = createResource(
+    () => {
+      const token = authToken();
+      return token ? { token, version: pinnedPlaylistsVersion() } : undefined;
+    },
+    async ({ token }) => {
+      return await getPinnedPlaylists(token).match(
+        (playlists) => playlists,
+        (err) => {
+          Logger.error(`${err.kind}: ${err.message}`, err);
+          return [] as Playlist[];
+        }
+      );
+    }
+  );
+
   //  TODO: Add actual nav icons
   //
   /** The navigation bar content. */
@@ -62,6 +94,29 @@ export function Navbar() {
             )}
           </>
         ))}
+        <For each={pinnedPlaylists()}>
+          {(playlist) => {
+            const path = () =>
+              `/user/${authUserId()}/library?playlist=${playlist.id}`;
+            return (
+              <div style={styles.buttonWrapperStyle}>
+                <button
+                  style={styles.navButtonStyle(
+                    location.pathname + location.search === path()
+                  )}
+                  title={playlist.title}
+                  onClick={(e) => {
+                    e.stopPropagation();
+                    navigate(path());
+                  }}
+                >
+                  <span>📌</span>
+                  <span style={styles.pinnedLabelStyle}>{playlist.title}</span>
+                </button>
+              </div>
+            );
+          }}
+        </For>
       </nav>
     </Shimmer>
   );
@@ -122,10 +177,20 @@ function navbarStyles() {
     'font-weight': '500',
   };
 
+  /** Style for the labels of pinned playlists (titles can be long). */
+  const pinnedLabelStyle: JSX.CSSProperties = {
+    ...labelStyle,
+    'max-width': '6rem',
+    overflow: 'hidden',
+    'text-overflow': 'ellipsis',
+    'white-space': 'nowrap',
+  };
+
   return {
     containerStyle,
     buttonWrapperStyle,
     navButtonStyle,
     labelStyle,
+    pinnedLabelStyle,
   };
 }